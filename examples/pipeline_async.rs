@@ -0,0 +1,33 @@
+use webscrape::{PipelineRunner, ScraperBuilder};
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+
+    let pipeline_file = std::env::args().nth(1).expect("no pipeline source given");
+
+    // A single scraper, awaited step by step.
+    let scraper = ScraperBuilder::default().set_headless(true).try_build_async().await?;
+
+    scraper.navigate_to("https://stackoverflow.com/questions?tab=newest&page=1&pagesize=50").await?;
+    scraper.find_elements_by_xpath("questions", "//a[contains(@href, '/questions/') and contains(@class, 's-link')]").await?;
+
+    let res = scraper.collect().await?;
+    println!("questions: {}", res.elements["questions"].len());
+
+    // A whole pipeline over many URLs, at most 4 at a time.
+    let sites = (1..=8)
+        .map(|i| format!("https://stackoverflow.com/questions?tab=newest&page={}&pagesize=50", i))
+        .collect::<Vec<String>>();
+
+    let mut runner = PipelineRunner::from_file(&pipeline_file)?;
+    runner.set_concurrency(4);
+
+    for (url, res) in sites.iter().zip(runner.run_async(&sites).await) {
+        match res {
+            Ok(res) => println!("{}: {} targets", url, res.elements.len()),
+            Err(e) => println!("{}: {}", url, e)
+        }
+    }
+
+    Ok(())
+}
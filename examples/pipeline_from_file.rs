@@ -1,4 +1,4 @@
-use webscrape::{ScraperBuilder, ScrapingPipeline};
+use webscrape::{ScrapingPipeline};
 
 
 pub fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -7,7 +7,7 @@ pub fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     println!("--------------------");
 
-    let mut pipeline = ScrapingPipeline::from_file(&pipeline_file);
+    let mut pipeline = ScrapingPipeline::from_file(&pipeline_file)?;
     
     print!("{}", pipeline);
 
@@ -16,9 +16,9 @@ pub fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
     let mut line = String::new();
     print!("Run : ");
-    let b1 = std::io::stdin().read_line(&mut line).unwrap();
+    std::io::stdin().read_line(&mut line).unwrap();
 
-    let res = pipeline.run(&"DEFAULT".to_string());
+    let res = pipeline.run("DEFAULT")?;
 
     println!("num: {}", &res.elements.len());
 
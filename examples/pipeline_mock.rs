@@ -0,0 +1,44 @@
+use webscrape::{MockBackend, MockStep, PipelineConfig, ScrapingPipeline};
+
+const PAGE_1: &str = r#"
+<html><body>
+    <a class="s-link" href="/questions/1">First question</a>
+    <a class="s-link" href="/questions/2">Second question</a>
+    <a title="Go to page 2" href="?page=2">2</a>
+</body></html>"#;
+
+const PAGE_2: &str = r#"
+<html><body>
+    <a class="s-link" href="/questions/3">Third question</a>
+</body></html>"#;
+
+/// It runs a pipeline YAML against in-memory pages, the same way a cargo test would.
+pub fn main() -> Result<(), Box<dyn std::error::Error>> {
+
+    let pipeline_file = std::env::args().nth(1).unwrap_or("examples/config/stack_questions2.yaml".to_string());
+    let config = PipelineConfig::from_file(&pipeline_file)?;
+
+    let mut mock = MockBackend::new();
+    mock.add_fixture("page1", PAGE_1)
+        .add_fixture("page2", PAGE_2)
+        .on_click("//a[contains(@title, 'Go to page 2')]", "page2");
+
+    let mut pipeline = ScrapingPipeline::with_backend(config, Box::new(mock.clone()));
+
+    let res = pipeline.run("page1")?;
+
+    assert_eq!(res.elements["element_1"].len(), 2);
+    assert_eq!(res.elements["element_2"].len(), 1);
+    assert_eq!(res.elements["element_2"][0].text, "Third question");
+    assert_eq!(mock.current_url(), Some("page2".to_string()));
+
+    for step in mock.steps() {
+        println!("{:?}", step);
+    }
+
+    assert!(matches!(mock.steps().last(), Some(MockStep::Save { .. })));
+
+    println!("{}", mock.saved()[0]);
+
+    Ok(())
+}
@@ -1,6 +1,5 @@
-use webscrape::{ScraperBuilder, ScrapingPipeline, ScrapingResult, PipelineRunner};
-use rayon::prelude::*;
-use std::time::Instant;
+use webscrape::{PipelineRunner, RetryPolicy};
+use std::time::{Duration, Instant};
 
 fn main() {
 
@@ -8,14 +7,12 @@ fn main() {
 
     let pipeline_file = std::env::args().nth(1).expect("no pipeline source given");
 
-    let p1 = pipeline_file.clone();
-
     let mut sites: Vec<String> = vec![];
     
     let paths = std::fs::read_dir("/Users/maurosciancalepore/Downloads/pskm/html").unwrap();
 
     for path in paths {
-        sites.push( format!("file://{}", path.unwrap().path().display().to_string()));
+        sites.push( format!("file://{}", path.unwrap().path().display()));
         //println!("Name: {}", path.unwrap().path().display())
     }
 
@@ -25,35 +22,40 @@ fn main() {
 
     let t0 = Instant::now();
 
-    println!("Running...");
-    let scraping_results = PipelineRunner::go(p1, &sites);
+    // Four browsers are launched at most, each one reused across pages, with a fresh tab every 50 pages.
+    let mut runner = PipelineRunner::from_file(&pipeline_file).expect("invalid pipeline");
 
-    let t1 = t0.elapsed();
+    // Pages failing to load are retried twice, after 2s then 4s.
+    let mut retry_policy = RetryPolicy::default();
+    retry_policy.set_retries(2).set_initial_backoff(Duration::from_secs(2));
 
-    println!("[Parallel] Done in {:.2?}.", t1);
+    runner.set_concurrency(4).set_recycle_after(50).set_retry_policy(retry_policy);
 
-    return;
-    let t0 = Instant::now();
-    let s = sites.iter().map(move |s| {
+    // If the run gets interrupted, running it again skips the pages already done.
+    runner.set_job_id("pskm").expect("unreadable checkpoint");
 
-        let mut pipeline = ScrapingPipeline::from_file(&pipeline_file.clone());
-        let res = pipeline.run(&s);
-        res
-
-    }).collect::<Vec<ScrapingResult>>();
+    println!("Running...");
+    let report = runner.run_report(&sites);
 
     let t1 = t0.elapsed();
 
-    println!("[Serial] Done in {:.2?}.", t1);
+    println!("[Parallel] Done in {:.2?}.", t1);
+
+    for url_report in &report.urls {
 
-    return; 
-    for res in scraping_results {
+        let res = match &url_report.result {
+            Ok(res) => res,
+            Err(e) => {
+                println!("FAILED: {}", e);
+                continue;
+            }
+        };
 
         println!("RESULT");
-        for (name, els) in res.elements {
+        for (name, els) in &res.elements {
         
             println!("TARGET: {}", name);
-            for el in &els {
+            for el in els {
                 println!("{:?}", el.text);
     
                 for (k, v) in &el.attrs {
@@ -65,63 +67,9 @@ fn main() {
 
     }
     
-    /* let handle = tokio::spawn(async move {
-        
-            let mut builder = ScraperBuilder::default();
-            let scraper = builder.set_headless(false).set_default_timeout(5).build();
-            
-            let mut pipeline = ScrapingPipeline::from_file(&pipeline_file.clone(), scraper);
-            let res = pipeline.run(&"DEFAULT".to_string());
-
-            println!("YO BY 1");
-            println!("num: {}", &res.elements.len());
-        
-            for (name, els) in res.elements {
-        
-                println!("TARGET: {}", name);
-                for el in &els {
-                    println!("{:?}", el.text);
-        
-                    for (k, v) in &el.attrs {
-                        println!("{} - {}", k, v);
-                    }
-                    println!("--------------------");
-                }
-            }
-
-    });
-
-    let handle2 = tokio::spawn(async move {
-        
-        let mut builder = ScraperBuilder::default();
-        let scraper = builder.set_headless(false).set_default_timeout(5).build();
-        
-        let mut pipeline = ScrapingPipeline::from_file(&p1, scraper);
-        let res = pipeline.run(&"DEFAULT".to_string());
-
-        println!("YO BY 2");
-        println!("num: {}", &res.elements.len());
-    
-        for (name, els) in res.elements {
-    
-            println!("TARGET: {}", name);
-            for el in &els {
-                println!("{:?}", el.text);
-    
-                for (k, v) in &el.attrs {
-                    println!("{} - {}", k, v);
-                }
-                println!("--------------------");
-            }
-        }
-
-});
-
-    // Do some other work
-
-    let out = handle.await.unwrap();
+    println!("{}", report);
 
-    handle2.await.unwrap(); */
+    // See pipeline_async.rs to run the same pipeline from a tokio runtime.
     
     
 }
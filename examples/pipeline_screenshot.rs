@@ -1,6 +1,6 @@
 use std::path::Path;
 
-use webscrape::{ScraperBuilder, ScrapingPipeline, save_screenshot};
+use webscrape::{ScrapingPipeline, save_screenshot};
 
 
 pub fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -9,7 +9,7 @@ pub fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     println!("--------------------");
 
-    let mut pipeline = ScrapingPipeline::from_file(&pipeline_file);
+    let mut pipeline = ScrapingPipeline::from_file(&pipeline_file)?;
     
     print!("{}", pipeline);
 
@@ -17,9 +17,9 @@ pub fn main() -> Result<(), Box<dyn std::error::Error>> {
     
     let mut line = String::new();
     print!("Run : ");
-    let b1 = std::io::stdin().read_line(&mut line).unwrap();
+    std::io::stdin().read_line(&mut line).unwrap();
 
-    let res = pipeline.run(&"DEFAULT".to_string());
+    let res = pipeline.run("DEFAULT")?;
 
     println!("num: {}", &res.screenshots.len());
 
@@ -28,7 +28,7 @@ pub fn main() -> Result<(), Box<dyn std::error::Error>> {
 
         println!("SCREENSHOT: {}", name);
         let s_name = format!("{}.png", &name);
-        save_screenshot(&img_data, &Path::new(&s_name)).unwrap();
+        save_screenshot(&img_data, Path::new(&s_name)).unwrap();
 
     }
     Ok(())
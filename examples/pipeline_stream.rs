@@ -0,0 +1,24 @@
+use webscrape::PipelineRunner;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+
+    let pipeline_file = std::env::args().nth(1).expect("no pipeline source given");
+
+    let sites = (1..=20)
+        .map(|i| format!("https://stackoverflow.com/questions?tab=newest&page={}&pagesize=50", i))
+        .collect::<Vec<String>>();
+
+    // At most 4 pages are scraped at a time, and at most 8 results wait to be handled.
+    let mut runner = PipelineRunner::from_file(&pipeline_file)?;
+    runner.set_concurrency(4).set_stream_buffer(8);
+
+    // Each result is handled, then dropped, as soon as its page is done.
+    for (url, res) in runner.stream(sites) {
+        match res {
+            Ok(res) => println!("{}: {} targets, {} screenshots", url, res.elements.len(), res.screenshots.len()),
+            Err(e) => println!("{}: {}", url, e)
+        }
+    }
+
+    Ok(())
+}
@@ -37,7 +37,7 @@ pub fn main() -> Result<(), Box<dyn std::error::Error>> {
         .find_elements_by_xpath("views", "//div[contains(@title, 'Viewed')]")
         .collect();
 
-    for (name, els) in result.elements {
+    for els in result.elements.into_values() {
         for el in &els {
             println!("{:?}", el.text);
 
@@ -0,0 +1,52 @@
+use webscrape::{Scrape, SimpleScraperBuilder};
+
+#[derive(Scrape)]
+struct Owner {
+    #[css("div.user-details a")]
+    name: String,
+
+    #[css("span.reputation-score")]
+    reputation: Option<String>,
+}
+
+#[derive(Scrape)]
+#[css("div.s-post-summary")]
+struct Question {
+    #[css("h3 a")]
+    title: String,
+
+    #[css("h3 a")]
+    #[attr("href")]
+    link: String,
+
+    #[css("span.s-post-summary--stats-item-number")]
+    votes: Option<i64>,
+
+    #[css("a.post-tag")]
+    tags: Vec<String>,
+
+    #[css("div.s-user-card")]
+    owner: Option<Owner>,
+}
+
+pub fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut builder = SimpleScraperBuilder::default();
+
+    let mut scraper = builder.set_default_timeout(5).try_build()?;
+
+    let questions: Vec<Question> = scraper
+        .try_navigate_to("https://stackoverflow.com/questions/tagged/rust")?
+        .try_scrape()?;
+
+    for question in &questions {
+        println!("{} ({} votes) - {}", question.title, question.votes.unwrap_or(0), question.link);
+        println!("tags: {}", question.tags.join(", "));
+
+        if let Some(owner) = &question.owner {
+            println!("asked by {} ({})", owner.name, owner.reputation.as_deref().unwrap_or("?"));
+        }
+        println!("--------------------");
+    }
+
+    Ok(())
+}
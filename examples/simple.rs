@@ -3,18 +3,18 @@ use webscrape::ScraperBuilder;
 pub fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut builder = ScraperBuilder::default();
 
-    let mut scraper = builder.set_headless(true).set_default_timeout(5).build();
+    let mut scraper = builder.set_headless(true).set_default_timeout(5).try_build()?;
 
     println!("--------------------");
 
     let result = scraper
-        .navigate_to("https://stackoverflow.com/questions/58787864/changing-primary-palette-color-when-using-kivymd-has-no-effect-on-buttons")
-        .find_elements_by_xpath("last_activity", "//a[contains(@href, 'lastact')]")
-        .find_elements_by_xpath("question", "//h1/a[contains(@class, 'question-hyperlink')]")
-        .find_elements_by_xpath("views", "//div[contains(@title, 'Viewed')]")
+        .try_navigate_to("https://stackoverflow.com/questions/58787864/changing-primary-palette-color-when-using-kivymd-has-no-effect-on-buttons")?
+        .try_find_elements_by_xpath("last_activity", "//a[contains(@href, 'lastact')]")?
+        .try_find_elements_by_xpath("question", "//h1/a[contains(@class, 'question-hyperlink')]")?
+        .try_find_elements_by_xpath("views", "//div[contains(@title, 'Viewed')]")?
         .collect();
 
-    for (name, els) in result.elements {
+    for els in result.elements.into_values() {
         for el in &els {
             println!("{:?}", el.text);
 
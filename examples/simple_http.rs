@@ -0,0 +1,28 @@
+use webscrape::SimpleScraperBuilder;
+
+pub fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut builder = SimpleScraperBuilder::default();
+
+    let mut scraper = builder.set_default_timeout(5).try_build()?;
+
+    println!("--------------------");
+
+    let result = scraper
+        .try_navigate_to("https://stackoverflow.com/questions/58787864/changing-primary-palette-color-when-using-kivymd-has-no-effect-on-buttons")?
+        .try_find_elements_by_xpath("question", "//h1/a[contains(@class, 'question-hyperlink')]")?
+        .try_find_elements_by_css("tags", "a.post-tag")?
+        .collect();
+
+    for els in result.elements.into_values() {
+        for el in &els {
+            println!("{:?}", el.text);
+
+            for (k, v) in &el.attrs {
+                println!("{} - {}", k, v);
+            }
+            println!("--------------------");
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,190 @@
+use std::{io::BufRead, process::ExitCode};
+
+use clap::{Parser, Subcommand};
+use webscrape::{Error, PipelineConfig, PipelineRunner, Result, RetryPolicy};
+
+/// Every URL was scraped (or skipped by robots.txt).
+const EXIT_OK: u8 = 0;
+
+/// The run went through, but some URLs failed.
+const EXIT_FAILURES: u8 = 1;
+
+/// Nothing was run: the pipeline, the URL list or the arguments are invalid.
+const EXIT_INVALID: u8 = 2;
+
+/// Run, inspect and validate webscrape pipelines.
+///
+/// Exit codes: 0 on success, 1 if some URLs failed, 2 if the pipeline or the arguments are invalid.
+#[derive(Parser)]
+#[command(name = "webscrape", version, about, long_about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command
+}
+
+#[derive(Subcommand)]
+enum Command {
+
+    /// Run a pipeline on its own URL, or on the given ones, and print a report.
+    Run {
+        pipeline: String,
+
+        /// A URL to scrape instead of the pipeline one. Repeat it for several URLs.
+        #[arg(long = "url", value_name = "U")]
+        urls: Vec<String>,
+
+        /// A file listing the URLs to scrape, one per line, or - for stdin. Blank lines and # comments are ignored.
+        #[arg(long = "urls", value_name = "FILE")]
+        urls_file: Option<String>,
+
+        /// Where to save results, instead of the pipeline out_dir.
+        #[arg(long, value_name = "DIR")]
+        out: Option<String>,
+
+        /// How many URLs are scraped at the same time. Defaults to the number of CPUs.
+        #[arg(long, value_name = "N")]
+        concurrency: Option<usize>,
+
+        /// How many times a URL failing to load is retried, with exponential backoff.
+        #[arg(long, value_name = "N", default_value_t = 0)]
+        retries: usize,
+
+        /// Make the run resumable: running it again with the same job id skips the URLs already done.
+        #[arg(long, value_name = "ID")]
+        job: Option<String>
+    },
+
+    /// Print the targets, actions and steps of a pipeline.
+    Show {
+        pipeline: String
+    },
+
+    /// Check a pipeline without running it.
+    Validate {
+        pipeline: String
+    }
+}
+
+fn main() -> ExitCode {
+
+    // Warnings, e.g. deprecated pipeline formats, show up unless RUST_LOG says otherwise.
+    env_logger::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+
+    let code = match Cli::parse().command {
+        Command::Run { pipeline, urls, urls_file, out, concurrency, retries, job } =>
+            run(&pipeline, urls, urls_file, out, concurrency, retries, job),
+        Command::Show { pipeline } => show(&pipeline),
+        Command::Validate { pipeline } => validate(&pipeline)
+    };
+
+    ExitCode::from(code)
+}
+
+fn run(
+    pipeline: &str,
+    mut urls: Vec<String>,
+    urls_file: Option<String>,
+    out: Option<String>,
+    concurrency: Option<usize>,
+    retries: usize,
+    job: Option<String>
+) -> u8 {
+
+    let mut pipeline_config = match load(pipeline) {
+        Ok(pipeline_config) => pipeline_config,
+        Err(code) => return code
+    };
+
+    if let Some(out) = out {
+        pipeline_config.pipeline.out_dir = out;
+    }
+
+    if let Some(urls_file) = urls_file {
+        match read_urls(&urls_file) {
+            Ok(listed) => urls.extend(listed),
+            Err(e) => {
+                eprintln!("Couldn't read the URLs of {}: {}", urls_file, e);
+                return EXIT_INVALID;
+            }
+        }
+    }
+
+    if urls.is_empty() {
+        urls.push(pipeline_config.pipeline.url.clone());
+    }
+
+    let mut runner = PipelineRunner::new(pipeline_config);
+
+    if let Some(concurrency) = concurrency {
+        runner.set_concurrency(concurrency);
+    }
+
+    let mut retry_policy = RetryPolicy::default();
+    retry_policy.set_retries(retries);
+    runner.set_retry_policy(retry_policy);
+
+    if let Some(job) = job {
+        if let Err(e) = runner.set_job_id(&job) {
+            eprintln!("{}", e);
+            return EXIT_INVALID;
+        }
+    }
+
+    let report = runner.run_report(&urls);
+    runner.shutdown();
+
+    println!("{}", report);
+
+    match report.is_success() {
+        true => EXIT_OK,
+        false => EXIT_FAILURES
+    }
+}
+
+fn show(pipeline: &str) -> u8 {
+
+    match load(pipeline) {
+        Ok(pipeline_config) => {
+            print!("{}", pipeline_config);
+            EXIT_OK
+        },
+        Err(code) => code
+    }
+}
+
+fn validate(pipeline: &str) -> u8 {
+
+    match load(pipeline) {
+        Ok(_) => {
+            println!("{}: OK", pipeline);
+            EXIT_OK
+        },
+        Err(code) => code
+    }
+}
+
+/// It reads and validates the pipeline, printing what's wrong with it, if anything.
+fn load(pipeline: &str) -> std::result::Result<PipelineConfig, u8> {
+
+    PipelineConfig::from_file(pipeline).map_err(|e| {
+        match e {
+            Error::Validation(diagnostics) => diagnostics.iter().for_each(|d| eprintln!("{}", d)),
+            e => eprintln!("{}: {}", pipeline, e)
+        }
+        EXIT_INVALID
+    })
+}
+
+fn read_urls(urls_file: &str) -> Result<Vec<String>> {
+
+    let lines = match urls_file {
+        "-" => std::io::stdin().lock().lines().collect::<std::io::Result<Vec<String>>>()?,
+        path => std::fs::read_to_string(path).map_err(Error::Io)?.lines().map(str::to_string).collect()
+    };
+
+    Ok(lines.iter()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
@@ -0,0 +1,120 @@
+use std::fmt::Display;
+
+/// Every fallible operation of this crate reports one of these errors. <br>
+/// Match on the variant to decide whether a failure is worth a retry, a skip or an abort.
+#[derive(Debug)]
+pub enum Error {
+    /// The browser process (or its initial tab) couldn't be started.
+    Launch(String),
+
+    /// The page couldn't be loaded.
+    Navigation { url: String, reason: String },
+
+    /// The page didn't finish loading within the configured timeout.
+    Timeout { url: String },
+
+    /// No page has been loaded yet: navigate_to(url) must be called first.
+    NotNavigated,
+
+    /// The selector is malformed or of the wrong kind (CSS vs XPath).
+    InvalidSelector { selector: String, reason: String },
+
+    /// The selector is valid but nothing matched it before the timeout.
+    ElementNotFound { name: String, selector: String },
+
+    /// The server answered 429 or 503, possibly telling how long to wait before the next request.
+    RateLimited { url: String, status: u16, retry_after: Option<std::time::Duration> },
+
+    /// robots.txt doesn't let us fetch the URL, so it was skipped.
+    Disallowed { url: String },
+
+    /// A proxy is malformed or the request through it failed.
+    Proxy(String),
+
+    /// Any other failure reported by the browser while talking to the page.
+    Browser(String),
+
+    /// The backend can't perform the operation, e.g. a CLICK without a browser.
+    Unsupported { backend: String, operation: String },
+
+    /// The pipeline configuration is malformed or inconsistent.
+    Config(String),
+
+    /// The pipeline file has problems, all listed with their position in the file.
+    Validation(Vec<crate::pipeline::Diagnostic>),
+
+    Io(std::io::Error),
+
+    Serialization(String),
+
+    /// A scraped value doesn't fit the struct field it's scraped into, e.g. abc for a u32, or nothing matched a required one.
+    Parse { field: String, reason: String },
+
+    /// The scraping of a URL panicked. The other URLs of the run aren't affected.
+    Panicked(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+
+    /// Whether the failure may be transient, i.e. the same URL may succeed on a new attempt.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, Error::Navigation { .. } | Error::Timeout { .. } | Error::RateLimited { .. } | Error::Proxy(_))
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Launch(reason) => write!(f, "Couldn't launch the browser: {}", reason),
+            Error::Navigation { url, reason } => write!(f, "Couldn't navigate to {}: {}", url, reason),
+            Error::Timeout { url } => write!(f, "Timed out while loading {}", url),
+            Error::NotNavigated => write!(f, "No page loaded. Didn't you call navigate_to(url) ?"),
+            Error::InvalidSelector { selector, reason } => write!(f, "Invalid selector {}: {}", selector, reason),
+            Error::ElementNotFound { name, selector } => write!(f, "Couldn't find element {} ({})", name, selector),
+            Error::RateLimited { url, status, retry_after: Some(d) } => write!(f, "Rate limited by {} (HTTP {}), retry after {:.0?}", url, status, d),
+            Error::RateLimited { url, status, retry_after: None } => write!(f, "Rate limited by {} (HTTP {})", url, status),
+            Error::Disallowed { url } => write!(f, "{} is disallowed by robots.txt", url),
+            Error::Proxy(reason) => write!(f, "Proxy failure: {}", reason),
+            Error::Browser(reason) => write!(f, "Browser failure: {}", reason),
+            Error::Unsupported { backend, operation } => write!(f, "{} is not supported by the {} backend", operation, backend),
+            Error::Config(reason) => write!(f, "Invalid pipeline configuration: {}", reason),
+            Error::Validation(diagnostics) => {
+                write!(f, "Invalid pipeline configuration:")?;
+                diagnostics.iter().try_for_each(|d| write!(f, "\n  {}", d))
+            },
+            Error::Io(err) => write!(f, "IO failure: {}", err),
+            Error::Serialization(reason) => write!(f, "Serialization failure: {}", reason),
+            Error::Parse { field, reason } => write!(f, "Couldn't parse {}: {}", field, reason),
+            Error::Panicked(reason) => write!(f, "Scraping panicked: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<serde_yaml::Error> for Error {
+    fn from(err: serde_yaml::Error) -> Self {
+        Error::Config(err.to_string())
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Serialization(err.to_string())
+    }
+}
@@ -1,14 +1,54 @@
 mod scraping;
 mod pipeline;
 mod utils;
+mod error;
 
 pub mod proxy;
-pub use crate::scraping::chrome::{ScraperBuilder};
+pub use crate::scraping::chrome::{ScraperBuilder, Scraper};
 
-pub use crate::pipeline::{ScrapingPipeline, PipelineRunner};
+pub use crate::scraping::async_chrome::AsyncScraper;
+
+pub use crate::error::{Error, Result};
+
+pub use crate::pipeline::{ScrapingPipeline, PipelineRunner, ResultStream, PipelineConfig, BackendKind, BackendPool, PoolGuard, RetryPolicy, RunReport, UrlReport, Diagnostic, PIPELINE_VERSION};
 
 pub use crate::utils::{img_to_base64, save_screenshot};
 
-pub use crate::scraping::simple::SimpleScraper;
+pub use crate::scraping::simple::{SimpleScraper, SimpleScraperBuilder};
+
+pub use crate::scraping::mock::{MockBackend, MockStep};
+
+pub use crate::scraping::{Backend, ScrapingResult, DOMElement, ScreenshotFormat};
+
+pub use crate::scraping::html::HtmlDocument;
+
+pub use crate::scraping::rate_limit::{RateLimit, HostLimiter, HostPermit};
+
+pub use crate::scraping::robots::{RobotsConfig, RobotsTxt};
+
+pub use crate::scraping::selector::{Selector, SelectorKind};
+
+pub use crate::scraping::record::Field;
+
+pub use crate::scraping::extract::Extract;
+
+pub use crate::scraping::links::Links;
+
+pub use crate::scraping::table::Table;
+
+pub use crate::scraping::metadata::{PageMetadata, MetadataItem};
+
+pub use crate::scraping::content::Content;
+
+pub use crate::scraping::scrape::{Scrape, ScrapeField};
+
+pub use webscrape_derive::Scrape;
+
+pub use crate::scraping::transform::{Transform, TransformFailure, Pattern};
 
-pub use crate::scraping::chrome::ScrapingResult;
\ No newline at end of file
+/// What the code generated by #[derive(Scrape)] relies on. Not part of the public API.
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::scraping::scrape::{required, optional, many};
+    pub use serde_json;
+}
@@ -0,0 +1,156 @@
+use std::{collections::HashMap, fs::{File, OpenOptions}, io::Write, path::{Path, PathBuf}};
+
+use log::{info, warn};
+use serde::{Serialize, Deserialize};
+
+use crate::error::{Error, Result};
+
+#[derive(Serialize, Deserialize)]
+struct CompletedUrl {
+    url: String,
+    saved: Vec<PathBuf>
+}
+
+/// Checkpoint is the progress of a job: which URLs are done, and where their results were saved. <br>
+/// It lives in {dir}/{job_id}.jsonl, one line per completed URL, appended as soon as the URL is done,
+/// so an interrupted run loses at most the URLs that were in flight.
+pub(crate) struct Checkpoint {
+    path: PathBuf,
+    completed: HashMap<String, Vec<PathBuf>>,
+    file: File
+}
+
+impl Checkpoint {
+
+    /// It loads the progress of job_id, or starts it if it's a new job.
+    pub(crate) fn open<P: AsRef<Path>>(dir: P, job_id: &str) -> Result<Checkpoint> {
+
+        let valid_id = !job_id.is_empty()
+            && job_id.chars().all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c))
+            && !job_id.starts_with('.');
+
+        if !valid_id {
+            return Err(Error::Config(format!("Invalid job id {:?}: use letters, digits, '-', '_' and '.'", job_id)));
+        }
+
+        std::fs::create_dir_all(&dir)?;
+
+        let path = dir.as_ref().join(format!("{}.jsonl", job_id));
+        let mut completed = HashMap::default();
+        let mut truncated = false;
+
+        if path.exists() {
+
+            let content = std::fs::read(&path)?;
+
+            for line in String::from_utf8_lossy(&content).lines() {
+
+                // The last line may be cut short if the process was killed while writing it.
+                match serde_json::from_str::<CompletedUrl>(line) {
+                    Ok(entry) => { completed.insert(entry.url, entry.saved); },
+                    Err(e) => warn!("[CHECKPOINT] Skipping a malformed line of {}: {}", path.display(), e)
+                }
+            }
+
+            truncated = content.last().is_some_and(|b| *b != b'\n');
+
+            info!("[CHECKPOINT] Resuming job {}: {} URLs already done", job_id, completed.len());
+        }
+
+        let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+
+        // Otherwise the next URL done would be glued to the cut line, and lost with it.
+        if truncated {
+            file.write_all(b"\n")?;
+        }
+
+        Ok(Checkpoint { path, completed, file })
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Where the results of url were saved, if it's already done.
+    pub(crate) fn completed(&self, url: &str) -> Option<&Vec<PathBuf>> {
+        self.completed.get(url)
+    }
+
+    /// It records url as done, on disk right away.
+    pub(crate) fn complete(&mut self, url: &str, saved: &[PathBuf]) -> Result<()> {
+
+        let entry = CompletedUrl { url: url.to_string(), saved: saved.to_vec() };
+
+        // A single write per line, so concurrent runs of the same job can't interleave halves of lines.
+        let mut line = serde_json::to_string(&entry)?;
+        line.push('\n');
+
+        self.file.write_all(line.as_bytes())?;
+        self.file.flush()?;
+
+        self.completed.insert(entry.url, entry.saved);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn dir(name: &str) -> PathBuf {
+
+        let dir = std::env::temp_dir().join(format!("webscrape_checkpoint_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        dir
+    }
+
+    #[test]
+    fn resumes_completed_urls() {
+
+        let dir = dir("resume");
+
+        let mut checkpoint = Checkpoint::open(&dir, "job").unwrap();
+        checkpoint.complete("https://a.com", &[PathBuf::from("out/a.json")]).unwrap();
+        checkpoint.complete("https://b.com", &[]).unwrap();
+        drop(checkpoint);
+
+        let checkpoint = Checkpoint::open(&dir, "job").unwrap();
+
+        assert_eq!(checkpoint.completed("https://a.com"), Some(&vec![PathBuf::from("out/a.json")]));
+        assert_eq!(checkpoint.completed("https://b.com"), Some(&vec![]));
+        assert_eq!(checkpoint.completed("https://c.com"), None);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn recovers_from_a_truncated_line() {
+
+        let dir = dir("truncated");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("job.jsonl"), "{\"url\":\"https://a.com\",\"saved\":[]}\n{\"url\":\"https://b.c").unwrap();
+
+        let mut checkpoint = Checkpoint::open(&dir, "job").unwrap();
+        assert!(checkpoint.completed("https://a.com").is_some());
+        assert!(checkpoint.completed("https://b.com").is_none());
+
+        checkpoint.complete("https://c.com", &[]).unwrap();
+        drop(checkpoint);
+
+        let checkpoint = Checkpoint::open(&dir, "job").unwrap();
+        assert!(checkpoint.completed("https://a.com").is_some());
+        assert!(checkpoint.completed("https://c.com").is_some());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_unsafe_job_ids() {
+
+        assert!(Checkpoint::open(dir("ids"), "../job").is_err());
+        assert!(Checkpoint::open(dir("ids"), ".job").is_err());
+    }
+}
@@ -0,0 +1,669 @@
+#![allow(unused_must_use)]
+
+use std::{fmt::Display, collections::{BTreeMap, HashMap}, path::PathBuf, sync::Arc};
+
+use log::warn;
+use serde::{Serialize, Deserialize};
+use tabled::{Tabled, Panel, Modify, object::{Rows}, Alignment, style::HorizontalLine, Style, Disable};
+
+mod runner;
+mod pool;
+mod retry;
+mod report;
+mod checkpoint;
+mod validate;
+
+pub use runner::{PipelineRunner, ResultStream};
+pub use pool::{BackendPool, PoolGuard};
+pub use retry::RetryPolicy;
+pub use report::{RunReport, UrlReport};
+pub use validate::Diagnostic;
+
+use crate::{scraping::{Backend, ScrapingResult, ScreenshotFormat, extract::Extract, links::Links, record::Field, table::Table, transform::Transform, rate_limit::{HostLimiter, RateLimit}, robots::{RobotsConfig, RobotsTxt}}, ScraperBuilder, SimpleScraperBuilder, error::{Error, Result}};
+
+#[derive(Clone, Debug, Serialize, Deserialize, Tabled)]
+#[serde(deny_unknown_fields)]
+#[tabled(rename_all = "UPPERCASE")]
+pub struct Target {
+
+    #[serde(skip)]
+    pub name: String,
+
+    /// A CSS selector, an XPath, or some text, told apart by a css:, xpath: or text: prefix. See Selector. <br>
+    /// Metadata targets, which read the whole page, go without, and so do content targets after the main article.
+    #[serde(default)]
+    pub selector: String,
+
+    /// Another target: selector is then evaluated relative to each of its elements, e.g. ./span
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[tabled(display_with = "display_option")]
+    pub parent: Option<String>,
+
+    /// Named selectors evaluated relative to each element: the target is then a list of records, see Field.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    #[tabled(display_with = "display_fields")]
+    pub fields: BTreeMap<String, Field>,
+
+    /// What is kept of each element, e.g. text or attr:href, rather than the whole element. See Extract.
+    #[serde(default, skip_serializing_if = "Extract::is_element")]
+    pub extract: Extract,
+
+    /// Clean-up steps run on every value, e.g. trim or parse_int. Elements are then kept as text, unless extract says otherwise.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[tabled(display_with = "display_transforms")]
+    pub transform: Vec<Transform>,
+
+    /// The target is then the absolute URLs its elements link to, filtered as links says. See Links.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[tabled(skip)]
+    pub links: Option<Links>,
+
+    /// The target is then a record per body row of its tables, keyed by header cells. See Table.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[tabled(skip)]
+    pub table: Option<Table>,
+
+    /// The target is then the structured data of the page, e.g. JSON-LD or OpenGraph tags. See PageMetadata.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    #[tabled(skip)]
+    pub metadata: bool,
+
+    /// The target is then the readable content of its elements, as Markdown and plain text, or of the main article of the page without a selector. See Content.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    #[tabled(skip)]
+    pub content: bool
+}
+
+fn display_option(value: &Option<String>) -> String {
+    value.clone().unwrap_or_default()
+}
+
+fn display_transforms(transforms: &[Transform]) -> String {
+    transforms.iter().map(Transform::to_string).collect::<Vec<String>>().join(" | ")
+}
+
+fn display_fields(fields: &BTreeMap<String, Field>) -> String {
+    fields.keys().cloned().collect::<Vec<String>>().join(", ")
+}
+
+
+impl Display for ActionData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        
+        if let ActionData::ActionClick(selector) = self {
+            write!(f, "{}", selector.selector);
+        } else if let ActionData::ActionWait(selector) = self {
+            write!(f, "{}", selector.duration);
+        } else if let ActionData::ActionTypeInto(selector) = self {
+            write!(f, "{}", selector.text);
+        } else if let ActionData::ActionScreenshot(selector) = self {
+            write!(f, "{}", selector.target);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ActionClick {
+
+    pub selector: String
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ActionWait {
+
+    pub duration: u32
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ActionTypeInto {
+
+    pub target: String,
+    pub text: String
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ActionScreenshot {
+
+    pub target: String,
+    pub format: String
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ActionSave {
+
+    pub targets: Vec<String>,
+    #[serde(default)]
+    pub flatten: bool,
+
+    /// The file to write, relative to out_dir. $URL$ is replaced by the name taken from the URL. <br>
+    /// By default, the file is named after the URL.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub save_path: Option<String>
+}
+
+/// What an action does, told by its type field, e.g.
+/// ```yaml
+/// actions:
+///   click1:
+///     type: CLICK
+///     selector: "#next"
+/// ```
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type")]
+#[allow(clippy::enum_variant_names)]
+pub enum ActionData {
+    #[serde(rename = "CLICK")]
+    ActionClick(ActionClick),
+    #[serde(rename = "SCREENSHOT")]
+    ActionScreenshot(ActionScreenshot),
+    #[serde(rename = "WAIT")]
+    ActionWait(ActionWait),
+    #[serde(rename = "TYPE_INTO")]
+    ActionTypeInto(ActionTypeInto),
+    #[serde(rename = "SAVE")]
+    ActionSave(ActionSave)
+    // Other possible response types here...
+}
+
+/// Version 1 actions, without a type field: it's guessed from their fields.
+#[derive(Deserialize)]
+#[serde(untagged)]
+#[allow(clippy::enum_variant_names)]
+enum UntypedActionData {
+    ActionClick(ActionClick),
+    ActionScreenshot(ActionScreenshot),
+    ActionWait(ActionWait),
+    ActionTypeInto(ActionTypeInto),
+    ActionSave(ActionSave)
+}
+
+impl From<UntypedActionData> for ActionData {
+    fn from(data: UntypedActionData) -> Self {
+        match data {
+            UntypedActionData::ActionClick(a) => ActionData::ActionClick(a),
+            UntypedActionData::ActionScreenshot(a) => ActionData::ActionScreenshot(a),
+            UntypedActionData::ActionWait(a) => ActionData::ActionWait(a),
+            UntypedActionData::ActionTypeInto(a) => ActionData::ActionTypeInto(a),
+            UntypedActionData::ActionSave(a) => ActionData::ActionSave(a)
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Tabled)]
+#[tabled(rename_all = "UPPERCASE")]
+pub struct Action {
+
+    #[serde(skip)]
+    pub name: String,
+    #[serde(flatten)]
+    pub data: ActionData
+}
+
+impl<'de> Deserialize<'de> for Action {
+
+    /// Actions with a type field are read strictly. The others are version 1 actions, still accepted but deprecated.
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+
+        let value = serde_yaml::Value::deserialize(deserializer)?;
+
+        let data = match value.get("type") {
+            Some(_) => ActionData::deserialize(value).map_err(serde::de::Error::custom)?,
+            None => UntypedActionData::deserialize(value)
+                .map_err(|_| serde::de::Error::custom("unknown action: give it a type, one of CLICK, SCREENSHOT, WAIT, TYPE_INTO or SAVE"))?
+                .into()
+        };
+
+        Ok(Action { name: String::new(), data })
+    }
+}
+
+/// The engine a pipeline runs on. <br>
+/// chrome renders JavaScript and supports every action, http only fetches static pages.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    #[default]
+    Chrome,
+    Http
+}
+
+/// The latest version of the pipeline format. <br>
+/// Version 2 pipelines declare the type of every action. Version 1 pipelines, without a version field, may leave it out.
+pub const PIPELINE_VERSION: u32 = 2;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Pipeline {
+
+    #[serde(default = "default_version")]
+    pub version: u32,
+    pub name: String,
+    pub url: String,
+    #[serde(default)]
+    pub backend: BackendKind,
+    pub headless: bool,
+    pub out_dir: String,
+    pub timeout: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimit>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub robots: Option<RobotsConfig>
+}
+
+fn default_version() -> u32 {
+    1
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PipelineConfig {
+
+    #[serde(flatten)]
+    pub pipeline: Pipeline,
+
+    pub targets: HashMap<String, Target>,
+    pub actions: HashMap<String, Action>,
+
+    pub steps: Vec<String>
+}
+
+impl PipelineConfig {
+
+    /// It reads and validates a pipeline file: any problem fails with Error::Validation, listing them all.
+    pub fn from_file(config_source: &str) -> Result<PipelineConfig> {
+
+        let content = std::fs::read_to_string(config_source)?;
+
+        PipelineConfig::parse(&content, Some(config_source))
+    }
+
+    pub fn from_yaml(config: &str) -> Result<PipelineConfig> {
+
+        PipelineConfig::parse(config, None)
+    }
+
+    /// It lists every problem of a pipeline, with its line and column, without running it. <br>
+    /// file only names the pipeline in the diagnostics. An empty list means the pipeline is valid.
+    pub fn check(config: &str, file: Option<&str>) -> Vec<Diagnostic> {
+
+        match PipelineConfig::parse(config, file) {
+            Err(Error::Validation(diagnostics)) => diagnostics,
+            _ => vec![]
+        }
+    }
+
+    fn parse(config: &str, file: Option<&str>) -> Result<PipelineConfig> {
+
+        let locator = validate::Locator::scan(config);
+
+        let pipeline_config: PipelineConfig = serde_yaml::from_str(config).map_err(|e| {
+
+            // Malformed actions are only located by the parser at the start of the actions section.
+            let diagnostics = validate::check_actions(config, &locator, file);
+
+            match diagnostics.is_empty() {
+                true => Error::Validation(vec![validate::yaml_diagnostic(&e, file)]),
+                false => Error::Validation(diagnostics)
+            }
+        })?;
+
+        let pipeline_config = pipeline_config.with_names();
+
+        let mut diagnostics = validate::check_version(&pipeline_config, config, &locator, file);
+        diagnostics.extend(validate::check_config(&pipeline_config, &locator, file));
+
+        // In file order, as they were found in separate passes.
+        diagnostics.sort_by_key(|d| d.position);
+
+        match diagnostics.is_empty() {
+            true => Ok(pipeline_config),
+            false => Err(Error::Validation(diagnostics))
+        }
+    }
+
+    /// Targets and actions are named after their key.
+    fn with_names(mut self) -> PipelineConfig {
+
+        for (k, t) in &mut self.targets {
+            t.name = k.to_string();
+        }
+
+        for (k, t) in &mut self.actions {
+            t.name = k.to_string();
+        }
+
+        self
+    }
+
+    /// It turns every "new_name from name" step into a copy of the name target or action, called new_name.
+    fn expand_aliases(&mut self) -> Result<()> {
+
+        let targets = &mut self.targets;
+
+        let actions = &mut self.actions;
+
+        for step in &mut self.steps {
+            
+            if step.contains(" from ") {
+
+                let (new_name, from_name) = step.split_once(" from ").unwrap();
+
+                if let Some(t) = targets.get(from_name) {
+
+                    let mut new_t = t.clone();
+                    new_t.name = new_name.to_string();
+
+                    targets.insert(new_t.name.clone(), new_t);
+                } else if let Some(a) = actions.get(from_name) {
+                    let mut new_t = a.clone();
+                    new_t.name = new_name.to_string();
+
+                    actions.insert(new_t.name.clone(), new_t);
+                } else {
+                    return Err(Error::Config(format!("Invalid alias: {}", step)));
+                }
+
+                *step = new_name.to_string();
+            }
+            
+        }
+
+        Ok(())
+    }
+
+    /// It checks the pipeline without running it, e.g. after editing it in code: every step must be a target or an action,
+    /// aliases and saved targets must exist, targets must be scraped before being saved, and selectors must parse.
+    pub fn validate(&self) -> Result<()> {
+
+        let diagnostics = validate::check_config(self, &validate::Locator::default(), None);
+
+        match diagnostics.is_empty() {
+            true => Ok(()),
+            false => Err(Error::Validation(diagnostics))
+        }
+    }
+
+    /// It materializes the backend chosen by the configuration.
+    pub fn build_backend(&self) -> Result<Box<dyn Backend + Send>> {
+
+        self.build_backend_with(self.build_host_limiter(), self.build_robots()?)
+    }
+
+    /// The limiter enforcing the rate_limit section, if any. Backends sharing it share the per-host limits.
+    /// With a robots section, there's always one, to apply Crawl-delay.
+    pub fn build_host_limiter(&self) -> Option<Arc<HostLimiter>> {
+
+        match (&self.pipeline.rate_limit, &self.pipeline.robots) {
+            (Some(rate_limit), _) => Some(Arc::new(HostLimiter::new(rate_limit.clone()))),
+            (None, Some(_)) => Some(Arc::new(HostLimiter::new(RateLimit::default()))),
+            (None, None) => None
+        }
+    }
+
+    /// The robots.txt cache of the robots section, if any. Backends sharing it share the cache.
+    pub fn build_robots(&self) -> Result<Option<Arc<RobotsTxt>>> {
+
+        match &self.pipeline.robots {
+            Some(robots) => Ok(Some(Arc::new(RobotsTxt::new(&robots.user_agent, &[])?))),
+            None => Ok(None)
+        }
+    }
+
+    /// Like build_backend, but rate limited by host_limiter and checked against robots.
+    pub fn build_backend_with(&self, host_limiter: Option<Arc<HostLimiter>>, robots: Option<Arc<RobotsTxt>>) -> Result<Box<dyn Backend + Send>> {
+
+        let pipeline = &self.pipeline;
+
+        let backend: Box<dyn Backend + Send> = match pipeline.backend {
+            BackendKind::Chrome => Box::new(
+                ScraperBuilder { host_limiter, robots, ..Default::default() }
+                    .set_headless(pipeline.headless)
+                    .set_default_timeout(pipeline.timeout)
+                    .set_save_dir(pipeline.out_dir.clone())
+                    .try_build()?
+            ),
+            BackendKind::Http => Box::new(
+                SimpleScraperBuilder { host_limiter, robots, ..Default::default() }
+                    .set_default_timeout(pipeline.timeout)
+                    .set_save_dir(pipeline.out_dir.clone())
+                    .try_build()?
+            )
+        };
+
+        Ok(backend)
+    }
+}
+
+pub struct ScrapingPipeline {
+
+    pub pipeline_config: PipelineConfig,
+    backend: Box<dyn Backend + Send>
+
+}
+
+impl ScrapingPipeline {
+    
+    pub fn from_file(config_source: &str) -> Result<ScrapingPipeline> {
+
+        let pipeline_config = PipelineConfig::from_file(config_source)?;
+        let backend = pipeline_config.build_backend()?;
+
+        Ok(ScrapingPipeline { pipeline_config, backend })
+    }
+
+    /// It runs the pipeline on a custom backend, whatever the configuration asks for.
+    pub fn with_backend(pipeline_config: PipelineConfig, backend: Box<dyn Backend + Send>) -> ScrapingPipeline {
+
+        ScrapingPipeline { pipeline_config, backend }
+    }
+
+    pub fn get_steps(&self) -> Vec<String> {
+
+        self.pipeline_config.steps.clone()
+    }
+
+    /// It runs every step on url, or on the configured URL if url is "DEFAULT". <br>
+    /// The run stops at the first failing step, e.g. an action the backend doesn't support.
+    pub fn run(&mut self, url: &str) -> Result<ScrapingResult> {
+
+        run_steps(&mut self.pipeline_config, self.backend.as_mut(), url)
+    }
+}
+
+/// It runs the pipeline steps on any backend, e.g. one lent by a BackendPool. <br>
+/// On failure, whatever was scraped so far is dropped, so a reused backend starts clean.
+pub(crate) fn run_steps(pipeline_config: &mut PipelineConfig, backend: &mut dyn Backend, url: &str) -> Result<ScrapingResult> {
+
+    let res = try_run_steps(pipeline_config, backend, url);
+
+    if res.is_err() {
+        backend.collect();
+    }
+
+    res
+}
+
+fn try_run_steps(pipeline_config: &mut PipelineConfig, backend: &mut dyn Backend, url: &str) -> Result<ScrapingResult> {
+
+
+    if url == "DEFAULT" {
+        backend.navigate(&pipeline_config.pipeline.url)?;
+    } else {
+        backend.navigate(url)?;
+    }
+
+    pipeline_config.expand_aliases()?;
+
+    let mut saved: Vec<PathBuf> = vec![];
+
+    for step in &pipeline_config.steps {
+        
+        if let Some(t) = pipeline_config.targets.get(step) {
+            register_target(backend, t, &pipeline_config.targets)?;
+
+        } else if let Some(a) = pipeline_config.actions.get(step) {
+
+            saved.extend(register_action(backend, a)?);
+        } else {
+            return Err(Error::Config(format!("{} not implemented.", step)));
+        }
+    }
+    
+    let mut res = backend.collect();
+    res.saved = saved;
+
+    let url = if url == "DEFAULT" { &pipeline_config.pipeline.url } else { url };
+
+    for failure in &res.failures {
+        warn!("{}: {}", url, failure);
+    }
+
+    Ok(res)
+}
+
+fn register_target(backend: &mut dyn Backend, target: &Target, targets: &HashMap<String, Target>) -> Result<()> {
+
+    let n = &target.name;
+    let s = &target.selector;
+
+    let parent = match target.parent.as_ref() {
+        Some(parent) => Some(targets.get(parent).ok_or_else(|| Error::Config(format!("{} has an unknown parent {}", n, parent)))?),
+        None => None
+    };
+
+    let parent_selector = parent.map(|p| p.selector.as_str());
+
+    match (&target.links, &target.table, parent) {
+        _ if target.metadata => backend.query_metadata(n)?,
+        _ if target.content => { backend.query_content(n, parent_selector, (!s.is_empty()).then_some(s.as_str()))?; },
+        _ if !target.fields.is_empty() => { backend.query_records(n, parent_selector, s, &target.fields)?; },
+        (Some(links), _, _) => { backend.query_links(n, parent_selector, s, links)?; },
+        (_, Some(table), _) => { backend.query_table(n, parent_selector, s, table)?; },
+        _ if target.extract != Extract::Element || !target.transform.is_empty() => {
+            backend.query_extract(n, parent_selector, s, &target.extract, &target.transform)?;
+        },
+        (_, _, Some(parent)) => { backend.query_within(n, &parent.selector, s)?; },
+        (_, _, None) => { backend.query(n, s)?; }
+    }
+
+    Ok(())
+}
+
+/// It returns the path of the written file, for SAVE actions.
+fn register_action(backend: &mut dyn Backend, action: &Action) -> Result<Option<PathBuf>> {
+    let n = &action.name;
+    
+    match &action.data {
+        ActionData::ActionClick(a) => backend.click(n, &a.selector)?,
+        ActionData::ActionScreenshot(a) => {
+            
+            let format = match a.format.as_str() {
+                "JPEG" => ScreenshotFormat::JPEG,
+                _ => ScreenshotFormat::PNG,
+            };
+            backend.screenshot(n, &a.target, format)?;
+        },
+        ActionData::ActionWait(a) => backend.wait(a.duration as u64),
+        ActionData::ActionTypeInto(a) => backend.type_into(n, &a.target, &a.text)?,
+        ActionData::ActionSave(a) => {
+
+            return backend.save(&a.targets, a.flatten, a.save_path.as_deref()).map(Some);
+        },
+    };
+
+    Ok(None)
+}
+
+impl Display for ScrapingPipeline {
+
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.pipeline_config)
+    }
+}
+
+impl Display for PipelineConfig {
+
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        
+        let pipeline = self;
+
+        //let separator = str::repeat("-", (80 - 4));
+
+        let mut targets_data: Vec<&Target> = Vec::default();
+
+        for target in pipeline.targets.values() {
+
+            targets_data.push(target);
+        }
+
+        let mut actions_data: Vec<&Action> = Vec::default();
+
+        for target in pipeline.actions.values() {
+
+            actions_data.push(target);
+        }
+
+        let steps = pipeline.steps.clone();
+
+        let steps: Vec<String> = steps.iter().enumerate().map(|(i, s)| format!("{}. {}", i+1, s)).collect();
+
+        let mut table_targets = tabled::Table::new(targets_data);
+        
+        table_targets.with(Panel::header("TARGETS"));
+        table_targets.with(tabled::Style::sharp().horizontals(
+            [HorizontalLine::new(1, Style::modern().get_horizontal())
+                    .main(Some('═'))
+                    .intersection(None),
+                    HorizontalLine::new(2, Style::modern().get_horizontal())
+                    .main(Some('═'))
+                    .intersection(None)
+                ]
+        ));
+        table_targets.with(Modify::new(Rows::new(0..=1)).with(Alignment::center()));
+        table_targets.with(tabled::Style::correct_spans());
+
+        let w = table_targets.total_width();
+        let mut table_actions = tabled::Table::new(actions_data);
+        
+        table_actions.with(Panel::header("ACTIONS"));
+        table_actions.with(tabled::Style::sharp().horizontals(
+            [HorizontalLine::new(1, Style::modern().get_horizontal())
+                    .main(Some('═'))
+                    .intersection(None),
+                    HorizontalLine::new(2, Style::modern().get_horizontal())
+                    .main(Some('═'))
+                    .intersection(None)
+                ]
+        ));
+        table_actions.with(Modify::new(Rows::new(0..=1)).with(Alignment::center()));
+        table_actions.with(tabled::Style::correct_spans());
+
+        table_actions.with(tabled::Width::increase(w).priority::<tabled::peaker::PriorityMax>());
+
+        let mut table_steps = tabled::Table::new(steps);
+        table_steps.with(Panel::header("STEPS"));
+        table_steps.with(Disable::row(Rows::single(1)));
+        
+        table_steps.with(tabled::Style::sharp());
+
+        table_steps.with(tabled::Style::sharp().horizontals(
+            [HorizontalLine::new(1, Style::modern().get_horizontal())
+                    .main(Some('═'))
+                    .intersection(None),
+                ]
+        ));
+        table_steps.with(Modify::new(Rows::first()).with(Alignment::center()));
+        table_steps.with(tabled::Width::increase(w).priority::<tabled::peaker::PriorityMax>());
+
+        writeln!(f, "{}", table_targets);
+        writeln!(f, "{}", table_actions);
+        writeln!(f, "{}", table_steps);
+        
+        Ok(())
+    }
+}
@@ -0,0 +1,274 @@
+use std::{ops::{Deref, DerefMut}, sync::{Condvar, Mutex}};
+
+use log::{info, warn};
+
+use crate::{scraping::Backend, error::{Error, Result}};
+
+type BackendFactory = Box<dyn Fn() -> Result<Box<dyn Backend + Send>> + Send + Sync>;
+
+struct PooledBackend {
+    backend: Box<dyn Backend + Send>,
+
+    /// Pages served since the last recycle.
+    pages: usize
+}
+
+struct PoolState {
+    idle: Vec<PooledBackend>,
+
+    /// Backends alive, idle or checked out.
+    alive: usize,
+
+    size: usize,
+
+    recycle_after: Option<usize>,
+
+    closed: bool
+}
+
+/// BackendPool keeps up to size backends (e.g. Chrome browsers) alive and lends them out, one URL at a time. <br>
+/// Backends are launched lazily, and recycled (e.g. a fresh tab) after recycle_after pages, if set. <br>
+/// Both settings can change while the pool is shared: they apply from the next checkout or checkin.
+pub struct BackendPool {
+    factory: BackendFactory,
+    state: Mutex<PoolState>,
+    returned: Condvar
+}
+
+/// A backend lent by a BackendPool. It goes back to the pool when dropped.
+pub struct PoolGuard<'a> {
+    pool: &'a BackendPool,
+    item: Option<PooledBackend>,
+    discard: bool
+}
+
+impl BackendPool {
+
+    pub fn new<F>(size: usize, factory: F) -> BackendPool
+    where
+        F: Fn() -> Result<Box<dyn Backend + Send>> + Send + Sync + 'static
+    {
+        BackendPool {
+            factory: Box::new(factory),
+            state: Mutex::new(PoolState { idle: vec![], alive: 0, size: size.max(1), recycle_after: None, closed: false }),
+            returned: Condvar::new()
+        }
+    }
+
+    /// Backends are recycled every pages pages. 0 disables recycling.
+    pub fn set_recycle_after(&self, pages: usize) -> &BackendPool {
+        self.lock().recycle_after = Some(pages).filter(|p| *p > 0);
+        self
+    }
+
+    /// At most size backends are alive. Idle backends beyond it are closed, busy ones once they're returned.
+    pub fn set_size(&self, size: usize) -> &BackendPool {
+
+        let closed = {
+            let mut state = self.lock();
+            state.size = size.max(1);
+
+            let excess = state.alive.saturating_sub(state.size).min(state.idle.len());
+            state.alive -= excess;
+
+            let keep = state.idle.len() - excess;
+            state.idle.split_off(keep)
+        };
+
+        drop(closed);
+        self.returned.notify_all();
+        self
+    }
+
+    pub fn size(&self) -> usize {
+        self.lock().size
+    }
+
+    /// It lends an idle backend, launches a new one if the pool isn't full, or waits for one to be returned.
+    pub fn checkout(&self) -> Result<PoolGuard<'_>> {
+
+        let mut state = self.lock();
+
+        loop {
+            if state.closed {
+                return Err(Error::Browser("the backend pool is shut down".to_string()));
+            }
+
+            if let Some(item) = state.idle.pop() {
+                return Ok(PoolGuard { pool: self, item: Some(item), discard: false });
+            }
+
+            if state.alive < state.size {
+                break;
+            }
+
+            state = self.returned.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+
+        state.alive += 1;
+        drop(state);
+
+        // Launching a browser takes a while: the pool stays available meanwhile.
+        match (self.factory)() {
+            Ok(backend) => {
+                info!("[POOL] Backend launched");
+                Ok(PoolGuard { pool: self, item: Some(PooledBackend { backend, pages: 0 }), discard: false })
+            },
+            Err(e) => {
+                self.lock().alive -= 1;
+                self.returned.notify_one();
+                Err(e)
+            }
+        }
+    }
+
+    /// It closes every idle backend and refuses new checkouts. <br>
+    /// Backends still lent out are closed as soon as they are returned.
+    pub fn shutdown(&self) {
+
+        let idle = {
+            let mut state = self.lock();
+            state.closed = true;
+            state.alive -= state.idle.len();
+            std::mem::take(&mut state.idle)
+        };
+
+        // Dropping a Chrome backend kills its browser process.
+        drop(idle);
+        self.returned.notify_all();
+    }
+
+    fn checkin(&self, mut item: PooledBackend, discard: bool) {
+
+        item.pages += 1;
+
+        let mut keep = !discard;
+
+        let recycle_after = self.lock().recycle_after;
+
+        if keep && recycle_after.is_some_and(|k| item.pages >= k) {
+            match item.backend.recycle() {
+                Ok(()) => item.pages = 0,
+                Err(e) => {
+                    warn!("[POOL] Couldn't recycle a backend, closing it: {}", e);
+                    keep = false;
+                }
+            }
+        }
+
+        let mut state = self.lock();
+
+        if keep && !state.closed && state.alive <= state.size {
+            state.idle.push(item);
+        } else {
+            state.alive -= 1;
+        }
+
+        drop(state);
+        self.returned.notify_one();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, PoolState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Drop for BackendPool {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+impl PoolGuard<'_> {
+
+    /// The backend is closed instead of going back to the pool, e.g. after the browser misbehaved.
+    pub fn discard(&mut self) {
+        self.discard = true;
+    }
+}
+
+impl Deref for PoolGuard<'_> {
+    type Target = dyn Backend + Send;
+
+    fn deref(&self) -> &Self::Target {
+        self.item.as_ref().expect("present until dropped").backend.as_ref()
+    }
+}
+
+impl DerefMut for PoolGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.item.as_mut().expect("present until dropped").backend.as_mut()
+    }
+}
+
+impl Drop for PoolGuard<'_> {
+    fn drop(&mut self) {
+
+        if let Some(item) = self.item.take() {
+            // A backend that was in use during a panic may be in any state.
+            self.pool.checkin(item, self.discard || std::thread::panicking());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::sync::{Arc, atomic::{AtomicUsize, Ordering}};
+
+    use crate::scraping::mock::MockBackend;
+
+    use super::*;
+
+    fn pool(launched: &Arc<AtomicUsize>) -> Arc<BackendPool> {
+
+        let launched = launched.clone();
+
+        Arc::new(BackendPool::new(2, move || {
+            launched.fetch_add(1, Ordering::SeqCst);
+            Ok(Box::new(MockBackend::new()) as Box<dyn Backend + Send>)
+        }))
+    }
+
+    #[test]
+    fn resizing_applies_to_every_holder() {
+
+        let launched = Arc::new(AtomicUsize::new(0));
+        let pool = pool(&launched);
+        let shared = pool.clone();
+
+        {
+            let _a = pool.checkout().unwrap();
+            let _b = pool.checkout().unwrap();
+        }
+        assert_eq!(launched.load(Ordering::SeqCst), 2);
+
+        shared.set_size(1);
+        assert_eq!(pool.size(), 1);
+        assert_eq!(pool.lock().alive, 1);
+
+        shared.set_size(3);
+
+        let _a = pool.checkout().unwrap();
+        let _b = pool.checkout().unwrap();
+        let _c = pool.checkout().unwrap();
+        assert_eq!(launched.load(Ordering::SeqCst), 4);
+    }
+
+    #[test]
+    fn busy_backends_close_when_returned_to_a_smaller_pool() {
+
+        let launched = Arc::new(AtomicUsize::new(0));
+        let pool = pool(&launched);
+
+        let a = pool.checkout().unwrap();
+        let b = pool.checkout().unwrap();
+
+        pool.set_size(1);
+        drop(a);
+        drop(b);
+
+        assert_eq!(pool.lock().alive, 1);
+        assert_eq!(pool.lock().idle.len(), 1);
+    }
+}
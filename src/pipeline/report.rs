@@ -0,0 +1,113 @@
+use std::{fmt::Display, time::Duration};
+
+use tabled::{Tabled, Panel, Modify, object::Rows, Alignment, Style};
+
+use crate::{scraping::ScrapingResult, error::{Error, Result}};
+
+/// The outcome of one URL of a PipelineRunner run.
+pub struct UrlReport {
+    pub url: String,
+
+    /// 1 if it went right the first time, more if it was retried,
+    /// 0 if a previous run of the job already did it: the result then only lists the saved files.
+    pub attempts: usize,
+
+    pub result: Result<ScrapingResult>
+}
+
+/// RunReport lists, in input order, which URLs of a run succeeded, which failed and why,
+/// and how many attempts each took. <br>
+/// Print it for a summary table.
+pub struct RunReport {
+    pub urls: Vec<UrlReport>,
+
+    pub elapsed: Duration
+}
+
+#[derive(Tabled)]
+#[tabled(rename_all = "UPPERCASE")]
+struct ReportRow<'a> {
+    url: &'a str,
+    status: &'static str,
+    attempts: usize,
+    error: String
+}
+
+impl UrlReport {
+
+    pub fn is_success(&self) -> bool {
+        self.result.is_ok()
+    }
+
+    /// Whether robots.txt kept this URL from being fetched.
+    pub fn is_skipped(&self) -> bool {
+        matches!(self.result, Err(Error::Disallowed { .. }))
+    }
+
+    /// Whether a previous run of the job already did this URL.
+    pub fn is_resumed(&self) -> bool {
+        self.attempts == 0
+    }
+}
+
+impl RunReport {
+
+    pub fn succeeded(&self) -> impl Iterator<Item = &UrlReport> {
+        self.urls.iter().filter(|u| u.is_success())
+    }
+
+    /// The URLs that failed, skipped ones aside.
+    pub fn failed(&self) -> impl Iterator<Item = &UrlReport> {
+        self.urls.iter().filter(|u| !u.is_success() && !u.is_skipped())
+    }
+
+    pub fn skipped(&self) -> impl Iterator<Item = &UrlReport> {
+        self.urls.iter().filter(|u| u.is_skipped())
+    }
+
+    /// Whether every URL succeeded or was skipped.
+    pub fn is_success(&self) -> bool {
+        self.failed().next().is_none()
+    }
+
+    /// The results alone, in input order.
+    pub fn into_results(self) -> Vec<Result<ScrapingResult>> {
+        self.urls.into_iter().map(|u| u.result).collect()
+    }
+}
+
+impl Display for RunReport {
+
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+
+        let rows = self.urls.iter().map(|u| ReportRow {
+            url: &u.url,
+            status: match (u.is_success(), u.is_resumed(), u.is_skipped()) {
+                (true, true, _) => "RESUMED",
+                (true, false, _) => "OK",
+                (false, _, true) => "SKIPPED",
+                (false, _, false) => "FAILED"
+            },
+            attempts: u.attempts,
+            error: u.result.as_ref().err().map_or(String::new(), |e| e.to_string())
+        }).collect::<Vec<ReportRow>>();
+
+        let header = format!(
+            "RUN REPORT: {} succeeded ({} resumed), {} skipped, {} failed in {:.2?}",
+            self.succeeded().count(),
+            self.urls.iter().filter(|u| u.is_resumed()).count(),
+            self.skipped().count(),
+            self.failed().count(),
+            self.elapsed
+        );
+
+        let mut table = tabled::Table::new(rows);
+
+        table.with(Panel::header(header));
+        table.with(Style::sharp());
+        table.with(Modify::new(Rows::first()).with(Alignment::center()));
+        table.with(Style::correct_spans());
+
+        write!(f, "{}", table)
+    }
+}
@@ -0,0 +1,49 @@
+use std::time::Duration;
+
+/// RetryPolicy tells PipelineRunner how many times a URL is retried after a transient failure
+/// (navigation, timeout or proxy errors), and how long to wait in between. <br>
+/// The wait starts at initial_backoff and is multiplied by multiplier after every attempt, up to max_backoff.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    pub retries: usize,
+    pub initial_backoff: Duration,
+    pub multiplier: u32,
+    pub max_backoff: Duration
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self { retries: 0, initial_backoff: Duration::from_secs(1), multiplier: 2, max_backoff: Duration::from_secs(60) }
+    }
+}
+
+impl RetryPolicy {
+
+    pub fn set_retries(&mut self, retries: usize) -> &mut RetryPolicy {
+        self.retries = retries;
+        self
+    }
+
+    pub fn set_initial_backoff(&mut self, initial_backoff: Duration) -> &mut RetryPolicy {
+        self.initial_backoff = initial_backoff;
+        self
+    }
+
+    pub fn set_multiplier(&mut self, multiplier: u32) -> &mut RetryPolicy {
+        self.multiplier = multiplier.max(1);
+        self
+    }
+
+    pub fn set_max_backoff(&mut self, max_backoff: Duration) -> &mut RetryPolicy {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// The wait before the given retry, starting from 1.
+    pub fn backoff(&self, retry: usize) -> Duration {
+
+        let factor = self.multiplier.saturating_pow(retry.saturating_sub(1) as u32);
+
+        self.initial_backoff.saturating_mul(factor).min(self.max_backoff)
+    }
+}
@@ -0,0 +1,339 @@
+use std::{panic::{catch_unwind, AssertUnwindSafe}, path::PathBuf, sync::{Arc, Mutex, mpsc}, time::Instant};
+
+use log::{info, warn};
+
+use rayon::prelude::*;
+use tokio::sync::{Semaphore, mpsc as async_mpsc};
+
+use crate::{scraping::{ScrapingResult, async_chrome::run_blocking}, error::{Error, Result}};
+
+use super::{PipelineConfig, BackendPool, RetryPolicy, RunReport, UrlReport, checkpoint::Checkpoint, run_steps};
+
+/// PipelineRunner runs one pipeline over many URLs, concurrently. <br>
+/// The configuration is read once, and backends (e.g. Chrome browsers) come from a BackendPool:
+/// at most concurrency of them are launched, and each is reused across URLs.
+/// Clones share the same pool, and so its concurrency and recycling.
+#[derive(Clone)]
+pub struct PipelineRunner {
+    pipeline_config: Arc<PipelineConfig>,
+    stream_buffer: usize,
+    retry_policy: RetryPolicy,
+    checkpoint_dir: String,
+    checkpoint: Option<Arc<Mutex<Checkpoint>>>,
+    pool: Arc<BackendPool>
+}
+
+/// The results of PipelineRunner::stream, as (url, result) pairs, in completion order. <br>
+/// Scraping stops early when it's dropped.
+pub struct ResultStream {
+    receiver: mpsc::Receiver<(String, Result<ScrapingResult>)>
+}
+
+impl Iterator for ResultStream {
+    type Item = (String, Result<ScrapingResult>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.receiver.recv().ok()
+    }
+}
+
+impl PipelineRunner {
+
+    pub fn new(pipeline_config: PipelineConfig) -> PipelineRunner {
+
+        let concurrency = std::thread::available_parallelism().map_or(4, |n| n.get());
+
+        let pipeline_config = Arc::new(pipeline_config);
+        let pool = PipelineRunner::build_pool(&pipeline_config, concurrency);
+
+        PipelineRunner { pipeline_config, stream_buffer: concurrency, retry_policy: RetryPolicy::default(),
+            checkpoint_dir: ".scraping_jobs/".to_string(), checkpoint: None, pool }
+    }
+
+    pub fn from_file(pipeline_file: &str) -> Result<PipelineRunner> {
+
+        Ok(PipelineRunner::new(PipelineConfig::from_file(pipeline_file)?))
+    }
+
+    /// How many URLs are scraped at the same time, hence how many backends are launched. Defaults to the number of CPUs.
+    pub fn set_concurrency(&mut self, concurrency: usize) -> &mut PipelineRunner {
+        self.pool.set_size(concurrency);
+        self
+    }
+
+    /// Backends get a fresh tab every pages URLs, to keep the browser memory in check. 0, the default, never recycles.
+    pub fn set_recycle_after(&mut self, pages: usize) -> &mut PipelineRunner {
+        self.pool.set_recycle_after(pages);
+        self
+    }
+
+    /// How many finished results stream and stream_async hold before pausing the scraping, until they're consumed.
+    /// Defaults to the concurrency.
+    pub fn set_stream_buffer(&mut self, results: usize) -> &mut PipelineRunner {
+        self.stream_buffer = results.max(1);
+        self
+    }
+
+    /// How URLs are retried after a transient failure. By default, they aren't.
+    pub fn set_retry_policy(&mut self, retry_policy: RetryPolicy) -> &mut PipelineRunner {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Where job progress is kept, see set_job_id. Defaults to .scraping_jobs/
+    pub fn set_checkpoint_dir(&mut self, checkpoint_dir: String) -> &mut PipelineRunner {
+        self.checkpoint_dir = checkpoint_dir;
+        self
+    }
+
+    /// It makes the runs resumable: every URL done is recorded in the checkpoint directory under job_id,
+    /// and URLs already done by a previous run of the same job are skipped, their report marked as resumed. <br>
+    /// Delete {checkpoint_dir}/{job_id}.jsonl to start the job over.
+    pub fn set_job_id(&mut self, job_id: &str) -> Result<&mut PipelineRunner> {
+
+        let checkpoint = Checkpoint::open(&self.checkpoint_dir, job_id)?;
+        self.checkpoint = Some(Arc::new(Mutex::new(checkpoint)));
+
+        Ok(self)
+    }
+
+    /// The state file of the job, if a job id is set.
+    pub fn checkpoint_file(&self) -> Option<PathBuf> {
+        self.checkpoint.as_ref().map(|c| lock(c).path().to_path_buf())
+    }
+
+    /// It closes every backend. Runs started afterwards fail.
+    pub fn shutdown(&self) {
+        self.pool.shutdown();
+    }
+
+    fn build_pool(pipeline_config: &Arc<PipelineConfig>, concurrency: usize) -> Arc<BackendPool> {
+
+        let pipeline_config = pipeline_config.clone();
+
+        // Every backend of the pool counts against the same per-host limits, and shares the robots.txt cache.
+        let host_limiter = pipeline_config.build_host_limiter();
+        let robots = pipeline_config.build_robots().map_err(|e| e.to_string());
+
+        Arc::new(BackendPool::new(concurrency, move || {
+            let robots = robots.clone().map_err(Error::Launch)?;
+            pipeline_config.build_backend_with(host_limiter.clone(), robots)
+        }))
+    }
+
+    pub fn go( pipeline_file: String, urls: &[String]) -> Vec<Result<ScrapingResult>> {
+
+        match PipelineRunner::from_file(&pipeline_file) {
+            Ok(runner) => runner.run(urls),
+            Err(e) => {
+                let reason = e.to_string();
+                urls.iter().map(|_| Err(Error::Config(reason.clone()))).collect()
+            }
+        }
+    }
+
+    /// It scrapes every URL on concurrency threads, returning the results in the same order.
+    pub fn run(&self, urls: &[String]) -> Vec<Result<ScrapingResult>> {
+        self.run_report(urls).into_results()
+    }
+
+    /// Like run, but it also tells how many attempts each URL took. <br>
+    /// A URL failing, or even panicking, never affects the others.
+    pub fn run_report(&self, urls: &[String]) -> RunReport {
+
+        let t0 = Instant::now();
+
+        let scrape = || urls.par_iter().map(|s| {
+
+            /* let parts = s.split("/").collect::<Vec<&str>>();
+            let id = parts.last().unwrap().to_string().replace(".html", "");
+            pipeline.pipeline_config.pipeline.name = format!("{}",  id); */
+
+            self.run_url(s)
+
+        }).collect::<Vec<UrlReport>>();
+
+        let reports = match rayon::ThreadPoolBuilder::new().num_threads(self.pool.size()).build() {
+            Ok(threads) => threads.install(scrape),
+            Err(_) => scrape()
+        };
+
+        RunReport { urls: reports, elapsed: t0.elapsed() }
+    }
+
+    /// It scrapes every URL from a tokio runtime, at most concurrency at a time, returning the results in the same order. <br>
+    /// Browsers are driven from tokio's blocking pool, so the runtime's workers are never blocked.
+    pub async fn run_async(&self, urls: &[String]) -> Vec<Result<ScrapingResult>> {
+        self.run_report_async(urls).await.into_results()
+    }
+
+    /// The tokio flavour of run_report.
+    pub async fn run_report_async(&self, urls: &[String]) -> RunReport {
+
+        let t0 = Instant::now();
+        let permits = Arc::new(Semaphore::new(self.pool.size()));
+
+        let handles = urls.iter().map(|url| {
+
+            let runner = self.clone();
+            let url = url.clone();
+            let permits = permits.clone();
+
+            tokio::spawn(async move {
+                let _permit = permits.acquire_owned().await.expect("the semaphore is never closed");
+
+                tokio::task::spawn_blocking(move || runner.run_url(&url)).await
+            })
+        }).collect::<Vec<_>>();
+
+        let mut reports: Vec<UrlReport> = vec![];
+
+        for (url, handle) in urls.iter().zip(handles) {
+
+            let report = match handle.await {
+                Ok(Ok(report)) => report,
+                Ok(Err(e)) | Err(e) => UrlReport {
+                    url: url.clone(),
+                    attempts: 1,
+                    result: Err(Error::Browser(format!("scraping task failed: {}", e)))
+                }
+            };
+
+            reports.push(report);
+        }
+
+        RunReport { urls: reports, elapsed: t0.elapsed() }
+    }
+
+    /// It scrapes every URL on concurrency threads, yielding each result as soon as its page is done. <br>
+    /// At most stream_buffer results wait to be consumed: scraping pauses while the buffer is full,
+    /// so memory stays flat however long the crawl.
+    pub fn stream(&self, urls: Vec<String>) -> ResultStream {
+
+        let (sender, receiver) = mpsc::sync_channel(self.stream_buffer);
+        let runner = self.clone();
+
+        std::thread::spawn(move || {
+
+            let scrape = || urls.into_par_iter().try_for_each_with(sender, |sender, url| {
+                let report = runner.run_url(&url);
+                // Err means the stream was dropped: no need to go on.
+                sender.send((report.url, report.result)).map_err(|_| ())
+            });
+
+            let _ = match rayon::ThreadPoolBuilder::new().num_threads(runner.pool.size()).build() {
+                Ok(threads) => threads.install(scrape),
+                Err(_) => scrape()
+            };
+        });
+
+        ResultStream { receiver }
+    }
+
+    /// The tokio flavour of stream: results come through a bounded channel, in completion order. <br>
+    /// It must be called from a tokio runtime.
+    pub fn stream_async(&self, urls: Vec<String>) -> async_mpsc::Receiver<(String, Result<ScrapingResult>)> {
+
+        let (sender, receiver) = async_mpsc::channel(self.stream_buffer);
+        let permits = Arc::new(Semaphore::new(self.pool.size()));
+
+        for url in urls {
+
+            let runner = self.clone();
+            let sender = sender.clone();
+            let permits = permits.clone();
+
+            tokio::spawn(async move {
+                // The permit is held until the result is handed over, so a full channel pauses the scraping.
+                let _permit = permits.acquire_owned().await.expect("the semaphore is never closed");
+
+                if sender.is_closed() {
+                    return;
+                }
+
+                let target = url.clone();
+                let res = run_blocking(move || runner.run_url(&target).result).await;
+
+                let _ = sender.send((url, res)).await;
+            });
+        }
+
+        receiver
+    }
+
+    /// It scrapes url, unless the job already did, retrying it as the retry policy says.
+    fn run_url(&self, url: &str) -> UrlReport {
+
+        if let Some(saved) = self.checkpoint.as_ref().and_then(|c| lock(c).completed(url).cloned()) {
+
+            info!("[CHECKPOINT] {} already done, skipping", url);
+
+            let res = ScrapingResult { saved, ..ScrapingResult::default() };
+            return UrlReport { url: url.to_string(), attempts: 0, result: Ok(res) };
+        }
+
+        let report = self.run_url_with_retries(url);
+
+        if let (Some(checkpoint), Ok(res)) = (&self.checkpoint, &report.result) {
+            if let Err(e) = lock(checkpoint).complete(url, &res.saved) {
+                warn!("[CHECKPOINT] Couldn't record {} as done: {}", url, e);
+            }
+        }
+
+        report
+    }
+
+    fn run_url_with_retries(&self, url: &str) -> UrlReport {
+
+        let mut attempts = 0;
+
+        loop {
+            attempts += 1;
+
+            let result = catch_unwind(AssertUnwindSafe(|| self.attempt_url(url)))
+                .unwrap_or_else(|panic| Err(Error::Panicked(panic_reason(panic))));
+
+            match result {
+                Err(e) if e.is_retryable() && attempts <= self.retry_policy.retries => {
+
+                    let backoff = match &e {
+                        // The server knows best, within the policy's bounds.
+                        Error::RateLimited { retry_after: Some(d), .. } => self.retry_policy.backoff(attempts).max((*d).min(self.retry_policy.max_backoff)),
+                        _ => self.retry_policy.backoff(attempts)
+                    };
+                    warn!("[RETRY] {} failed ({}), retrying in {:.2?}", url, e, backoff);
+
+                    std::thread::sleep(backoff);
+                },
+                result => return UrlReport { url: url.to_string(), attempts, result }
+            }
+        }
+    }
+
+    fn attempt_url(&self, url: &str) -> Result<ScrapingResult> {
+
+        let mut pipeline_config = PipelineConfig::clone(&self.pipeline_config);
+        let mut backend = self.pool.checkout()?;
+
+        let res = run_steps(&mut pipeline_config, &mut *backend, url);
+
+        // The page may be stuck: better launch a new backend than reuse it.
+        if let Err(Error::Browser(_) | Error::Timeout { .. }) = res {
+            backend.discard();
+        }
+
+        res
+    }
+}
+
+fn lock(checkpoint: &Mutex<Checkpoint>) -> std::sync::MutexGuard<'_, Checkpoint> {
+    checkpoint.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn panic_reason(panic: Box<dyn std::any::Any + Send>) -> String {
+
+    match panic.downcast::<String>() {
+        Ok(reason) => *reason,
+        Err(panic) => panic.downcast_ref::<&str>().map_or("unknown reason".to_string(), |r| r.to_string())
+    }
+}
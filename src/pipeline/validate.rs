@@ -0,0 +1,581 @@
+use std::{collections::{BTreeMap, HashMap, HashSet}, fmt::Display};
+
+use log::warn;
+use serde::Deserialize;
+
+use crate::scraping::{html::check_selector, record::Field};
+
+use super::{Action, ActionData, PipelineConfig, PIPELINE_VERSION};
+
+/// A problem found in a pipeline file, before running it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub file: Option<String>,
+
+    /// 1-based line and column, when the problem can be pinned down.
+    pub position: Option<(usize, usize)>,
+
+    pub message: String
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+
+        match (&self.file, self.position) {
+            (Some(file), Some((line, column))) => write!(f, "{}:{}:{}: {}", file, line, column, self.message),
+            (Some(file), None) => write!(f, "{}: {}", file, self.message),
+            (None, Some((line, column))) => write!(f, "{}:{}: {}", line, column, self.message),
+            (None, None) => write!(f, "{}", self.message)
+        }
+    }
+}
+
+/// Positions of the keys, values and list items of a block-style YAML document,
+/// by path, e.g. targets.element_1.selector or steps.2 <br>
+/// Flow collections ({...} and [...]) and scalars spanning several lines aren't looked into:
+/// what's inside them is located at the key holding them.
+#[derive(Default)]
+pub(crate) struct Locator {
+    keys: HashMap<String, (usize, usize)>,
+    values: HashMap<String, (usize, usize)>
+}
+
+impl Locator {
+
+    pub(crate) fn scan(content: &str) -> Locator {
+
+        let mut locator = Locator::default();
+
+        // (indent, path, is a list item) of the enclosing keys.
+        let mut stack: Vec<(usize, String, bool)> = vec![];
+        let mut items: HashMap<String, usize> = HashMap::new();
+
+        // Brackets still open in a flow collection, whose lines are skipped.
+        let mut flow_depth = 0;
+        // Lines indented deeper than this continue the scalar above, and are skipped.
+        let mut scalar_indent: Option<usize> = None;
+        // The quote of a quoted scalar still open, whose lines are skipped whatever their indentation.
+        let mut open_quote: Option<char> = None;
+
+        for (i, line) in content.lines().enumerate() {
+
+            let trimmed = line.trim_start();
+            let indent = line.len() - trimmed.len();
+
+            if flow_depth > 0 {
+                flow_depth = flow_depth_after(flow_depth, trimmed);
+                continue;
+            }
+
+            if let Some(quote) = open_quote {
+                open_quote = quote_end(quote, trimmed).is_none().then_some(quote);
+                continue;
+            }
+
+            if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with("---") {
+                continue;
+            }
+
+            if let Some(scalar_indent) = scalar_indent.take() {
+                if indent > scalar_indent {
+                    continue;
+                }
+            }
+
+            if trimmed.starts_with('{') || trimmed.starts_with('[') {
+                flow_depth = flow_depth_after(0, trimmed);
+                continue;
+            }
+
+            if let Some(item) = trimmed.strip_prefix('-').filter(|rest| rest.is_empty() || rest.starts_with(' ')) {
+
+                // Items may sit at the same indentation as their key.
+                while stack.last().is_some_and(|(ind, _, is_item)| *ind > indent || (*ind == indent && *is_item)) {
+                    stack.pop();
+                }
+
+                let parent = stack.last().map(|(_, path, _)| path.clone()).unwrap_or_default();
+                let index = items.entry(parent.clone()).or_insert(0);
+
+                let path = join(&parent, &index.to_string());
+                *index += 1;
+
+                let column = indent + 1 + (item.len() - item.trim_start().len()) + 1;
+                locator.keys.insert(path.clone(), (i + 1, indent + 1));
+                locator.values.insert(path.clone(), (i + 1, column));
+
+                let item = item.trim_start();
+
+                if item.starts_with('{') || item.starts_with('[') {
+                    flow_depth = flow_depth_after(0, item);
+                } else if split_key(item).is_none() {
+                    scalar_indent = Some(indent);
+                }
+
+                stack.push((indent, path, true));
+                continue;
+            }
+
+            let Some((key, value)) = split_key(trimmed) else {
+                continue;
+            };
+
+            while stack.last().is_some_and(|(ind, _, _)| *ind >= indent) {
+                stack.pop();
+            }
+
+            let parent = stack.last().map(|(_, path, _)| path.as_str()).unwrap_or_default();
+            let path = join(parent, key.trim().trim_matches(|c| c == '"' || c == '\''));
+
+            locator.keys.insert(path.clone(), (i + 1, indent + 1));
+
+            let value_start = value.len() - value.trim_start().len();
+
+            if !value.trim().is_empty() && !value.trim_start().starts_with('#') {
+                locator.values.insert(path.clone(), (i + 1, indent + key.len() + 1 + value_start + 1));
+
+                match value.trim_start() {
+                    v if v.starts_with('{') || v.starts_with('[') => flow_depth = flow_depth_after(0, v),
+                    v if v.starts_with('"') || v.starts_with('\'') => {
+                        let quote = v.chars().next().expect("not empty");
+                        open_quote = quote_end(quote, &v[1..]).is_none().then_some(quote);
+                    },
+                    _ => scalar_indent = Some(indent)
+                }
+            }
+
+            stack.push((indent, path, false));
+        }
+
+        locator
+    }
+
+    /// Where the key at path is, or its closest located parent.
+    pub(crate) fn key(&self, path: &str) -> Option<(usize, usize)> {
+        self.find(path, &self.keys)
+    }
+
+    /// Where the value at path is, or its key.
+    pub(crate) fn value(&self, path: &str) -> Option<(usize, usize)> {
+        self.values.get(path).copied().or_else(|| self.key(path))
+    }
+
+    fn find(&self, path: &str, positions: &HashMap<String, (usize, usize)>) -> Option<(usize, usize)> {
+
+        let mut path = path;
+
+        loop {
+            if let Some(position) = positions.get(path) {
+                return Some(*position);
+            }
+
+            path = path.rsplit_once('.')?.0;
+        }
+    }
+}
+
+/// The key and the value of a mapping line, e.g. `selector: //a` or `"a: b": c`.
+fn split_key(line: &str) -> Option<(&str, &str)> {
+
+    let end = match line.chars().next() {
+        // A quoted key may hold colons.
+        Some(quote @ ('"' | '\'')) => line[1..].find(quote)? + 2,
+        _ => 0
+    };
+
+    // A colon only ends a plain key when followed by a space, e.g. not in https://a.com
+    let colon = line[end..].find(": ").or_else(|| line.ends_with(':').then(|| line.len() - 1 - end))? + end;
+
+    Some((&line[..colon], &line[colon + 1..]))
+}
+
+/// Where the quoted scalar opened with quote ends in text, the rest of it, if it does.
+fn quote_end(quote: char, text: &str) -> Option<usize> {
+
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' if quote == '"' => { chars.next(); },
+            // '' is an escaped quote in single-quoted scalars.
+            '\'' if quote == '\'' && chars.peek().is_some_and(|(_, c)| *c == '\'') => { chars.next(); },
+            c if c == quote => return Some(i + 1),
+            _ => ()
+        }
+    }
+
+    None
+}
+
+/// How many brackets of a flow collection are still open after line, out of depth before it.
+fn flow_depth_after(depth: usize, line: &str) -> usize {
+
+    let mut depth = depth;
+    let mut quote = None;
+
+    for c in line.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => (),
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '[' | '{') => depth += 1,
+            (None, ']' | '}') => depth = depth.saturating_sub(1),
+            _ => ()
+        }
+    }
+
+    depth
+}
+
+fn join(parent: &str, key: &str) -> String {
+
+    match parent.is_empty() {
+        true => key.to_string(),
+        false => format!("{}.{}", parent, key)
+    }
+}
+
+/// It checks what YAML deserialization can't: steps refer to targets or actions, aliases to existing ones,
+/// saved targets are scraped before being saved, screenshot formats exist and selectors parse.
+pub(crate) fn check_config(config: &PipelineConfig, locator: &Locator, file: Option<&str>) -> Vec<Diagnostic> {
+
+    let mut diagnostics = vec![];
+
+    let mut report = |position: Option<(usize, usize)>, message: String| {
+        diagnostics.push(Diagnostic { file: file.map(str::to_string), position, message });
+    };
+
+    // Aliases are expanded before running, so a step can use one defined further down.
+    let mut aliases: HashMap<&str, &str> = HashMap::new();
+
+    for (i, step) in config.steps.iter().enumerate() {
+
+        if let Some((new_name, from_name)) = step.split_once(" from ") {
+
+            if config.targets.contains_key(from_name) || config.actions.contains_key(from_name) {
+                aliases.insert(new_name, from_name);
+            } else {
+                report(locator.value(&format!("steps.{}", i)), format!("Invalid alias: {} is neither a target nor an action", from_name));
+            }
+        }
+    }
+
+    let resolve = |step: &'_ str| -> String {
+        let name = step.split_once(" from ").map_or(step, |(new_name, _)| new_name);
+        aliases.get(name).map_or(name, |from_name| *from_name).to_string()
+    };
+
+    let is_target = |name: &str| config.targets.contains_key(&resolve(name));
+
+    let mut scraped: HashSet<String> = HashSet::new();
+
+    for (i, step) in config.steps.iter().enumerate() {
+
+        let step_name = step.split_once(" from ").map_or(step.as_str(), |(new_name, _)| new_name);
+        let name = resolve(step);
+
+        if config.targets.contains_key(&name) {
+            scraped.insert(step_name.to_string());
+            continue;
+        }
+
+        let Some(action) = config.actions.get(&name) else {
+            if !step.contains(" from ") {
+                report(locator.value(&format!("steps.{}", i)), format!("Unknown step {}: it's neither a target nor an action", step));
+            }
+            continue;
+        };
+
+        if let ActionData::ActionSave(save) = &action.data {
+
+            // Unknown targets are reported with the action itself, below.
+            for target in save.targets.iter().filter(|target| is_target(target)) {
+
+                if !scraped.contains(target.as_str()) {
+                    report(
+                        locator.value(&format!("steps.{}", i)),
+                        format!("{} saves {} before it's scraped: add a {} step before this one", step_name, target, target)
+                    );
+                }
+            }
+        }
+    }
+
+    let mut targets = config.targets.iter().collect::<Vec<_>>();
+    targets.sort_by_key(|(name, _)| locator.key(&format!("targets.{}", name)));
+
+    for (name, target) in targets {
+
+        if target.metadata {
+            if !target.selector.is_empty() || target.parent.is_some() || !target.fields.is_empty() || target.links.is_some() || target.table.is_some() || !target.extract.is_element() || !target.transform.is_empty() || target.content {
+                report(locator.key(&format!("targets.{}", name)), format!("{} is a metadata target, which reads the whole page: it takes no other option", name));
+            }
+            continue;
+        }
+
+        if target.content && target.selector.is_empty() {
+            if target.parent.is_some() {
+                report(locator.value(&format!("targets.{}.parent", name)), format!("{} reads the main article of the page, which has no parent: give it a selector relative to the parent instead", name));
+            }
+        } else if target.selector.is_empty() {
+            report(locator.key(&format!("targets.{}", name)), format!("{} has no selector", name));
+        } else if let Err(e) = check_selector(&target.selector) {
+            report(locator.value(&format!("targets.{}.selector", name)), e.to_string());
+        }
+
+        check_fields(&target.fields, &format!("targets.{}.fields", name), locator, &mut report);
+
+        if !target.fields.is_empty() && !target.extract.is_element() {
+            report(locator.value(&format!("targets.{}.extract", name)), format!("{} has fields, so it yields records: give extract to its fields instead", name));
+        }
+
+        if target.links.is_some() && (!target.fields.is_empty() || !target.extract.is_element() || !target.transform.is_empty()) {
+            report(locator.value(&format!("targets.{}.links", name)), format!("{} has links, so it yields URLs: it can't have fields, extract or transform too", name));
+        }
+
+        if target.table.is_some() && (!target.fields.is_empty() || target.links.is_some() || !target.extract.is_element() || !target.transform.is_empty()) {
+            report(locator.value(&format!("targets.{}.table", name)), format!("{} is a table, so it yields its rows: it can't have fields, links, extract or transform too", name));
+        }
+
+        if target.content && (!target.fields.is_empty() || target.links.is_some() || target.table.is_some() || !target.extract.is_element() || !target.transform.is_empty()) {
+            report(locator.value(&format!("targets.{}.content", name)), format!("{} is a content target, so it yields Markdown and text: it can't have fields, links, table, extract or transform too", name));
+        }
+
+        if !target.fields.is_empty() && !target.transform.is_empty() {
+            report(locator.value(&format!("targets.{}.transform", name)), format!("{} has fields, so it yields records: give transform to its fields instead", name));
+        }
+
+        let Some(parent) = &target.parent else {
+            continue;
+        };
+
+        let position = locator.value(&format!("targets.{}.parent", name));
+
+        match config.targets.get(parent) {
+            None => report(position, format!("{} has an unknown parent {}", name, parent)),
+            Some(_) if parent == name => report(position, format!("{} can't be its own parent", name)),
+            Some(p) if p.metadata => report(position, format!("{} has a metadata target as parent, which matches no elements", name)),
+            Some(p) if p.content && p.selector.is_empty() => report(position, format!("{} has the main article of the page as parent, which matches no elements", name)),
+            Some(p) if p.parent.is_some() => report(position, format!("{} has a parent with a parent itself: use a selector relative to the topmost one", name)),
+            Some(_) => ()
+        }
+    }
+
+    let mut actions = config.actions.iter().collect::<Vec<_>>();
+    actions.sort_by_key(|(name, _)| locator.key(&format!("actions.{}", name)));
+
+    for (name, action) in actions {
+
+        let (field, selector) = match &action.data {
+            ActionData::ActionClick(a) => ("selector", &a.selector),
+            ActionData::ActionTypeInto(a) => ("target", &a.target),
+            ActionData::ActionScreenshot(a) => {
+                if a.format != "PNG" && a.format != "JPEG" {
+                    report(locator.value(&format!("actions.{}.format", name)), format!("Unknown screenshot format {}: use PNG or JPEG", a.format));
+                }
+                ("target", &a.target)
+            },
+            ActionData::ActionSave(save) => {
+                // Even if no step runs it yet.
+                for (j, target) in save.targets.iter().enumerate().filter(|(_, target)| !is_target(target)) {
+                    report(locator.value(&format!("actions.{}.targets.{}", name, j)), format!("{} saves the unknown target {}", name, target));
+                }
+                continue
+            },
+            ActionData::ActionWait(_) => continue
+        };
+
+        if let Err(e) = check_selector(selector) {
+            report(locator.value(&format!("actions.{}.{}", name, field)), e.to_string());
+        }
+    }
+
+    diagnostics
+}
+
+/// It checks the selectors of record fields, nested ones included.
+fn check_fields(fields: &BTreeMap<String, Field>, path: &str, locator: &Locator, report: &mut dyn FnMut(Option<(usize, usize)>, String)) {
+
+    for (name, field) in fields {
+
+        let path = format!("{}.{}", path, name);
+
+        if let Err(e) = check_selector(&field.selector) {
+            // A field may be its selector alone.
+            report(locator.value(&format!("{}.selector", path)), format!("field {}: {}", name, e));
+        }
+
+        check_fields(&field.fields, &format!("{}.fields", path), locator, report);
+    }
+}
+
+/// It checks the version of the pipeline format: version 2 pipelines must give every action a type,
+/// version 1 pipelines only get a deprecation warning for the actions without one.
+pub(crate) fn check_version(config: &PipelineConfig, content: &str, locator: &Locator, file: Option<&str>) -> Vec<Diagnostic> {
+
+    let diagnostic = |position: Option<(usize, usize)>, message: String| Diagnostic { file: file.map(str::to_string), position, message };
+
+    let version = config.pipeline.version;
+
+    if version == 0 || version > PIPELINE_VERSION {
+        return vec![diagnostic(locator.value("version"), format!("Unsupported pipeline version {}: use 1 or {}", version, PIPELINE_VERSION))];
+    }
+
+    let mut untyped = serde_yaml::from_str::<serde_yaml::Value>(content).ok()
+        .as_ref()
+        .and_then(|v| v.get("actions"))
+        .and_then(|actions| actions.as_mapping())
+        .map(|actions| actions.iter()
+            .filter(|(_, action)| action.get("type").is_none())
+            .filter_map(|(name, _)| name.as_str().map(str::to_string))
+            .collect::<Vec<String>>())
+        .unwrap_or_default();
+
+    untyped.sort_by_key(|name| locator.key(&format!("actions.{}", name)));
+
+    if untyped.is_empty() {
+        return vec![];
+    }
+
+    if version >= 2 {
+        return untyped.iter()
+            .map(|name| diagnostic(locator.key(&format!("actions.{}", name)), format!("{} has no type: give it one of CLICK, SCREENSHOT, WAIT, TYPE_INTO or SAVE", name)))
+            .collect();
+    }
+
+    warn!(
+        "{}: actions without a type ({}) are deprecated: give them one, and set version: {}",
+        file.unwrap_or("pipeline"), untyped.join(", "), PIPELINE_VERSION
+    );
+
+    vec![]
+}
+
+/// It reads every action on its own, to tell which ones are malformed.
+pub(crate) fn check_actions(content: &str, locator: &Locator, file: Option<&str>) -> Vec<Diagnostic> {
+
+    let value = serde_yaml::from_str::<serde_yaml::Value>(content).ok();
+
+    let Some(actions) = value.as_ref().and_then(|v| v.get("actions")).and_then(|a| a.as_mapping()) else {
+        return vec![];
+    };
+
+    let mut diagnostics = actions.iter()
+        .filter_map(|(name, action)| {
+            let name = name.as_str()?;
+            let err = Action::deserialize(action.clone()).err()?;
+
+            Some(Diagnostic {
+                file: file.map(str::to_string),
+                position: locator.key(&format!("actions.{}", name)),
+                message: format!("actions.{}: {}", name, err)
+            })
+        })
+        .collect::<Vec<Diagnostic>>();
+
+    diagnostics.sort_by_key(|d| d.position);
+
+    diagnostics
+}
+
+/// It turns a YAML or deserialization error into a diagnostic, pinned to where the parser stopped.
+pub(crate) fn yaml_diagnostic(err: &serde_yaml::Error, file: Option<&str>) -> Diagnostic {
+
+    let position = err.location().map(|l| (l.line(), l.column()));
+
+    // The location is already in the file:line:column prefix.
+    let message = match position {
+        Some((line, column)) => err.to_string().replacen(&format!(" at line {} column {}", line, column), "", 1),
+        None => err.to_string()
+    };
+
+    Diagnostic { file: file.map(str::to_string), position, message }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    const HEADER: &str = "version: 2\nname: t\nurl: https://a.com\nheadless: true\nout_dir: out\ntimeout: 5\n";
+
+    fn check(body: &str) -> Vec<Diagnostic> {
+        PipelineConfig::check(&format!("{}{}", HEADER, body), None)
+    }
+
+    #[test]
+    fn unused_save_actions_are_checked() {
+
+        let diagnostics = check("targets:\n  a:\n    selector: //a\nactions:\n  save1:\n    type: SAVE\n    targets:\n      - a\n      - b\nsteps:\n  - a\n");
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "save1 saves the unknown target b");
+        assert_eq!(diagnostics[0].position, Some((15, 9)));
+    }
+
+    #[test]
+    fn saves_of_aliases_are_known() {
+
+        let diagnostics = check("targets:\n  a:\n    selector: //a\nactions:\n  save1:\n    type: SAVE\n    targets: [a, b]\nsteps:\n  - a\n  - b from a\n  - save1\n");
+
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    }
+
+    #[test]
+    fn saves_of_action_aliases_are_unknown() {
+
+        let diagnostics = check("targets:\n  a:\n    selector: //a\nactions:\n  save1:\n    type: SAVE\n    targets: [a, save2]\nsteps:\n  - a\n  - save2 from save1\n");
+
+        assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+        assert_eq!(diagnostics[0].message, "save1 saves the unknown target save2");
+    }
+
+    #[test]
+    fn block_style_is_located() {
+
+        let locator = Locator::scan("targets:\n  a:\n    selector: //a\n    parent: b\nsteps:\n  - a\n  - save1\n");
+
+        assert_eq!(locator.value("targets.a.selector"), Some((3, 15)));
+        assert_eq!(locator.value("targets.a.parent"), Some((4, 13)));
+        assert_eq!(locator.value("steps.1"), Some((7, 5)));
+        assert_eq!(locator.key("targets.a.fields.x"), Some((2, 3)));
+    }
+
+    #[test]
+    fn multi_line_scalars_are_not_looked_into() {
+
+        let locator = Locator::scan("targets:\n  a:\n    parent: b\n    selector: \"//a[@title='see\n    parent: here']\"\n    extract: text\n");
+
+        assert_eq!(locator.value("targets.a.parent"), Some((3, 13)));
+        assert_eq!(locator.value("targets.a.extract"), Some((6, 14)));
+
+        let locator = Locator::scan("targets:\n  a:\n    selector: >\n      //a\n    parent: b\n");
+
+        assert_eq!(locator.value("targets.a.parent"), Some((5, 13)));
+    }
+
+    #[test]
+    fn flow_collections_are_located_at_their_key() {
+
+        let locator = Locator::scan("targets:\n  b: {selector: //b,\n  parent: missing}\n  a:\n    selector: //a\nsteps: [a,\n  b]\n");
+
+        assert_eq!(locator.value("targets.b.parent"), Some((2, 3)));
+        assert_eq!(locator.key("targets.parent"), Some((1, 1)));
+        assert_eq!(locator.value("targets.a.selector"), Some((5, 15)));
+        assert_eq!(locator.value("steps.1"), Some((6, 1)));
+
+        let diagnostics = check("targets:\n  b: {selector: //b,\n    parent: missing}\nactions: {}\nsteps: [b]\n");
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].position, Some((8, 3)));
+    }
+
+    #[test]
+    fn quoted_keys_may_hold_colons() {
+
+        let locator = Locator::scan("targets:\n  \"a: b\":\n    selector: //a\n  c:\n    selector: https://c\n");
+
+        assert_eq!(locator.value("targets.a: b.selector"), Some((3, 15)));
+        assert_eq!(locator.value("targets.c.selector"), Some((5, 15)));
+    }
+}
@@ -1,5 +1,7 @@
 use std::{io::{self, BufRead}, fs::File};
 
+use crate::error::{Error, Result};
+
 #[derive(Eq, Hash, PartialEq, Default)]
 pub enum ProxyField {
     #[default] Host,
@@ -57,7 +59,7 @@ impl CSVProxyListBuilder {
 
             },
             false => {
-                std::fs::File::open(&self.source).expect(format!("Proxy file not found: {}", &self.source).as_str())
+                std::fs::File::open(&self.source).unwrap_or_else(|_| panic!("Proxy file not found: {}", &self.source))
             }
             
         };
@@ -74,20 +76,17 @@ impl CSVProxyListBuilder {
         
         let lines = std::io::BufReader::new(&source_file).lines();
 
-        for line in lines {
-            if let Ok(proxy_string) = line {
-                let parts = proxy_string.split(&self.separator).collect::<Vec<&str>>();
-
-                let p = SimpleProxy {
-                        host: parts.get(host_pos).unwrap().to_string(),
-                        port: parts.get(port_pos).unwrap().to_string(),
-                        user: parts.get(user_pos).unwrap().to_string(),
-                        password: parts.get(password_pos).unwrap().to_string(),
-                    };
+        for proxy_string in lines.map_while(|line| line.ok()) {
+            let parts = proxy_string.split(&self.separator).collect::<Vec<&str>>();
 
-                proxies.push(p);
+            let p = SimpleProxy {
+                    host: parts.get(host_pos).unwrap().to_string(),
+                    port: parts.get(port_pos).unwrap().to_string(),
+                    user: parts.get(user_pos).unwrap().to_string(),
+                    password: parts.get(password_pos).unwrap().to_string(),
+                };
 
-            }
+            proxies.push(p);
         }
 
         proxies
@@ -112,4 +111,16 @@ impl SimpleProxy {
 
     }
 
+    /// It converts the proxy into a reqwest one, with basic auth if credentials are provided.
+    pub fn to_reqwest_proxy(&self) -> Result<reqwest::Proxy> {
+
+        let proxy = reqwest::Proxy::all(self.get_address())
+            .map_err(|e| Error::Proxy(format!("{}: {}", self.get_address(), e)))?;
+
+        match self.user.is_empty() {
+            true => Ok(proxy),
+            false => Ok(proxy.basic_auth(&self.user, &self.password))
+        }
+    }
+
 }
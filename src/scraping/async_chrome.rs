@@ -0,0 +1,110 @@
+use std::{path::PathBuf, sync::{Arc, Mutex}};
+
+use crate::error::{Error, Result};
+
+use super::{ScrapingResult, ScreenshotFormat, chrome::{Scraper, ScraperBuilder}};
+
+/// AsyncScraper is the tokio flavour of Scraper. <br>
+/// Every operation runs on tokio's blocking pool, so it can be awaited from async code without stalling the runtime.
+/// Clones drive the same browser tab, one operation at a time.
+#[derive(Clone)]
+pub struct AsyncScraper {
+    scraper: Arc<Mutex<Scraper>>
+}
+
+impl ScraperBuilder {
+
+    /// It materializes a new AsyncScraper instance with the provided properties.
+    pub async fn try_build_async(&self) -> Result<AsyncScraper> {
+
+        let builder = self.clone();
+        let scraper = run_blocking(move || builder.try_build()).await?;
+
+        Ok(AsyncScraper::from(scraper))
+    }
+}
+
+impl From<Scraper> for AsyncScraper {
+    fn from(scraper: Scraper) -> Self {
+        AsyncScraper { scraper: Arc::new(Mutex::new(scraper)) }
+    }
+}
+
+impl AsyncScraper {
+
+    /// It runs f on the wrapped Scraper, from tokio's blocking pool.
+    async fn with_scraper<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Scraper) -> Result<T> + Send + 'static
+    {
+        let scraper = self.scraper.clone();
+
+        run_blocking(move || {
+            let mut scraper = scraper.lock().map_err(|_| Error::Browser("a previous operation panicked".to_string()))?;
+            f(&mut scraper)
+        }).await
+    }
+
+    pub async fn navigate_to<S: Into<String>>(&self, url: S) -> Result<()> {
+
+        let url = url.into();
+        self.with_scraper(move |s| s.try_navigate_to(url).map(|_| ())).await
+    }
+
+    pub async fn find_elements_by_css<S: Into<String>>(&self, name: S, target: S) -> Result<()> {
+
+        let (name, target) = (name.into(), target.into());
+        self.with_scraper(move |s| s.try_find_elements_by_css(name, target).map(|_| ())).await
+    }
+
+    pub async fn find_elements_by_xpath<S: Into<String>>(&self, name: S, target: S) -> Result<()> {
+
+        let (name, target) = (name.into(), target.into());
+        self.with_scraper(move |s| s.try_find_elements_by_xpath(name, target).map(|_| ())).await
+    }
+
+    pub async fn click<S: Into<String>>(&self, name: S, target: S) -> Result<()> {
+
+        let (name, target) = (name.into(), target.into());
+        self.with_scraper(move |s| s.try_click(name, target).map(|_| ())).await
+    }
+
+    pub async fn type_into<S: Into<String>>(&self, name: S, target: S, text: S) -> Result<()> {
+
+        let (name, target, text) = (name.into(), target.into(), text.into());
+        self.with_scraper(move |s| s.try_type_into(name, target, text).map(|_| ())).await
+    }
+
+    pub async fn screenshot<S: Into<String>>(&self, name: S, target: S, format: ScreenshotFormat) -> Result<()> {
+
+        let (name, target) = (name.into(), target.into());
+        self.with_scraper(move |s| s.try_screenshot(name, target, format).map(|_| ())).await
+    }
+
+    /// Unlike Scraper::sleep, it yields to the runtime instead of blocking a thread.
+    pub async fn sleep(&self, seconds: u64) {
+        tokio::time::sleep(std::time::Duration::from_secs(seconds)).await;
+    }
+
+    pub async fn save(&self, targets: Vec<String>, flatten: bool) -> Result<PathBuf> {
+
+        self.with_scraper(move |s| s.try_save(&targets, &flatten)).await
+    }
+
+    pub async fn collect(&self) -> Result<ScrapingResult> {
+
+        self.with_scraper(|s| Ok(s.collect())).await
+    }
+}
+
+/// It runs f on tokio's blocking pool, turning a panic into an error.
+pub(crate) async fn run_blocking<T, F>(f: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T> + Send + 'static
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .unwrap_or_else(|e| Err(Error::Browser(format!("scraping task failed: {}", e))))
+}
@@ -1,8 +1,8 @@
-use std::{str::FromStr, sync::Arc, collections::HashMap};
+use std::{str::FromStr, sync::{Arc, Mutex}, collections::{BTreeMap, HashMap}, path::PathBuf, rc::Rc, time::Duration};
 
 use headless_chrome::{
     browser::{
-        tab::RequestPausedDecision,
+        tab::{NoElementFound, RequestPausedDecision},
         transport::{SessionId, Transport},
     },
     protocol::{cdp::{
@@ -11,33 +11,29 @@ use headless_chrome::{
         },
         Network::ResourceType
     }, self},
+    types::RemoteError,
+    util::Timeout,
     Browser, Element, LaunchOptions, Tab,
 };
 
-use log::info;
+use log::{info, warn};
 use rand::{rngs::StdRng, Rng, SeedableRng};
-use serde::Serialize;
 
-use crate::proxy::SimpleProxy;
+use crate::{proxy::SimpleProxy, error::{Error, Result}};
 
+use super::{Backend, DOMElement, ScrapingResult, ScreenshotFormat, save_elements, query, rate_limit::{HostLimiter, RateLimit, is_throttling, parse_retry_after}, robots::RobotsTxt, extract::Extract, links::Links, record::Field, scrape::Scrape, table::Table, selector::{Selector, SelectorKind}, transform::Transform, html::{HtmlDocument, number_value}};
 
-#[derive(Clone, Serialize)]
-/// It contains all the metadata of a scraped element.
-pub struct DOMElement {
-    
-    pub text: String,
-    pub attrs: HashMap<String, String>
-}
+/// What the tab's event handlers noticed since the last navigation.
+#[derive(Default)]
+struct TabState {
+    /// Last failure of the request interceptor, if any. It lets navigate_to tell a dead proxy
+    /// (or a page disallowed by robots.txt) apart from a dead page.
+    intercept_error: Mutex<Option<Error>>,
 
-enum Selector {
-    CSS,
-    XPath
+    /// Status and Retry-After of the first document answered.
+    document_status: Mutex<Option<(u16, Option<Duration>)>>
 }
 
-pub enum ScreenshotFormat {
-    JPEG,
-    PNG
-}
 
 /// Scraper is the main player of this crate. <br>
 /// It wraps a Chrome browser and high level interfaces to scrape DOM elements and run automated actions.
@@ -51,11 +47,16 @@ pub struct Scraper {
 
     current_url: Option<String>,
 
-    elements: HashMap<String, Vec<DOMElement>>,
+    /// What was scraped since the last collect.
+    scraped: ScrapingResult,
+
+    save_dir: String,
 
-    screenshots: HashMap<String, Vec<u8>>,
+    tab_state: Arc<TabState>,
 
-    save_dir: String
+    host_limiter: Option<Arc<HostLimiter>>,
+
+    robots: Option<Arc<RobotsTxt>>
 }
 
 /// Just a builder for the Scraper struct. <br>
@@ -66,24 +67,20 @@ pub struct ScraperBuilder {
     pub proxies: Vec<SimpleProxy>,
     pub default_timeout: u64,
     pub headless: bool,
-    pub save_dir: String
+    pub save_dir: String,
+    pub host_limiter: Option<Arc<HostLimiter>>,
+    pub robots: Option<Arc<RobotsTxt>>
 }
 
 impl Default for ScraperBuilder {
     fn default() -> Self {
 
         let save_dir = ".scraping_results/".to_string();
-        Self { proxies: vec![], default_timeout: 5, headless: true, save_dir: save_dir.clone() }
+        Self { proxies: vec![], default_timeout: 5, headless: true, save_dir, host_limiter: None, robots: None }
     }
 }
 
 
-pub struct ScrapingResult {
-
-    pub elements: HashMap<String, Vec<DOMElement>>,
-    pub screenshots: HashMap<String, Vec<u8>>
-}
-
 impl ScraperBuilder {
 
     pub fn set_headless(&mut self, headless: bool) -> &mut ScraperBuilder {
@@ -105,159 +102,326 @@ impl ScraperBuilder {
         self
     }
 
+    /// Scrapers built from now on share the per-host limits of rate_limit.
+    pub fn set_rate_limit(&mut self, rate_limit: RateLimit) -> &mut ScraperBuilder {
+        self.host_limiter = Some(Arc::new(HostLimiter::new(rate_limit)));
+        self
+    }
+
+    /// Scrapers built from now on share the per-host limits of host_limiter, e.g. with SimpleScrapers.
+    pub fn set_host_limiter(&mut self, host_limiter: Arc<HostLimiter>) -> &mut ScraperBuilder {
+        self.host_limiter = Some(host_limiter);
+        self
+    }
+
+    /// Scrapers built from now on comply with robots.txt, as user_agent. See RobotsTxt.
+    pub fn set_robots_txt(&mut self, user_agent: &str) -> Result<&mut ScraperBuilder> {
+        let robots = RobotsTxt::new(user_agent, &self.proxies)?;
+        Ok(self.set_robots(Arc::new(robots)))
+    }
+
+    /// Scrapers built from now on comply with robots.txt, sharing the cache of robots, e.g. with SimpleScrapers.
+    pub fn set_robots(&mut self, robots: Arc<RobotsTxt>) -> &mut ScraperBuilder {
+
+        // Crawl-delay needs a limiter to be applied.
+        if self.host_limiter.is_none() {
+            self.host_limiter = Some(Arc::new(HostLimiter::new(RateLimit::default())));
+        }
+
+        self.robots = Some(robots);
+        self
+    }
+
     /// It materializes a new Scraper instance with the provided properties.
+    /// 
+    /// Panics if the browser can't be launched, see try_build for a fallible version.
     pub fn build(&self) -> Scraper {
+
+        match self.try_build() {
+            Ok(scraper) => scraper,
+            Err(e) => panic!("{}", e)
+        }
+    }
+
+    /// It materializes a new Scraper instance with the provided properties, reporting launch and proxy failures.
+    pub fn try_build(&self) -> Result<Scraper> {
        
-        std::fs::create_dir(&self.save_dir).unwrap_or(());
+        std::fs::create_dir_all(&self.save_dir).unwrap_or(());
+
+        // Fail fast on malformed proxies, rather than on the first intercepted request.
+        for proxy in &self.proxies {
+            proxy.to_reqwest_proxy()?;
+        }
 
         let browser = Browser::new(LaunchOptions {
             headless: self.headless,
             ..Default::default()
         })
-        .unwrap();
+        .map_err(|e| Error::Launch(e.to_string()))?;
 
-        let tab = browser.wait_for_initial_tab().unwrap();
+        let tab = browser.wait_for_initial_tab().map_err(|e| Error::Launch(e.to_string()))?;
 
-        tab.set_default_timeout(std::time::Duration::from_secs(self.default_timeout));
-        tab.enable_fetch(None, None).unwrap();
+        let tab_state: Arc<TabState> = Arc::default();
 
-        let proxies = self.proxies.clone();
+        setup_tab(&tab, self.default_timeout, &self.proxies, self.robots.as_ref(), &tab_state)?;
 
-       /*  let sync_event = Arc::new(move |event: &Event| match event {
-            Event::PageLifecycleEvent(lifecycle) => {
-                if lifecycle.params.name == "DOMContentLoaded" {
-                    println!("{}", "loaded");
-                }
+        Ok(Scraper {
+            proxy: self.proxies.clone(),
+            default_timeout: self.default_timeout,
+            browser,
+            tab,
+            current_url: None,
+            scraped: ScrapingResult::default(),
+            save_dir: self.save_dir.clone(),
+            tab_state,
+            host_limiter: self.host_limiter.clone(),
+            robots: self.robots.clone()
+        })
+    }
+}
+
+/// It prepares a fresh tab: default timeout, fetch domain, document status tracking, and interception for proxies and robots.txt.
+fn setup_tab(
+    tab: &Arc<Tab>,
+    default_timeout: u64,
+    proxies: &[SimpleProxy],
+    robots: Option<&Arc<RobotsTxt>>,
+    tab_state: &Arc<TabState>
+) -> Result<()> {
+
+    tab.set_default_timeout(std::time::Duration::from_secs(default_timeout));
+    tab.enable_fetch(None, None).map_err(|e| Error::Launch(e.to_string()))?;
+
+    let state = tab_state.clone();
+
+    // Subframes are documents too: only the first one answered, the page itself, counts.
+    tab.register_response_handling("document_status", Box::new(move |params, _| {
+
+        if params.Type != ResourceType::Document {
+            return;
+        }
+
+        let mut status = state.document_status.lock().unwrap();
+
+        if status.is_none() {
+            let retry_after = match &params.response.headers.0 {
+                Some(serde_json::Value::Object(headers)) => headers.iter()
+                    .find(|(k, _)| k.eq_ignore_ascii_case("retry-after"))
+                    .and_then(|(_, v)| v.as_str())
+                    .and_then(parse_retry_after),
+                _ => None
+            };
+
+            *status = Some((params.response.status as u16, retry_after));
+        }
+    })).map_err(|e| Error::Launch(e.to_string()))?;
+
+    let proxies = proxies.to_vec();
+
+   /*  let sync_event = Arc::new(move |event: &Event| match event {
+        Event::PageLifecycleEvent(lifecycle) => {
+            if lifecycle.params.name == "DOMContentLoaded" {
+                println!("{}", "loaded");
             }
-            _ => {}
-        });
-    
-        tab.add_event_listener(sync_event).unwrap(); */
-
-        /* 
-        Tab interception is useful to:
-            - Enable request-level proxying
-            - Block requests based on mime type  
-        */
-
-        if proxies.len() > 0 {
-
-            tab.enable_request_interception(Arc::new(
-                move |_: Arc<Transport>, _: SessionId, intercepted: RequestPausedEvent| {
-                    // !intercepted.params.request.url.ends_with(".jpg") && !intercepted.params.request.url.ends_with(".png") && !intercepted.params.request.url.ends_with(".js")
+        }
+        _ => {}
+    });
+
+    tab.add_event_listener(sync_event).unwrap(); */
+
+    /* 
+    Tab interception is useful to:
+        - Enable request-level proxying
+        - Block documents disallowed by robots.txt, e.g. reached by clicking a link
+        - Block requests based on mime type  
+    */
+
+    if !proxies.is_empty() || robots.is_some() {
+
+        let state = tab_state.clone();
+        let robots = robots.cloned();
+
+        tab.enable_request_interception(Arc::new(
+            move |_: Arc<Transport>, _: SessionId, intercepted: RequestPausedEvent| {
+                // !intercepted.params.request.url.ends_with(".jpg") && !intercepted.params.request.url.ends_with(".png") && !intercepted.params.request.url.ends_with(".js")
+                
+                if intercepted.params.request.url.starts_with("file:///") {
+                    return RequestPausedDecision::Continue(None);
+                }
+
+                //println!("{:?}", intercepted.params.resource_Type);
+
+                if intercepted.params.resource_Type == ResourceType::Document {
                     
-                    if intercepted.params.request.url.starts_with("file:///") || proxies.len() == 0 {
+                    let request_id = intercepted.params.request_id.clone();
+                    let url = intercepted.params.request.url.clone();
+
+                    if robots.as_ref().is_some_and(|r| !r.is_allowed(&url)) {
+                        info!("[ROBOTS] {} is disallowed, blocking it", url);
+                        *state.intercept_error.lock().unwrap() = Some(Error::Disallowed { url });
+
+                        return RequestPausedDecision::Fail(FailRequest {
+                            request_id,
+                            error_reason: headless_chrome::protocol::cdp::Network::ErrorReason::BlockedByClient,
+                        });
+                    }
+
+                    if proxies.is_empty() {
                         return RequestPausedDecision::Continue(None);
                     }
-                    let v = intercepted.params.request.headers.0.unwrap();
-
-                    let headersmap = v.as_object().unwrap().clone();
-
-                    //println!("{:?}", intercepted.params.resource_Type);
-
-                    if intercepted.params.resource_Type == ResourceType::Document {
-                        
-                        let mut reqwest_proxy: Option<reqwest::Proxy> = None;
-                        if proxies.len() > 0 {
-                            let mut rng: StdRng = SeedableRng::from_entropy();
-                            let rnd_i = rng.gen_range(0..proxies.len());
-                            let proxy = proxies.get(rnd_i).unwrap().to_owned();
-                            //println!("{}", proxy.get_address());
-
-                            reqwest_proxy = match proxy.user == "" {
-                                true => Some(reqwest::Proxy::all(proxy.get_address()).unwrap()),
-                                false => Some(
-                                            reqwest::Proxy::all(proxy.get_address())
-                                                .unwrap()
-                                                .basic_auth(&proxy.user, &proxy.password),
-                                        )
-                            };
+
+                    match fetch_through_proxy(&proxies, intercepted) {
+                        Ok(fulfill_request) => RequestPausedDecision::Fulfill(fulfill_request),
+                        Err(e) => {
+                            warn!("{}", e);
+                            *state.intercept_error.lock().unwrap() = Some(e);
+
+                            RequestPausedDecision::Fail(FailRequest {
+                                request_id,
+                                error_reason:
+                                    headless_chrome::protocol::cdp::Network::ErrorReason::ConnectionFailed,
+                            })
                         }
+                    }
+                } else { // TODO: block some resources
+                    RequestPausedDecision::Continue(None)
+                }
+            },
+        )).map_err(|e| Error::Proxy(e.to_string()))?;
+    }
 
-                        let mut req_headers: Vec<HeaderEntry> = vec![];
+    Ok(())
+}
 
-                        // Build a header map to simulate a real browser request. 
-                        let mut headers = reqwest::header::HeaderMap::new();
+/// It downloads the intercepted document through a random proxy, forwarding the browser headers.
+fn fetch_through_proxy(proxies: &[SimpleProxy], intercepted: RequestPausedEvent) -> Result<FulfillRequest> {
 
-                        for (k, val) in headersmap {
-                            let myk = k.to_owned();
-                            let myv = val.to_owned();
-                            let myv = myv.to_string();
+    let mut rng: StdRng = SeedableRng::from_entropy();
+    let proxy = &proxies[rng.gen_range(0..proxies.len())];
+    //println!("{}", proxy.get_address());
 
-                            let key = reqwest::header::HeaderName::from_str(&myk).unwrap();
-                            let vall = reqwest::header::HeaderValue::from_str(&myv);
+    let reqwest_proxy = proxy.to_reqwest_proxy()?;
 
-                            headers.insert(key, vall.unwrap());
-                        }
+    // Build a header map to simulate a real browser request. 
+    let mut headers = reqwest::header::HeaderMap::new();
 
-                        req_headers.push(HeaderEntry {
-                            name: "Content-Type".to_string(),
-                            value: "text/html; charset=utf-8".to_string(),
-                        });
+    if let Some(serde_json::Value::Object(headersmap)) = intercepted.params.request.headers.0 {
+        for (k, val) in headersmap {
+            let key = reqwest::header::HeaderName::from_str(&k);
+            let vall = reqwest::header::HeaderValue::from_str(val.as_str().unwrap_or_default());
 
-                        let req_builder = reqwest::blocking::Client::builder();
-
-                        let req_builder = match reqwest_proxy {
-                            Some(p) => req_builder.proxy(p),
-                            None => req_builder,
-                        };
-
-                        let client = req_builder
-                            .default_headers(headers.to_owned())
-                            .build()
-                            .unwrap();
-
-                        let res = client
-                            .get(intercepted.params.request.url)
-                            .send()
-                            .unwrap()
-                            .text()
-                            .unwrap();
-
-                        let fulfill_request = FulfillRequest {
-                            request_id: intercepted.params.request_id,
-                            response_code: 200,
-                            response_headers: Some(req_headers),
-                            binary_response_headers: None,
-                            body: Some(base64::encode(res)),
-                            response_phrase: None,
-                        };
-
-                        RequestPausedDecision::Fulfill(fulfill_request)
-                    } else if intercepted.params.resource_Type != ResourceType::Document {
-                        RequestPausedDecision::Continue(None)
-                    } else { // TODO: block some resources
-                        RequestPausedDecision::Fail(FailRequest {
-                            request_id: intercepted.params.request_id,
-                            error_reason:
-                                headless_chrome::protocol::cdp::Network::ErrorReason::BlockedByClient,
-                        })
-                    }
-                },
-            )).expect("You should check the validity of your proxies or the URL provided.");
-        }
-        Scraper {
-            proxy: self.proxies.clone(),
-            default_timeout: self.default_timeout,
-            browser,
-            tab,
-            current_url: None,
-            elements: HashMap::default(),
-            screenshots: HashMap::default(),
-            save_dir: self.save_dir.clone()
+            if let (Ok(key), Ok(vall)) = (key, vall) {
+                headers.insert(key, vall);
+            }
         }
     }
+
+    let mut req_headers: Vec<HeaderEntry> = vec![HeaderEntry {
+        name: "Content-Type".to_string(),
+        value: "text/html; charset=utf-8".to_string(),
+    }];
+
+    let client = reqwest::blocking::Client::builder()
+        .proxy(reqwest_proxy)
+        .default_headers(headers)
+        .build()
+        .map_err(|e| Error::Proxy(e.to_string()))?;
+
+    let to_error = |e: reqwest::Error| Error::Proxy(format!("{} via {}: {}", intercepted.params.request.url, proxy.get_address(), e));
+
+    let resp = client
+        .get(&intercepted.params.request.url)
+        .send()
+        .map_err(to_error)?;
+
+    // The status and Retry-After go through, so that throttling is noticed behind proxies too.
+    let status = resp.status().as_u16();
+
+    if let Some(retry_after) = resp.headers().get(reqwest::header::RETRY_AFTER).and_then(|v| v.to_str().ok()) {
+        req_headers.push(HeaderEntry { name: "Retry-After".to_string(), value: retry_after.to_string() });
+    }
+
+    let res = resp.text().map_err(to_error)?;
+
+    Ok(FulfillRequest {
+        request_id: intercepted.params.request_id,
+        response_code: status as u32,
+        response_headers: Some(req_headers),
+        binary_response_headers: None,
+        body: Some(base64::encode(res)),
+        response_phrase: None,
+    })
 }
 
+/// It turns a failed element query into the matching crate error.
+fn query_error(name: &str, selector: &str, err: anyhow::Error) -> Error {
+
+    if err.is::<NoElementFound>() || err.is::<Timeout>() {
+        return Error::ElementNotFound { name: name.to_string(), selector: selector.to_string() };
+    }
+
+    if err.is::<RemoteError>() {
+        return Error::InvalidSelector { selector: selector.to_string(), reason: err.to_string() };
+    }
+
+    Error::Browser(err.to_string())
+}
 
 
 
 impl Scraper {
     pub fn navigate_to<S: AsRef<str> + Clone>(&mut self, url: S) -> &mut Scraper {
         
+        if let Err(e) = self.try_navigate_to(url) {
+            warn!("{}", e);
+        }
+
+        self
+    }
+
+    pub fn try_navigate_to<S: AsRef<str> + Clone>(&mut self, url: S) -> Result<&mut Scraper> {
+        
+        let url = url.as_ref();
         let t0 = std::time::Instant::now();
         info!("[NAVIGATE_TO] Start");
-        self.tab.navigate_to(url.as_ref()).unwrap();
+
+        self.current_url = None;
+        self.tab_state.intercept_error.lock().unwrap().take();
+        self.tab_state.document_status.lock().unwrap().take();
+
+        if let Some(robots) = &self.robots {
+            robots.check(url, self.host_limiter.as_ref())?;
+        }
+
+        let permit = self.host_limiter.as_ref().map(|l| l.acquire(url));
+
+        // headless_chrome panics when the page doesn't load within the tab timeout.
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            self.tab.navigate_to(url).map(|_| ())
+        }));
+
+        let intercept_error = self.tab_state.intercept_error.lock().unwrap().take();
+
+        match (res, intercept_error) {
+            (_, Some(e)) => return Err(e),
+            (Err(_), None) => return Err(Error::Timeout { url: url.to_string() }),
+            (Ok(Err(e)), None) => return Err(Error::Navigation { url: url.to_string(), reason: e.to_string() }),
+            (Ok(Ok(())), None) => ()
+        }
+
+        drop(permit);
+
+        if let Some((status, retry_after)) = self.tab_state.document_status.lock().unwrap().take() {
+
+            if is_throttling(status) {
+                if let (Some(limiter), Some(retry_after)) = (&self.host_limiter, retry_after) {
+                    limiter.pause(url, retry_after);
+                }
+
+                return Err(Error::RateLimited { url: url.to_string(), status, retry_after });
+            }
+        }
+
         info!("[NAVIGATE_TO] DONE in {}", t0.elapsed().as_secs());
         /* if let Err(_) = self.tab.wait_until_navigated() {
             //println!("Page load timeout..");
@@ -267,9 +431,27 @@ impl Scraper {
             .wait_for_xpath_with_custom_timeout("//body", std::time::Duration::from_secs(5)){
                 println!("Page load timeout..");
             } */
-        self.current_url = Some(url.as_ref().to_string());
+        self.current_url = Some(url.to_string());
 
-        self
+        Ok(self)
+    }
+
+    /// It replaces the tab with a fresh one and closes the old one, releasing the memory the visited pages held on to. <br>
+    /// The browser process is kept.
+    pub fn recycle_tab(&mut self) -> Result<&mut Scraper> {
+
+        let tab = self.browser.new_tab().map_err(|e| Error::Launch(e.to_string()))?;
+        setup_tab(&tab, self.default_timeout, &self.proxy, self.robots.as_ref(), &self.tab_state)?;
+
+        let old_tab = std::mem::replace(&mut self.tab, tab);
+
+        if let Err(e) = old_tab.close(false) {
+            warn!("Couldn't close the recycled tab: {}", e);
+        }
+
+        self.current_url = None;
+
+        Ok(self)
     }
 
     pub fn sleep(&self, seconds: u64) -> &Scraper {
@@ -279,62 +461,56 @@ impl Scraper {
     }
 
     pub fn collect(&mut self) -> ScrapingResult {
-
-        let res = ScrapingResult {
-            elements: self.elements.clone(),
-            screenshots: self.screenshots.clone()
-        };
-        
-        self.elements.clear();
-        self.screenshots.clear();
-        res
+        std::mem::take(&mut self.scraped)
     }
 
     pub fn find_elements_by_css<S: AsRef<str> + Clone>(&mut self, name: S, target: S) -> &mut Scraper 
     {
-        let target = target.as_ref();
-        let name = name.as_ref();
-
-        if target.starts_with("/") {
-            println!("Invalid CSS selector: {}", target);
-            return self;
+        if let Err(e) = self.query_css(name.as_ref(), target.as_ref()) {
+            warn!("{}", e);
         }
 
-        if let None = self.current_url {
-            println!("Didn't you call navigate_to(url) ?");
+        self
+    }
 
-            return self;
-        }
+    pub fn try_find_elements_by_css<S: AsRef<str> + Clone>(&mut self, name: S, target: S) -> Result<&mut Scraper> 
+    {
+        let name = name.as_ref();
+        let selector = Selector::parse_as(target.as_ref(), SelectorKind::Css);
+        let target = selector.expect(SelectorKind::Css)?.expression();
 
-        let query_result = self.tab.wait_for_elements(&target);
+        if self.current_url.is_none() {
+            return Err(Error::NotNavigated);
+        }
 
-        let elements = match query_result {
-            Ok(elements) => elements,
-            Err(_) => { vec![]}, // println!("Element {} not found", name);
-        };
+        let elements = self.tab.wait_for_elements(target).map_err(|e| query_error(name, target, e))?;
 
-        let dom_els: Vec<DOMElement> = elements.iter().map(|el| self.build_dom_element(el)).collect();
+        let dom_els = elements.iter().map(|el| self.build_dom_element(el)).collect::<Result<Vec<DOMElement>>>()?;
 
-        self.elements.insert(name.to_string(), dom_els);
+        self.scraped.values.remove(name);
+        self.scraped.elements.insert(name.to_string(), dom_els);
         
-
-        return self;
+        Ok(self)
     }
 
     pub fn find_elements_by_xpath<S: AsRef<str> + Clone>(&mut self, name: S, target: S) -> &mut Scraper {
         
-        let target = target.as_ref();
-        let name = name.as_ref();
-
-        if !target.starts_with("/") {
-            println!("Invalid XPath selector: {}", target);
-            return self;
+        if let Err(e) = self.query_xpath(name.as_ref(), target.as_ref()) {
+            warn!("{}", e);
         }
 
-        if let None = self.current_url {
-            println!("Didn't you call navigate_to(url) ?");
+        self
+    }
 
-            return self;
+    pub fn try_find_elements_by_xpath<S: AsRef<str> + Clone>(&mut self, name: S, target: S) -> Result<&mut Scraper> {
+        
+        let name = name.as_ref();
+        let selector = Selector::parse_as(target.as_ref(), SelectorKind::XPath);
+        let target = selector.expect(SelectorKind::XPath)?.to_xpath().unwrap_or_default();
+        let target = target.as_str();
+
+        if self.current_url.is_none() {
+            return Err(Error::NotNavigated);
         }
 
         /* self.tab.reload(false, None).unwrap();
@@ -345,56 +521,257 @@ impl Scraper {
 
         let t0 = std::time::Instant::now();
         info!("[XPATH] Start");
-        let query_result = self.tab.wait_for_elements_by_xpath(&target);
 
-        let elements = match query_result {
-            Ok(elements) => elements,
-            Err(_) => {vec![]}, //println!("Element {} not found", name);
-        };
+        if let Some(values) = self.xpath_values(name, target)? {
+            self.scraped.elements.remove(name);
+            self.scraped.values.insert(name.to_string(), values);
+
+            return Ok(self);
+        }
+
+        let elements = self.tab.wait_for_elements_by_xpath(target).map_err(|e| query_error(name, target, e))?;
+
+        // Nothing had matched yet: what showed up since tells values, e.g. of //a/@href, from elements.
+        if let Some(values) = self.xpath_values(name, target)? {
+            self.scraped.elements.remove(name);
+            self.scraped.values.insert(name.to_string(), values);
+
+            return Ok(self);
+        }
 
-        let dom_els: Vec<DOMElement> = elements.iter().map(|el| self.build_dom_element(el)).collect();
+        let dom_els = elements.iter().map(|el| self.build_dom_element(el)).collect::<Result<Vec<DOMElement>>>()?;
 
-        self.elements.insert(name.to_string(), dom_els);
+        self.scraped.values.remove(name);
+        self.scraped.elements.insert(name.to_string(), dom_els);
         
         info!("[XPATH] DONE in {}s", t0.elapsed().as_secs());
 
-        return self;
+        Ok(self)
     }
 
-    fn build_dom_element(&self, el: &Element ) -> DOMElement {
+    fn build_dom_element(&self, el: &Element ) -> Result<DOMElement> {
 
         let attrs_map: HashMap<String, String> = el.attrs.clone();
         
         let dom_el = DOMElement {
-            text: el.get_inner_text().unwrap(),
-            attrs: attrs_map
+            text: el.get_inner_text().map_err(|e| Error::Browser(e.to_string()))?,
+            attrs: attrs_map,
+            tag: None,
+            inner_html: None,
+            outer_html: None
         };
 
-        dom_el
+        Ok(dom_el)
     }
 
-    fn get_selector_type<S: AsRef<str> + Clone>(&self, target: &S) -> Selector {
+    /// It evaluates xpath in the page, returning what it yields if it's not elements, e.g. //a/@href or count(//a). <br>
+    /// None means the XPath selects elements, or nothing yet: an empty node-set can't tell which.
+    fn xpath_values(&self, name: &str, xpath: &str) -> Result<Option<Vec<serde_json::Value>>> {
+
+        let script = format!(r#"(() => {{
+            const r = document.evaluate({}, document, null, XPathResult.ANY_TYPE, null);
+            switch (r.resultType) {{
+                case XPathResult.NUMBER_TYPE: return JSON.stringify([r.numberValue]);
+                case XPathResult.STRING_TYPE: return JSON.stringify([r.stringValue]);
+                case XPathResult.BOOLEAN_TYPE: return JSON.stringify([r.booleanValue]);
+            }}
+            const values = [];
+            let elements = true;
+            for (let n = r.iterateNext(); n; n = r.iterateNext()) {{
+                elements = elements && n.nodeType === Node.ELEMENT_NODE;
+                values.push(n.textContent);
+            }}
+            return elements ? "null" : JSON.stringify(values);
+        }})()"#, serde_json::to_string(xpath)?);
+
+        let res = self.tab.evaluate(&script, false).map_err(|e| query_error(name, xpath, e))?;
+
+        let json = match res.value {
+            Some(serde_json::Value::String(json)) => json,
+            // The XPath threw, e.g. it's malformed.
+            _ => return Err(Error::InvalidSelector { selector: xpath.to_string(), reason: res.description.unwrap_or_default() })
+        };
 
-        let target = target.as_ref();
-        if target.starts_with("/") {
-            return Selector::XPath;
-        }
+        let values: Option<Vec<serde_json::Value>> = serde_json::from_str(&json)?;
 
-        Selector::CSS
+        Ok(values.map(|values| values.into_iter()
+            .map(|v| v.as_f64().map_or(v, number_value))
+            .collect()))
     }
 
-    pub fn click<S: AsRef<str> + Clone>(&mut self, name: S, target: S) -> &mut Scraper {
+    /// How many elements or values are stored under name.
+    fn matched(&self, name: &str) -> usize {
+        self.scraped.elements.get(name).map(Vec::len)
+            .or_else(|| self.scraped.values.get(name).map(Vec::len))
+            .unwrap_or(0)
+    }
 
-        let target = target.as_ref();
-        let selector_type = self.get_selector_type(&target);
+    /// It returns the first element matching target, whatever its selector kind.
+    fn wait_for_first_element(&self, name: &str, target: &str) -> Result<Element<'_>> {
+        self.wait_for_first(name, &Selector::parse(target))
+    }
+
+    /// Same as wait_for_first_element, with a parsed selector.
+    fn wait_for_first(&self, name: &str, selector: &Selector) -> Result<Element<'_>> {
 
-        let res = match selector_type {
-            Selector::CSS => self.tab.wait_for_element(&target).and_then(|el| {el.click().and_then(|_r| Ok(true))}),
-            Selector::XPath => self.tab.wait_for_elements_by_xpath(&target).and_then(|el| {el.get(0).unwrap().click().and_then(|_r| Ok(true))})
+        let res = match selector.to_xpath() {
+            None => self.tab.wait_for_element(selector.expression()),
+            Some(xpath) => self.tab.wait_for_elements_by_xpath(&xpath).map(|els| els.into_iter().next())
+                .and_then(|el| el.ok_or_else(|| NoElementFound {}.into())),
         };
 
-        if res.is_err() {
-            println!("Couldn't find element: {}", name.as_ref());
+        res.map_err(|e| query_error(name, selector.expression(), e))
+    }
+
+    pub fn find_elements_within<S: AsRef<str> + Clone>(&mut self, name: S, parent: S, target: S) -> &mut Scraper {
+
+        if let Err(e) = self.query_within(name.as_ref(), parent.as_ref(), target.as_ref()) {
+            warn!("{}", e);
+        }
+
+        self
+    }
+
+    /// It finds the elements matching target relative to each element matching parent, e.g. ./span for the spans of each parent. <br>
+    /// Both are selectors of any kind, see Selector. Once a parent shows up, they're looked up in a snapshot of the rendered page.
+    pub fn try_find_elements_within<S: AsRef<str> + Clone>(&mut self, name: S, parent: S, target: S) -> Result<&mut Scraper> {
+
+        query::within(self, name.as_ref(), parent.as_ref(), target.as_ref())?;
+
+        Ok(self)
+    }
+
+    pub fn extract(&mut self, name: &str, parent: Option<&str>, target: &str, extract: &Extract, transforms: &[Transform]) -> &mut Scraper {
+
+        if let Err(e) = self.query_extract(name, parent, target, extract, transforms) {
+            warn!("{}", e);
+        }
+
+        self
+    }
+
+    /// It finds the elements matching target, relative to the elements matching parent if any, and keeps what extract says of each, e.g. attr:href. <br>
+    /// Unless extract keeps elements, the results are stored as values, after going through transforms if any: elements are then kept as text. <br>
+    /// Values a transform fails on become null, and are reported in the failures of ScrapingResult. Like try_find_elements_within, it works on a snapshot of the rendered page.
+    pub fn try_extract(&mut self, name: &str, parent: Option<&str>, target: &str, extract: &Extract, transforms: &[Transform]) -> Result<&mut Scraper> {
+
+        query::extract(self, name, parent, target, extract, transforms)?;
+
+        Ok(self)
+    }
+
+    pub fn find_links(&mut self, name: &str, parent: Option<&str>, target: &str, links: &Links) -> &mut Scraper {
+
+        if let Err(e) = self.query_links(name, parent, target, links) {
+            warn!("{}", e);
+        }
+
+        self
+    }
+
+    /// It finds the absolute URLs the elements matching target link to, relative to the elements matching parent if any, and keeps the ones links allows. <br>
+    /// Elements give their href, and XPaths like //a/@href their values. URLs are stored as values. Like try_find_elements_within, it works on a snapshot of the rendered page.
+    pub fn try_find_links(&mut self, name: &str, parent: Option<&str>, target: &str, links: &Links) -> Result<&mut Scraper> {
+
+        query::links(self, name, parent, target, links)?;
+
+        Ok(self)
+    }
+
+    pub fn find_table(&mut self, name: &str, parent: Option<&str>, target: &str, table: &Table) -> &mut Scraper {
+
+        if let Err(e) = self.query_table(name, parent, target, table) {
+            warn!("{}", e);
+        }
+
+        self
+    }
+
+    /// It turns the tables matching target, relative to the elements matching parent if any, into a record per body row keyed by header cells. <br>
+    /// Records are stored as values, see Table. Like try_find_elements_within, it works on a snapshot of the rendered page.
+    pub fn try_find_table(&mut self, name: &str, parent: Option<&str>, target: &str, table: &Table) -> Result<&mut Scraper> {
+
+        query::table(self, name, parent, target, table)?;
+
+        Ok(self)
+    }
+
+    pub fn find_metadata(&mut self, name: &str) -> &mut Scraper {
+
+        if let Err(e) = self.try_find_metadata(name) {
+            warn!("{}", e);
+        }
+
+        self
+    }
+
+    /// It stores under name the structured data of the page: JSON-LD, microdata, RDFa, OpenGraph and Twitter tags, canonical URL and title. It reads the rendered page, so metadata added by scripts counts too.
+    pub fn try_find_metadata(&mut self, name: &str) -> Result<&mut Scraper> {
+
+        query::metadata(self, name)?;
+
+        Ok(self)
+    }
+
+    pub fn find_content(&mut self, name: &str, parent: Option<&str>, target: Option<&str>) -> &mut Scraper {
+
+        if let Err(e) = self.query_content(name, parent, target) {
+            warn!("{}", e);
+        }
+
+        self
+    }
+
+    /// It stores under name the readable content, as Markdown and plain text, of each element matching target, relative to the elements matching parent if any. <br>
+    /// Without a target, it's the main article of the page, found as readability does. Contents are stored as values, see Content. <br>
+    /// It reads the rendered page, so content added by scripts counts too.
+    pub fn try_find_content(&mut self, name: &str, parent: Option<&str>, target: Option<&str>) -> Result<&mut Scraper> {
+
+        query::content(self, name, parent, target)?;
+
+        Ok(self)
+    }
+
+    pub fn find_records(&mut self, name: &str, parent: Option<&str>, target: &str, fields: &BTreeMap<String, Field>) -> &mut Scraper {
+
+        if let Err(e) = self.query_records(name, parent, target, fields) {
+            warn!("{}", e);
+        }
+
+        self
+    }
+
+    /// It builds a record of fields for each element matching target, relative to the elements matching parent if any, see Field. <br>
+    /// Records are stored as values, e.g. {"title": "...", "votes": 3}. Like try_find_elements_within, they are built from a snapshot of the rendered page.
+    pub fn try_find_records(&mut self, name: &str, parent: Option<&str>, target: &str, fields: &BTreeMap<String, Field>) -> Result<&mut Scraper> {
+
+        query::records(self, name, parent, target, fields)?;
+
+        Ok(self)
+    }
+
+    /// Like try_scrape, but logging failures and returning no value instead.
+    pub fn scrape<T: Scrape>(&mut self) -> Vec<T> {
+
+        match self.try_scrape() {
+            Ok(values) => values,
+            Err(e) => {
+                warn!("{}", e);
+                vec![]
+            }
+        }
+    }
+
+    /// It scrapes a T out of each of its containers on the current page, see Scrape. None is found if no container matches. <br>
+    /// Values aren't stored: they're returned, typed. Like try_find_records, it works on a snapshot of the rendered page.
+    pub fn try_scrape<T: Scrape>(&mut self) -> Result<Vec<T>> {
+        query::scrape(self)
+    }
+
+    pub fn click<S: AsRef<str> + Clone>(&mut self, name: S, target: S) -> &mut Scraper {
+
+        if let Err(e) = self.try_click(name, target) {
+            warn!("{}", e);
         }
 
         // TODO fix headless click and navigation
@@ -421,92 +798,153 @@ impl Scraper {
         self
     }
 
-    pub fn type_into<S: AsRef<str> + Clone>(&mut self, name: S, target: S, text: S) -> &mut Scraper {
+    /// It clicks the first element matching target. <br>
+    /// If it's a link to a page disallowed by robots.txt, the page is blocked and Error::Disallowed reported.
+    pub fn try_click<S: AsRef<str> + Clone>(&mut self, name: S, target: S) -> Result<&mut Scraper> {
 
-        let target = target.as_ref();
-        let name = name.as_ref();
-        let text = text.as_ref();
+        self.tab_state.intercept_error.lock().unwrap().take();
 
-        let selector_type = self.get_selector_type(&target);
+        self.wait_for_first_element(name.as_ref(), target.as_ref())?
+            .click()
+            .map_err(|e| Error::Browser(e.to_string()))?;
 
-        let res = match selector_type {
-            Selector::CSS => self.tab.wait_for_element(&target).and_then(|el| {el.type_into(&text).and_then(|_r| Ok(true))}),
-            Selector::XPath => self.tab.wait_for_elements_by_xpath(&target).and_then(|el| {el.get(0).unwrap().type_into(&text).and_then(|_r| Ok(true))}),
-        };
+        if let Some(e) = self.tab_state.intercept_error.lock().unwrap().take() {
+            return Err(e);
+        }
+
+        Ok(self)
+    }
+
+    pub fn type_into<S: AsRef<str> + Clone>(&mut self, name: S, target: S, text: S) -> &mut Scraper {
 
-        if res.is_err() {
-            println!("Couldn't find element: {}", name);
+        if let Err(e) = self.try_type_into(name, target, text) {
+            warn!("{}", e);
         }
 
         self
     }
 
+    pub fn try_type_into<S: AsRef<str> + Clone>(&mut self, name: S, target: S, text: S) -> Result<&mut Scraper> {
+
+        self.wait_for_first_element(name.as_ref(), target.as_ref())?
+            .type_into(text.as_ref())
+            .map_err(|e| Error::Browser(e.to_string()))?;
+
+        Ok(self)
+    }
+
     pub fn screenshot<S: AsRef<str> + Clone>(&mut self, name: S, target: S, format: ScreenshotFormat) -> &mut Scraper {
 
+        if let Err(e) = self.try_screenshot(name, target, format) {
+            warn!("{}", e);
+        }
+
+        self
+    }
+
+    pub fn try_screenshot<S: AsRef<str> + Clone>(&mut self, name: S, target: S, format: ScreenshotFormat) -> Result<&mut Scraper> {
+
         let target = target.as_ref();
         let name = name.as_ref();
 
         let format = match format {
             ScreenshotFormat::JPEG => protocol::cdp::Page::CaptureScreenshotFormatOption::Jpeg,
             ScreenshotFormat::PNG => protocol::cdp::Page::CaptureScreenshotFormatOption::Png,
-            _ => protocol::cdp::Page::CaptureScreenshotFormatOption::Png
         };
 
-        let selector_type = self.get_selector_type(&target);
+        let img_data = self.wait_for_first_element(name, target)?
+            .capture_screenshot(format)
+            .map_err(|e| Error::Browser(e.to_string()))?;
 
-        let res = match selector_type {
-            Selector::CSS => self.tab.wait_for_element(&target).and_then(|el| {el.capture_screenshot(format).and_then(|r| Ok(r))}),
-            Selector::XPath => self.tab.wait_for_elements_by_xpath(&target).and_then(|el| {el.get(0).unwrap().capture_screenshot(format).and_then(|r| Ok(r))}),
-        };
+        self.scraped.screenshots.insert(name.to_string(), img_data);
 
-        let Ok(img_data) = res else {
+        Ok(self)
+    }
 
-            println!("Couldn't find element: {}", name);
-            return self;
-        };
 
-        self.screenshots.insert(name.to_string(), img_data);
-        
+    pub fn save(&self, targets: &[String], flatten: &bool ) {
 
-        self
+        if let Err(e) = self.try_save(targets, flatten) {
+            warn!("{}", e);
+        }
     }
 
+    /// It writes the given targets as JSON in the save directory, returning the path of the written file.
+    pub fn try_save(&self, targets: &[String], flatten: &bool ) -> Result<PathBuf> {
 
-    pub fn save(&self, targets: &Vec<String>, flatten: &bool ) {
+        self.try_save_as(targets, flatten, None)
+    }
 
-        let curr_url = self.current_url.as_ref().unwrap();
-        let parts = curr_url.split("/").collect::<Vec<&str>>();
-        let name = parts.last().unwrap().to_string();
-        let name = name.split_once(".").unwrap_or((name.as_str(), ""));
-        let name = name.0.to_string();
+    /// Like try_save, but into save_path, relative to the save directory. $URL$ in save_path is replaced by the name taken from the URL.
+    pub fn try_save_as(&self, targets: &[String], flatten: &bool, save_path: Option<&str>) -> Result<PathBuf> {
 
-        let save_path = &self.save_dir;
-        let options = sanitize_filename::Options {
-            truncate: false, // true by default, truncates to 255 bytes
-            windows: false, // default value depends on the OS, removes reserved names like `con` from start of strings on Windows
-            replacement: "_" // str to replace sanitized chars/strings
-        };
+        save_elements(&self.scraped.elements, &self.scraped.values, &self.scraped.metadata, self.current_url.as_ref(), &self.save_dir, targets, flatten, save_path)
+    }
+}
 
-        let name = sanitize_filename::sanitize_with_options(name, options);
-        let save_path = format!("./{}/{}.json", save_path, name);
-        
-        //println!("{}", &save_path);
-        let mut els = self.elements.clone();
-        els.retain(|k,_| targets.contains(k));
+impl Backend for Scraper {
 
-        if *flatten {
+    fn name(&self) -> &'static str {
+        "chrome"
+    }
 
-            let els = els.iter().map(|(k,v)| v.clone() ).collect::<Vec<Vec<DOMElement>>>();
-            let els = els.into_iter().flatten().collect::<Vec<DOMElement>>();
-            let s = serde_json::to_string_pretty(&els).unwrap();
+    fn navigate(&mut self, url: &str) -> Result<()> {
+        self.try_navigate_to(url).map(|_| ())
+    }
+
+    fn current_url(&self) -> Option<String> {
+        self.current_url.clone()
+    }
 
-            std::fs::write(save_path, s).unwrap();
-        } else {
-            let s = serde_json::to_string_pretty(&els).unwrap();
+    /// It parses the rendered page, once an element matching wait_for shows up, for the queries the DevTools protocol doesn't answer.
+    fn document(&mut self, name: &str, wait_for: Option<&Selector>) -> Result<Rc<HtmlDocument>> {
 
-            std::fs::write(save_path, s).unwrap();
+        if self.current_url.is_none() {
+            return Err(Error::NotNavigated);
         }
-        
-        
+
+        if let Some(wait_for) = wait_for {
+            self.wait_for_first(name, wait_for)?;
+        }
+
+        let content = self.tab.get_content().map_err(|e| Error::Browser(e.to_string()))?;
+
+        Ok(Rc::new(HtmlDocument::parse(&content)))
+    }
+
+    fn scraped(&mut self) -> &mut ScrapingResult {
+        &mut self.scraped
+    }
+
+    /// Elements are looked up in the live page, not in a snapshot.
+    fn query_css(&mut self, name: &str, selector: &str) -> Result<usize> {
+        let found = self.try_find_elements_by_css(name, selector).map(|scraper| scraper.matched(name));
+        query::or_empty(found, &mut self.scraped, name, true)
+    }
+
+    /// Same as query_css: the XPath is evaluated in the live page.
+    fn query_xpath(&mut self, name: &str, selector: &str) -> Result<usize> {
+        let found = self.try_find_elements_by_xpath(name, selector).map(|scraper| scraper.matched(name));
+        query::or_empty(found, &mut self.scraped, name, true)
+    }
+
+    fn click(&mut self, name: &str, selector: &str) -> Result<()> {
+        self.try_click(name, selector).map(|_| ())
+    }
+
+    fn type_into(&mut self, name: &str, selector: &str, text: &str) -> Result<()> {
+        self.try_type_into(name, selector, text).map(|_| ())
+    }
+
+    fn screenshot(&mut self, name: &str, selector: &str, format: ScreenshotFormat) -> Result<()> {
+        self.try_screenshot(name, selector, format).map(|_| ())
+    }
+
+    fn save(&self, targets: &[String], flatten: bool, save_path: Option<&str>) -> Result<PathBuf> {
+        self.try_save_as(targets, &flatten, save_path)
+    }
+
+    fn recycle(&mut self) -> Result<()> {
+        self.recycle_tab().map(|_| ())
     }
 }
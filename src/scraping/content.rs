@@ -0,0 +1,652 @@
+use std::collections::HashMap;
+
+use ego_tree::{NodeId, NodeRef};
+use scraper::{ElementRef, Html, Node};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// Elements that are never content, left out of both scoring and output.
+const SKIPPED_TAGS: &[&str] = &["script", "style", "noscript", "template", "nav", "aside", "form", "button", "input", "select", "textarea", "iframe", "svg", "canvas", "footer", "head"];
+
+/// Class or id words of page chrome: such elements are left out of scoring, unless they look like content too. <br>
+/// Words are whole, e.g. nav matches main-nav and mainNav but not canvas.
+const UNLIKELY_WORDS: &[&str] = &["ad", "ads", "advert", "banner", "breadcrumb", "breadcrumbs", "combx", "comment", "comments", "community", "cookie", "cookies", "disqus", "extra", "footer", "footnote", "footnotes", "header", "legends", "menu", "modal", "nav", "navbar", "navigation", "pager", "pagination", "popup", "promo", "related", "remark", "rss", "share", "shoutbox", "sidebar", "skyscraper", "social", "sponsor", "sponsored", "subscribe", "tweet", "widget"];
+
+/// Class or id words of content, whole too.
+const LIKELY_WORDS: &[&str] = &["article", "blog", "body", "content", "entry", "hentry", "main", "page", "post", "story", "text"];
+
+/// Blocks whose text is scored and credited to their ancestors.
+const SCORED_TAGS: &[&str] = &["p", "pre", "td", "blockquote", "li"];
+
+/// Paragraphs shorter than this are too short to tell content apart.
+const MIN_SCORED_LENGTH: usize = 25;
+
+/// Content is readable text taken out of HTML, scraped by a `content: true` target: the main article of the page, or what a selector matched.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Content {
+
+    /// The first heading of the content, or the `<title>` of the page for main articles.
+    pub title: Option<String>,
+
+    /// Headings, lists, links, emphasis, code blocks, quotes and tables as Markdown, with absolute URLs.
+    pub markdown: String,
+
+    /// The same, as plain text: a paragraph per block, lists with their markers.
+    pub text: String
+}
+
+impl Content {
+
+    /// It renders els, in order, as one content. URLs resolve against base_url, if given.
+    pub(crate) fn of(els: &[ElementRef], title: Option<String>, base_url: Option<&Url>) -> Content {
+
+        let render = |markdown: bool| {
+            let renderer = Renderer { markdown, base_url };
+            let mut blocks = vec![];
+            for el in els {
+                renderer.blocks(*el, &mut blocks);
+            }
+            blocks.join("\n\n")
+        };
+
+        let title = els.iter()
+            .flat_map(|el| el.descendants().filter_map(ElementRef::wrap))
+            .find(|el| matches!(el.value().name(), "h1" | "h2" | "h3" | "h4" | "h5" | "h6"))
+            .map(|heading| normalized_text(&heading))
+            .filter(|heading| !heading.is_empty())
+            .or(title);
+
+        Content { title, markdown: render(true), text: render(false) }
+    }
+}
+
+/// It finds the main article of html, as readability does, and renders it. URLs resolve against base_url, if given.
+pub(crate) fn main_content(html: &Html, base_url: Option<&Url>) -> Content {
+
+    let root = html.root_element();
+
+    let body = root.descendants().filter_map(ElementRef::wrap).find(|el| el.value().name() == "body").unwrap_or(root);
+    let title = root.descendants().filter_map(ElementRef::wrap).find(|el| el.value().name() == "title").map(|el| normalized_text(&el)).filter(|t| !t.is_empty());
+
+    let mut scores: HashMap<NodeId, f64> = HashMap::new();
+    score(body, &mut scores);
+
+    // Scores are cut by the share of text in links: navigation is mostly links. Ties go to the first candidate in the page.
+    let top = body.descendants()
+        .filter_map(ElementRef::wrap)
+        .filter_map(|el| scores.get(&el.id()).map(|score| (el, score * (1.0 - link_density(&el)))))
+        .fold(None, |top: Option<(ElementRef, f64)>, (el, score)| match top {
+            Some((_, top_score)) if top_score >= score => top,
+            _ => Some((el, score))
+        });
+
+    let Some((top, top_score)) = top else {
+        return Content::of(&[body], title, base_url);
+    };
+
+    if top == body {
+        return Content::of(&[body], title, base_url);
+    }
+
+    // Siblings scoring close to the top candidate, or holding long paragraphs, are part of the article too, e.g. split in several divs.
+    let threshold = f64::max(10.0, top_score * 0.2);
+
+    let parts: Vec<ElementRef> = match top.parent().and_then(ElementRef::wrap) {
+        Some(parent) => parent.children()
+            .filter_map(ElementRef::wrap)
+            .filter(|sibling| {
+                *sibling == top
+                    || !skipped(sibling) && scores.get(&sibling.id()).is_some_and(|score| score * (1.0 - link_density(sibling)) >= threshold)
+                    || sibling.value().name() == "p" && text_length(sibling) > 80 && link_density(sibling) < 0.25
+            })
+            .collect(),
+        None => vec![top]
+    };
+
+    Content::of(&parts, title, base_url)
+}
+
+/// It credits the text of every scored block below el to its parent, and less so to its grandparent and great-grandparent.
+fn score(el: ElementRef, scores: &mut HashMap<NodeId, f64>) {
+
+    for child in el.children().filter_map(ElementRef::wrap) {
+
+        if skipped(&child) || unlikely(&child) {
+            continue;
+        }
+
+        if SCORED_TAGS.contains(&child.value().name()) {
+
+            let text = normalized_text(&child);
+            let length = text.chars().count();
+
+            if length >= MIN_SCORED_LENGTH {
+
+                // A point for the paragraph, one per comma, and one per 100 characters up to 3.
+                let points = 1.0 + text.matches(',').count() as f64 + f64::min((length / 100) as f64, 3.0);
+
+                let ancestors = child.ancestors().filter_map(ElementRef::wrap).take(3);
+
+                for (level, ancestor) in ancestors.enumerate() {
+                    let score = scores.entry(ancestor.id()).or_insert_with(|| initial_score(&ancestor));
+                    *score += points / (level + 1) as f64;
+                }
+            }
+        }
+
+        score(child, scores);
+    }
+}
+
+/// What an element scores before its text: containers of prose win over lists and headings.
+fn initial_score(el: &ElementRef) -> f64 {
+
+    let tag = match el.value().name() {
+        "article" | "main" => 10.0,
+        "div" | "section" => 5.0,
+        "pre" | "td" | "blockquote" => 3.0,
+        "address" | "ol" | "ul" | "dl" | "dd" | "dt" | "li" | "form" => -3.0,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+        _ => 0.0
+    };
+
+    let words = class_and_id(el);
+    let likely = if LIKELY_WORDS.iter().any(|w| words.iter().any(|word| word == w)) { 25.0 } else { 0.0 };
+    let unlikely = if UNLIKELY_WORDS.iter().any(|w| words.iter().any(|word| word == w)) { 25.0 } else { 0.0 };
+
+    tag + likely - unlikely
+}
+
+/// The words of the class and id of el, lowercase: post-body, post_body and postBody are all post and body.
+fn class_and_id(el: &ElementRef) -> Vec<String> {
+
+    let value = el.value();
+    let mut words = vec![];
+
+    for name in [value.attr("class"), value.attr("id")].into_iter().flatten() {
+
+        let mut word = String::new();
+        let mut previous = ' ';
+
+        for c in name.chars() {
+            if (!c.is_alphanumeric() || c.is_uppercase() && previous.is_lowercase()) && !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            if c.is_alphanumeric() {
+                word.extend(c.to_lowercase());
+            }
+            previous = c;
+        }
+
+        if !word.is_empty() {
+            words.push(word);
+        }
+    }
+
+    words
+}
+
+/// Elements that are never content: scripts, forms, navigation and hidden ones.
+fn skipped(el: &ElementRef) -> bool {
+
+    let value = el.value();
+
+    SKIPPED_TAGS.contains(&value.name())
+        || value.attr("hidden").is_some()
+        || value.attr("aria-hidden").is_some_and(|v| v.trim() == "true")
+        || value.attr("style").is_some_and(|style| style.replace(' ', "").to_lowercase().contains("display:none"))
+}
+
+/// Page chrome by its class or id, e.g. a sidebar or a comment section, unless it looks like content too.
+fn unlikely(el: &ElementRef) -> bool {
+
+    if matches!(el.value().name(), "body" | "article" | "main") {
+        return false;
+    }
+
+    let words = class_and_id(el);
+    let has = |list: &[&str]| list.iter().any(|w| words.iter().any(|word| word == w));
+
+    has(UNLIKELY_WORDS) && !has(LIKELY_WORDS)
+}
+
+/// The share of the text of el that's in links.
+fn link_density(el: &ElementRef) -> f64 {
+
+    let length = text_length(el);
+
+    if length == 0 {
+        return 0.0;
+    }
+
+    let linked: usize = el.descendants()
+        .filter_map(ElementRef::wrap)
+        .filter(|a| a.value().name() == "a")
+        .map(|a| text_length(&a))
+        .sum();
+
+    linked as f64 / length as f64
+}
+
+fn text_length(el: &ElementRef) -> usize {
+    normalized_text(el).chars().count()
+}
+
+fn normalized_text(el: &ElementRef) -> String {
+    el.text().collect::<String>().split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// It renders elements as Markdown, or plain text, block by block.
+struct Renderer<'u> {
+    markdown: bool,
+    base_url: Option<&'u Url>
+}
+
+impl Renderer<'_> {
+
+    /// It adds the blocks of el to out, e.g. a paragraph, a heading or a list. Runs of inline content between blocks make paragraphs.
+    fn blocks(&self, el: ElementRef, out: &mut Vec<String>) {
+
+        let mut inline = String::new();
+
+        for child in el.children() {
+            match child.value() {
+                Node::Element(value) if is_block(value.name()) => {
+                    if let Some(child) = ElementRef::wrap(child).filter(|c| !skipped(c)) {
+                        self.paragraph(&mut inline, out);
+                        self.block(child, out);
+                    }
+                },
+                _ => self.inline(child, &mut inline)
+            }
+        }
+
+        self.paragraph(&mut inline, out);
+    }
+
+    /// It adds the inline content gathered so far to out, as a paragraph, if there's any.
+    fn paragraph(&self, inline: &mut String, out: &mut Vec<String>) {
+
+        let lines = inline.lines().map(str::trim).filter(|line| !line.is_empty());
+        let mut paragraph = match self.markdown {
+            true => lines.map(escape_line_start).collect::<Vec<String>>().join("\n"),
+            false => lines.collect::<Vec<&str>>().join("\n")
+        };
+
+        // A hard break at the very end of a paragraph is no break. It's an odd backslash: escaped ones come in pairs.
+        while self.markdown && (paragraph.len() - paragraph.trim_end_matches('\\').len()) % 2 == 1 {
+            paragraph.pop();
+            paragraph.truncate(paragraph.trim_end().len());
+        }
+
+        if !paragraph.is_empty() {
+            out.push(paragraph);
+        }
+
+        inline.clear();
+    }
+
+    fn block(&self, el: ElementRef, out: &mut Vec<String>) {
+
+        match el.value().name() {
+            tag @ ("h1" | "h2" | "h3" | "h4" | "h5" | "h6") => {
+                let text = self.inline_text(el).replace('\n', " ");
+                let text = text.trim();
+                if !text.is_empty() {
+                    let level = tag[1..].parse::<usize>().unwrap_or(1);
+                    out.push(match self.markdown {
+                        true => format!("{} {}", "#".repeat(level), text),
+                        false => text.to_string()
+                    });
+                }
+            },
+            "ul" | "ol" | "menu" => {
+                if let Some(list) = self.list(el) {
+                    out.push(list);
+                }
+            },
+            "pre" => {
+                let code = el.text().collect::<String>();
+                let code = code.trim_matches('\n').trim_end();
+                if !code.is_empty() {
+                    out.push(match self.markdown {
+                        true => {
+                            let fence = "`".repeat(longest_run(code, '`').max(2) + 1);
+                            format!("{}{}\n{}\n{}", fence, language(&el).unwrap_or_default(), code, fence)
+                        },
+                        false => code.to_string()
+                    });
+                }
+            },
+            "blockquote" => {
+                let mut quoted = vec![];
+                self.blocks(el, &mut quoted);
+                match self.markdown {
+                    true if !quoted.is_empty() => {
+                        let quote = quoted.join("\n\n").lines().map(|line| match line {
+                            "" => ">".to_string(),
+                            line => format!("> {}", line)
+                        }).collect::<Vec<String>>().join("\n");
+                        out.push(quote);
+                    },
+                    _ => out.extend(quoted)
+                }
+            },
+            "hr" => {
+                if self.markdown {
+                    out.push("---".to_string());
+                }
+            },
+            "table" => {
+                if let Some(table) = self.table(el) {
+                    out.push(table);
+                }
+            },
+            "dt" => {
+                let term = self.inline_text(el).replace('\n', " ");
+                let term = term.trim();
+                if !term.is_empty() {
+                    out.push(match self.markdown {
+                        true => format!("**{}**", term),
+                        false => term.to_string()
+                    });
+                }
+            },
+            _ => self.blocks(el, out)
+        }
+    }
+
+    /// The items of a list, one per line, with their nested blocks indented below them.
+    fn list(&self, el: ElementRef) -> Option<String> {
+
+        let ordered = el.value().name() == "ol";
+        let start = el.value().attr("start").and_then(|s| s.trim().parse::<i64>().ok()).unwrap_or(1);
+
+        let items: Vec<String> = el.children()
+            .filter_map(ElementRef::wrap)
+            .filter(|li| li.value().name() == "li" && !skipped(li))
+            .enumerate()
+            .filter_map(|(i, li)| {
+                let mut blocks = vec![];
+                self.blocks(li, &mut blocks);
+
+                if blocks.is_empty() {
+                    return None;
+                }
+
+                let marker = match ordered {
+                    true => format!("{}. ", start + i as i64),
+                    false => "- ".to_string()
+                };
+                let indent = " ".repeat(marker.len());
+
+                let item = blocks.join("\n").lines()
+                    .enumerate()
+                    .map(|(n, line)| match (n, line) {
+                        (0, line) => format!("{}{}", marker, line),
+                        (_, "") => String::new(),
+                        (_, line) => format!("{}{}", indent, line)
+                    })
+                    .collect::<Vec<String>>()
+                    .join("\n");
+
+                Some(item)
+            })
+            .collect();
+
+        (!items.is_empty()).then(|| items.join("\n"))
+    }
+
+    /// A table as a Markdown pipe table, its first row as header, or as tab-separated lines in plain text.
+    fn table(&self, el: ElementRef) -> Option<String> {
+
+        // Rows of nested tables belong to their own table.
+        let rows: Vec<Vec<String>> = el.descendants()
+            .filter_map(ElementRef::wrap)
+            .filter(|tr| tr.value().name() == "tr" && tr.ancestors().filter_map(ElementRef::wrap).find(|a| a.value().name() == "table") == Some(el))
+            .map(|tr| tr.children()
+                .filter_map(ElementRef::wrap)
+                .filter(|cell| matches!(cell.value().name(), "td" | "th"))
+                .map(|cell| self.inline_text(cell).split_whitespace().collect::<Vec<&str>>().join(" "))
+                .collect::<Vec<String>>())
+            .filter(|row| !row.is_empty())
+            .collect();
+
+        let columns = rows.iter().map(Vec::len).max()?;
+
+        let lines: Vec<String> = match self.markdown {
+            true => {
+                let line = |row: &Vec<String>| {
+                    let cells = (0..columns).map(|i| row.get(i).map_or(String::new(), |cell| cell.replace('|', "\\|")));
+                    format!("| {} |", cells.collect::<Vec<String>>().join(" | "))
+                };
+                let separator = format!("|{}", " --- |".repeat(columns));
+                std::iter::once(line(&rows[0])).chain(std::iter::once(separator)).chain(rows[1..].iter().map(line)).collect()
+            },
+            false => rows.iter().map(|row| row.join("\t")).collect()
+        };
+
+        Some(lines.join("\n"))
+    }
+
+    /// The inline content of el, e.g. of a heading or a table cell.
+    fn inline_text(&self, el: ElementRef) -> String {
+
+        let mut text = String::new();
+        for child in el.children() {
+            self.inline(child, &mut text);
+        }
+
+        text.lines().map(str::trim).filter(|line| !line.is_empty()).collect::<Vec<&str>>().join("\n")
+    }
+
+    /// It adds node to out as inline content: text with its whitespace collapsed, links, emphasis, code, images and line breaks.
+    fn inline(&self, node: NodeRef<Node>, out: &mut String) {
+
+        match node.value() {
+            Node::Text(text) => {
+                // Newlines in text are just whitespace: only br breaks lines.
+                let text = text.replace(char::is_whitespace, " ");
+                let text = match self.markdown {
+                    true => escape(&text),
+                    false => text
+                };
+                push(out, &text);
+            },
+            Node::Element(_) => {
+                let Some(el) = ElementRef::wrap(node).filter(|el| !skipped(el)) else {
+                    return;
+                };
+
+                let inner = || {
+                    let mut inner = String::new();
+                    for child in el.children() {
+                        self.inline(child, &mut inner);
+                    }
+                    inner
+                };
+
+                match (el.value().name(), self.markdown) {
+                    ("br", true) => push(out, "\\\n"),
+                    ("br", false) => push(out, "\n"),
+                    ("img", true) => {
+                        if let Some(src) = el.value().attr("src").map(|src| self.absolute(src)) {
+                            let alt = escape(el.value().attr("alt").unwrap_or_default().trim());
+                            push(out, &format!("![{}]({})", alt, src));
+                        }
+                    },
+                    ("img", false) => push(out, el.value().attr("alt").unwrap_or_default().trim()),
+                    ("a", true) => {
+                        let text = inner();
+                        match el.value().attr("href").map(str::trim).filter(|href| !href.is_empty() && !href.starts_with("javascript:")) {
+                            Some(href) if !text.trim().is_empty() => push(out, &surround(&text, "[", &format!("]({})", self.absolute(href).replace(' ', "%20").replace(')', "%29")))),
+                            _ => push(out, &text)
+                        }
+                    },
+                    ("strong" | "b", true) => push(out, &surround(&inner(), "**", "**")),
+                    ("em" | "i", true) => push(out, &surround(&inner(), "_", "_")),
+                    ("del" | "s" | "strike", true) => push(out, &surround(&inner(), "~~", "~~")),
+                    ("code" | "kbd" | "samp" | "tt", true) => {
+                        let code = el.text().collect::<String>().split_whitespace().collect::<Vec<&str>>().join(" ");
+                        if !code.is_empty() {
+                            let fence = "`".repeat(longest_run(&code, '`') + 1);
+                            // Code starting or ending with a backtick needs a space between it and the fence.
+                            let pad = if code.starts_with('`') || code.ends_with('`') { " " } else { "" };
+                            push(out, &format!("{}{}{}{}{}", fence, pad, code, pad, fence));
+                        }
+                    },
+                    _ => push(out, &inner())
+                }
+            },
+            _ => ()
+        }
+    }
+
+    fn absolute(&self, href: &str) -> String {
+        self.base_url.and_then(|base| base.join(href.trim()).ok()).map_or_else(|| href.trim().to_string(), |url| url.to_string())
+    }
+}
+
+fn is_block(tag: &str) -> bool {
+    matches!(tag, "address" | "article" | "blockquote" | "center" | "dd" | "details" | "dialog" | "div" | "dl" | "dt" | "fieldset" | "figcaption" | "figure" | "footer" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "header" | "hgroup" | "hr" | "li" | "main" | "menu" | "ol" | "p" | "pre" | "section" | "summary" | "table" | "ul")
+}
+
+/// It appends text to out, whitespace runs collapsed into a space, also where they meet. Newlines are line breaks, kept as is.
+fn push(out: &mut String, text: &str) {
+
+    for c in text.chars() {
+        match c {
+            '\n' => {
+                if out.ends_with(' ') {
+                    out.pop();
+                }
+                out.push('\n');
+            },
+            c if c.is_whitespace() => {
+                if !out.is_empty() && !out.ends_with([' ', '\n']) {
+                    out.push(' ');
+                }
+            },
+            c => out.push(c)
+        }
+    }
+}
+
+/// It wraps text with open and close, e.g. ** for bold, keeping its surrounding whitespace outside. Blank text stays as is.
+fn surround(text: &str, open: &str, close: &str) -> String {
+
+    let trimmed = text.trim();
+
+    if trimmed.is_empty() {
+        return text.to_string();
+    }
+
+    let before = if text.starts_with(char::is_whitespace) { " " } else { "" };
+    let after = if text.ends_with(char::is_whitespace) { " " } else { "" };
+
+    format!("{}{}{}{}{}", before, open, trimmed, close, after)
+}
+
+/// Markdown punctuation in text is escaped, so it reads as is.
+fn escape(text: &str) -> String {
+
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '_' | '`' | '[' | ']') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+/// A line starting like a heading, a quote or a list item is escaped, e.g. `# 1` becomes `\# 1` and `1. place` becomes `1\. place`.
+fn escape_line_start(line: &str) -> String {
+
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let rest = &line[digits..];
+
+    match line.chars().next() {
+        Some('#' | '>') => format!("\\{}", line),
+        Some('-' | '+') if line.len() == 1 || line[1..].starts_with(' ') => format!("\\{}", line),
+        Some('0'..='9') if digits <= 9 && rest.starts_with(['.', ')']) && (rest.len() == 1 || rest[1..].starts_with(' ')) => format!("{}\\{}", &line[..digits], rest),
+        _ => line.to_string()
+    }
+}
+
+fn longest_run(text: &str, c: char) -> usize {
+    text.split(|x| x != c).map(str::len).max().unwrap_or(0)
+}
+
+/// The language of a code block, from a language-x or lang-x class on the pre or its code.
+fn language(pre: &ElementRef) -> Option<String> {
+
+    std::iter::once(*pre)
+        .chain(pre.children().filter_map(ElementRef::wrap).filter(|c| c.value().name() == "code"))
+        .flat_map(|el| el.value().classes().map(str::to_string).collect::<Vec<String>>())
+        .find_map(|class| class.strip_prefix("language-").or_else(|| class.strip_prefix("lang-")).map(str::to_string))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn markdown(html: &str) -> String {
+        main_content(&Html::parse_document(html), None).markdown
+    }
+
+    fn first<'h>(html: &'h Html, tag: &str) -> ElementRef<'h> {
+        html.root_element().descendants().filter_map(ElementRef::wrap).find(|el| el.value().name() == tag).unwrap()
+    }
+
+    #[test]
+    fn class_and_id_are_split_into_words() {
+
+        let html = Html::parse_fragment(r#"<div class="post-body main_Nav" id="sideBar2"></div>"#);
+        assert_eq!(class_and_id(&first(&html, "div")), vec!["post", "body", "main", "nav", "side", "bar2"]);
+    }
+
+    #[test]
+    fn words_match_whole() {
+
+        let html = Html::parse_fragment(r#"<div class="downloads"></div><section class="canvas-wrapper"></section><span class="ad-slot"></span><p id="site-nav"></p>"#);
+
+        assert!(!unlikely(&first(&html, "div")));
+        assert!(!unlikely(&first(&html, "section")));
+        assert!(unlikely(&first(&html, "span")));
+        assert!(unlikely(&first(&html, "p")));
+    }
+
+    #[test]
+    fn main_article_is_found() {
+
+        let page = r#"<html><body>
+            <div class="sidebar"><p>Links to everything else on the site, repeated on every single page.</p></div>
+            <div class="downloads"><p>The article itself, long enough to be scored, with commas, and more commas, and words.</p></div>
+        </body></html>"#;
+
+        assert_eq!(markdown(page), "The article itself, long enough to be scored, with commas, and more commas, and words.");
+    }
+
+    #[test]
+    fn trailing_breaks_are_dropped() {
+
+        assert_eq!(markdown(r#"<body><p>one<br>two<br><br></p></body>"#), "one\\\ntwo");
+        assert_eq!(markdown(r#"<body><p>C:\<br></p></body>"#), "C:\\\\");
+        assert_eq!(markdown(r#"<body><p>C:\</p></body>"#), "C:\\\\");
+        assert_eq!(main_content(&Html::parse_document(r#"<body><p>C:\</p></body>"#), None).text, "C:\\");
+    }
+
+    #[test]
+    fn line_starts_are_escaped() {
+
+        assert_eq!(markdown(r#"<body><p>#1 in sales</p></body>"#), "\\#1 in sales");
+        assert_eq!(markdown(r#"<body><p>&gt; not a quote</p></body>"#), "\\> not a quote");
+        assert_eq!(markdown(r#"<body><p>1. not a list<br>- nor this<br>2024) nor this</p></body>"#), "1\\. not a list\\\n\\- nor this\\\n2024\\) nor this");
+        assert_eq!(markdown(r#"<body><p>1.5 stays, -1 too, and 3 # 4 &gt; 2</p></body>"#), "1.5 stays, -1 too, and 3 # 4 > 2");
+        assert_eq!(markdown(r#"<body><ul><li>1. first</li></ul></body>"#), "- 1\\. first");
+    }
+}
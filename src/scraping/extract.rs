@@ -0,0 +1,114 @@
+use std::{fmt::Display, str::FromStr};
+
+use scraper::ElementRef;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+
+use super::html::build_dom_element;
+
+/// What a target keeps of each element it matches, e.g. `extract: attr:href`. <br>
+/// Element and ElementHtml keep a DOMElement, the others a JSON value stored in ScrapingResult::values.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Extract {
+
+    /// Its text and attributes, as a DOMElement.
+    #[default]
+    Element,
+
+    /// Same as Element, with its tag, inner and outer HTML too.
+    ElementHtml,
+
+    /// Its text, trimmed.
+    Text,
+
+    /// Its text nodes joined as they are, like the DOM textContent.
+    TextContent,
+
+    InnerHtml,
+    OuterHtml,
+
+    /// One of its attributes, null if it's missing.
+    Attr(String),
+
+    /// All of its attributes, as a JSON object.
+    Attrs
+}
+
+impl Extract {
+
+    /// True if elements are kept as DOMElement rather than values.
+    pub fn is_element(&self) -> bool {
+        matches!(self, Extract::Element | Extract::ElementHtml)
+    }
+
+    /// What el is turned into.
+    pub(crate) fn apply(&self, el: &ElementRef) -> serde_json::Value {
+
+        match self {
+            Extract::Element | Extract::ElementHtml => serde_json::to_value(build_dom_element(el, self == &Extract::ElementHtml)).unwrap_or_default(),
+            Extract::Text => serde_json::Value::String(el.text().collect::<String>().trim().to_string()),
+            Extract::TextContent => serde_json::Value::String(el.text().collect()),
+            Extract::InnerHtml => serde_json::Value::String(el.inner_html()),
+            Extract::OuterHtml => serde_json::Value::String(el.html()),
+            Extract::Attr(name) => el.value().attr(name).map_or(serde_json::Value::Null, |v| serde_json::Value::String(v.to_string())),
+            Extract::Attrs => serde_json::Value::Object(el.value().attrs().map(|(k, v)| (k.to_string(), serde_json::Value::String(v.to_string()))).collect())
+        }
+    }
+}
+
+impl FromStr for Extract {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+
+        match s {
+            "element" => Ok(Extract::Element),
+            "element_html" => Ok(Extract::ElementHtml),
+            "text" => Ok(Extract::Text),
+            "text_content" => Ok(Extract::TextContent),
+            "inner_html" => Ok(Extract::InnerHtml),
+            "outer_html" => Ok(Extract::OuterHtml),
+            "attr:*" => Ok(Extract::Attrs),
+            _ => match s.strip_prefix("attr:") {
+                Some(name) if !name.trim().is_empty() => Ok(Extract::Attr(name.trim().to_string())),
+                _ => Err(Error::Config(unknown(s)))
+            }
+        }
+    }
+}
+
+impl TryFrom<String> for Extract {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse().map_err(|_| unknown(&s))
+    }
+}
+
+fn unknown(extract: &str) -> String {
+    format!("unknown extract {}: use element, element_html, text, text_content, inner_html, outer_html, attr:NAME or attr:*", extract)
+}
+
+impl From<Extract> for String {
+    fn from(extract: Extract) -> String {
+        extract.to_string()
+    }
+}
+
+impl Display for Extract {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+
+        match self {
+            Extract::Element => write!(f, "element"),
+            Extract::ElementHtml => write!(f, "element_html"),
+            Extract::Text => write!(f, "text"),
+            Extract::TextContent => write!(f, "text_content"),
+            Extract::InnerHtml => write!(f, "inner_html"),
+            Extract::OuterHtml => write!(f, "outer_html"),
+            Extract::Attr(name) => write!(f, "attr:{}", name),
+            Extract::Attrs => write!(f, "attr:*")
+        }
+    }
+}
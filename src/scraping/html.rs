@@ -0,0 +1,541 @@
+use std::{cell::{OnceCell, RefCell}, collections::{BTreeMap, HashMap, HashSet}, rc::Rc, sync::atomic::{AtomicU64, Ordering}};
+
+use ego_tree::NodeRef;
+use scraper::{ElementRef, Html, Node};
+use sxd_document::{dom, Package};
+use sxd_xpath::{nodeset, Context, Factory, Value, XPath};
+
+use crate::error::{Error, Result};
+
+use url::Url;
+
+use super::{DOMElement, content::{Content, main_content}, extract::Extract, metadata::{PageMetadata, page_metadata}, record::{Field, extract_records}, selector::{Selector, SelectorKind}, transform::{Transform, TransformFailure, transform_values}};
+
+/// It checks that selector parses, whatever its kind.
+pub(crate) fn check_selector(selector: &str) -> Result<()> {
+
+    Selector::parse(selector).check()
+}
+
+/// A static HTML page, parsed once and queried through CSS selectors or XPath. <br>
+/// It backs every browser-free scraper of this crate.
+pub struct HtmlDocument {
+    html: Html,
+
+    /// The page mirrored for XPath queries, built by the first one.
+    mirror: OnceCell<Package>,
+
+    /// Compiled XPaths, as the same ones are evaluated again and again, e.g. for every record.
+    xpaths: RefCell<HashMap<String, Rc<XPath>>>
+}
+
+impl HtmlDocument {
+
+    pub fn parse(content: &str) -> HtmlDocument {
+
+        HtmlDocument { html: Html::parse_document(content), mirror: OnceCell::new(), xpaths: RefCell::default() }
+    }
+
+    /// It selects the elements matching selector, whatever its kind. <br>
+    /// XPaths yielding values rather than elements, e.g. //a/@href, fail with Error::InvalidSelector: use evaluate.
+    pub fn select(&self, name: &str, selector: &Selector) -> Result<Vec<ElementRef<'_>>> {
+
+        self.evaluate(name, selector)?.into_elements(selector)
+    }
+
+    /// It evaluates selector, which yields elements, or values for XPaths like //a/@href, //h1/text() or count(//a).
+    pub fn evaluate(&self, name: &str, selector: &Selector) -> Result<Matches<'_>> {
+
+        match selector.kind() {
+            SelectorKind::Css => self.select_css(name, selector.expression()).map(Matches::Elements),
+            SelectorKind::XPath | SelectorKind::Text => self.with_mirror(|mirror| mirror.evaluate(name, selector, None))
+        }
+    }
+
+    /// Same as evaluate, but relative to each element matching parent. XPath values come parent by parent too.
+    pub fn evaluate_within(&self, name: &str, parent: &Selector, selector: &Selector) -> Result<Matches<'_>> {
+
+        self.with_mirror(|mirror| {
+            let parents = mirror.evaluate(name, parent, None)?.into_elements(parent)?;
+            mirror.evaluate(name, selector, Some(&parents))
+        })
+    }
+
+    /// evaluate, or evaluate_within if there's a parent.
+    pub fn evaluate_from(&self, name: &str, parent: Option<&Selector>, selector: &Selector) -> Result<Matches<'_>> {
+
+        match parent {
+            Some(parent) => self.evaluate_within(name, parent, selector),
+            None => self.evaluate(name, selector)
+        }
+    }
+
+    /// It builds a record of fields for each element matching selector, relative to the elements matching parent if any. <br>
+    /// Values the transforms of a field fail on are reported in failures.
+    pub fn records(&self, name: &str, parent: Option<&Selector>, selector: &Selector, fields: &BTreeMap<String, Field>, page_url: Option<&Url>, failures: &mut Vec<TransformFailure>) -> Result<Vec<serde_json::Value>> {
+
+        self.with_mirror(|mirror| {
+
+            let containers = match parent {
+                Some(parent) => {
+                    let parents = mirror.evaluate(name, parent, None)?.into_elements(parent)?;
+                    mirror.evaluate(name, selector, Some(&parents))?
+                },
+                None => mirror.evaluate(name, selector, None)?
+            };
+
+            extract_records(mirror, name, &containers.into_elements(selector)?, fields, page_url, failures)
+        })
+    }
+
+    /// The URL relative links of the page resolve against: its `<base href>`, itself relative to page_url, or page_url.
+    pub fn base_url(&self, page_url: &Url) -> Url {
+
+        let base = scraper::Selector::parse("base[href]").ok()
+            .and_then(|base| self.html.select(&base).next())
+            .and_then(|base| base.value().attr("href"))
+            .and_then(|href| page_url.join(href.trim()).ok());
+
+        base.unwrap_or_else(|| page_url.clone())
+    }
+
+    /// The structured data of the page, see PageMetadata. URLs resolve against base_url, if given.
+    pub fn metadata(&self, base_url: Option<&Url>) -> PageMetadata {
+
+        page_metadata(&self.html, base_url)
+    }
+
+    /// The readable content of each element matching selector, relative to the elements matching parent if any. <br>
+    /// Without a selector, it's the main article of the page, found as readability does. URLs resolve against base_url, if given.
+    pub fn content(&self, name: &str, parent: Option<&Selector>, selector: Option<&Selector>, base_url: Option<&Url>) -> Result<Vec<Content>> {
+
+        match selector {
+            Some(selector) => {
+                let elements = self.evaluate_from(name, parent, selector)?.into_elements(selector)?;
+                Ok(elements.iter().map(|el| Content::of(&[*el], None, base_url)).collect())
+            },
+            None => Ok(vec![main_content(&self.html, base_url)])
+        }
+    }
+
+    pub fn select_css(&self, name: &str, selector: &str) -> Result<Vec<ElementRef<'_>>> {
+
+        let css = parse_css(selector)?;
+
+        let elements = self.html.select(&css).collect::<Vec<ElementRef>>();
+
+        if elements.is_empty() {
+            return Err(Error::ElementNotFound { name: name.to_string(), selector: selector.to_string() });
+        }
+
+        Ok(elements)
+    }
+
+    /// It runs f against the page mirrored for sxd-xpath, which only evaluates its own DOM. The mirror is built once, by the first XPath query.
+    fn with_mirror<'h, R>(&'h self, f: impl for<'d> FnOnce(&Mirror<'d, 'h>) -> R) -> R {
+
+        let package = self.mirror.get_or_init(|| {
+
+            let package = Package::new();
+            let document = package.as_document();
+
+            for child in self.html.tree.root().children() {
+                if let Some(el) = mirror_element(&document, child) {
+                    document.root().append_child(el);
+                }
+            }
+
+            package
+        });
+
+        f(&Mirror { html: &self.html, document: package.as_document(), xpaths: &self.xpaths })
+    }
+}
+
+static NEXT_PAGE_ID: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    /// The last page parsed on this thread. Parsed pages can't cross threads, so scrapers, which do, keep the source and parse it here.
+    static PARSED: RefCell<Option<(u64, Rc<HtmlDocument>)>> = const { RefCell::new(None) };
+}
+
+/// The source of the page a scraper is on. <br>
+/// It's parsed by the first query, and the following ones reuse it, along with its XPath mirror, as long as they run on the same thread.
+pub(crate) struct Page {
+    id: u64,
+    source: String
+}
+
+impl Page {
+
+    pub(crate) fn new(source: String) -> Page {
+        Page { id: NEXT_PAGE_ID.fetch_add(1, Ordering::Relaxed), source }
+    }
+
+    pub(crate) fn document(&self) -> Rc<HtmlDocument> {
+
+        PARSED.with(|parsed| {
+            let mut parsed = parsed.borrow_mut();
+
+            match parsed.as_ref() {
+                Some((id, document)) if *id == self.id => document.clone(),
+                _ => {
+                    let document = Rc::new(HtmlDocument::parse(&self.source));
+                    *parsed = Some((self.id, document.clone()));
+                    document
+                }
+            }
+        })
+    }
+}
+
+impl Drop for Page {
+    fn drop(&mut self) {
+
+        // Left over if the page moved to another thread: the next page parsed on this one replaces it.
+        let _ = PARSED.try_with(|parsed| {
+            if let Ok(mut parsed) = parsed.try_borrow_mut() {
+                if parsed.as_ref().is_some_and(|(id, _)| *id == self.id) {
+                    *parsed = None;
+                }
+            }
+        });
+    }
+}
+
+/// The page mirrored into an sxd document. <br>
+/// Mirrored elements are found back by their path, as the mirror has the same elements, in the same order.
+pub(crate) struct Mirror<'d, 'h> {
+    html: &'h Html,
+    document: dom::Document<'d>,
+    xpaths: &'h RefCell<HashMap<String, Rc<XPath>>>
+}
+
+impl<'d, 'h> Mirror<'d, 'h> {
+
+    /// It evaluates selector from the document root, or relative to each of contexts.
+    pub(crate) fn evaluate(&self, name: &str, selector: &Selector, contexts: Option<&[ElementRef<'h>]>) -> Result<Matches<'h>> {
+
+        let Some(xpath) = selector.to_xpath() else {
+            return self.select_css(name, selector, contexts);
+        };
+
+        let invalid = |reason: String| Error::InvalidSelector { selector: xpath.to_string(), reason };
+
+        let expression = self.compile(&xpath)?;
+
+        let context_nodes: Vec<nodeset::Node> = match contexts {
+            None => vec![self.document.root().into()],
+            Some(contexts) => contexts.iter().filter_map(|el| self.mirrored(el)).map(|el| el.into()).collect()
+        };
+
+        let mut seen = HashSet::new();
+        let mut elements = vec![];
+        let mut values = vec![];
+
+        for node in context_nodes {
+
+            let value = expression
+                .evaluate(&Context::new(), node)
+                .map_err(|e| invalid(e.to_string()))?;
+
+            let nodes = match value {
+                Value::Nodeset(nodes) => nodes.document_order(),
+                Value::Number(n) => { values.push(number_value(n)); continue; },
+                Value::String(s) => { values.push(serde_json::Value::String(s)); continue; },
+                Value::Boolean(b) => { values.push(serde_json::Value::Bool(b)); continue; }
+            };
+
+            for node in nodes {
+
+                let element = match node {
+                    nodeset::Node::Element(el) => self.parsed(el),
+                    _ => None
+                };
+
+                match element {
+                    Some(el) => if seen.insert(el.id()) { elements.push(el) },
+                    // Attributes, text nodes and the like are values.
+                    None => values.push(serde_json::Value::String(node.string_value()))
+                }
+            }
+        }
+
+        match (elements.is_empty(), values.is_empty()) {
+            (true, true) => Err(Error::ElementNotFound { name: name.to_string(), selector: xpath.to_string() }),
+            (false, true) => Ok(Matches::Elements(elements)),
+            (true, false) => Ok(Matches::Values(values)),
+            (false, false) => Err(invalid("the XPath mixes elements with other nodes".to_string()))
+        }
+    }
+
+    fn select_css(&self, name: &str, selector: &Selector, contexts: Option<&[ElementRef<'h>]>) -> Result<Matches<'h>> {
+
+        let css = parse_css(selector.expression())?;
+        let mut seen = HashSet::new();
+
+        let elements = match contexts {
+            None => self.html.select(&css).collect::<Vec<ElementRef>>(),
+            Some(contexts) => contexts.iter()
+                .flat_map(|el| el.select(&css))
+                .filter(|el| seen.insert(el.id()))
+                .collect()
+        };
+
+        if elements.is_empty() {
+            return Err(Error::ElementNotFound { name: name.to_string(), selector: selector.expression().to_string() });
+        }
+
+        Ok(Matches::Elements(elements))
+    }
+
+    /// The mirror of el.
+    fn mirrored(&self, el: &ElementRef<'h>) -> Option<dom::Element<'d>> {
+
+        // Positions among element siblings, from the top.
+        let mut path: Vec<usize> = std::iter::once(**el)
+            .chain(el.ancestors())
+            .filter(|node| node.value().is_element())
+            .map(|node| node.prev_siblings().filter(|s| s.value().is_element()).count())
+            .collect();
+        path.reverse();
+
+        let mut children = self.document.root().children().into_iter().filter_map(|c| c.element()).collect::<Vec<dom::Element>>();
+        let mut mirrored = None;
+
+        for position in path {
+            let el = *children.get(position)?;
+            children = el.children().into_iter().filter_map(|c| c.element()).collect();
+            mirrored = Some(el);
+        }
+
+        mirrored
+    }
+
+    /// The parsed element el mirrors.
+    fn parsed(&self, el: dom::Element<'d>) -> Option<ElementRef<'h>> {
+
+        let mut path = vec![];
+        let mut current = el;
+
+        loop {
+            let position = |siblings: Vec<dom::Element<'d>>| siblings.iter().position(|s| *s == current);
+
+            match current.parent()? {
+                dom::ParentOfChild::Element(parent) => {
+                    path.push(position(parent.children().into_iter().filter_map(|c| c.element()).collect())?);
+                    current = parent;
+                },
+                dom::ParentOfChild::Root(root) => {
+                    path.push(position(root.children().into_iter().filter_map(|c| c.element()).collect())?);
+                    break;
+                }
+            }
+        }
+
+        let mut node = self.html.tree.root();
+
+        for position in path.into_iter().rev() {
+            node = node.children().filter(|c| c.value().is_element()).nth(position)?;
+        }
+
+        ElementRef::wrap(node)
+    }
+
+    fn compile(&self, xpath: &str) -> Result<Rc<XPath>> {
+
+        if let Some(compiled) = self.xpaths.borrow().get(xpath) {
+            return Ok(compiled.clone());
+        }
+
+        let invalid = |reason: String| Error::InvalidSelector { selector: xpath.to_string(), reason };
+
+        let compiled = Rc::new(Factory::new()
+            .build(xpath)
+            .map_err(|e| invalid(e.to_string()))?
+            .ok_or_else(|| invalid("empty XPath".to_string()))?);
+
+        self.xpaths.borrow_mut().insert(xpath.to_string(), compiled.clone());
+
+        Ok(compiled)
+    }
+}
+
+/// What a selector yields: elements, or values for XPaths like //a/@href, //h1/text() or count(//a).
+pub enum Matches<'a> {
+    Elements(Vec<ElementRef<'a>>),
+    Values(Vec<serde_json::Value>)
+}
+
+impl<'a> Matches<'a> {
+
+    pub(crate) fn into_elements(self, selector: &Selector) -> Result<Vec<ElementRef<'a>>> {
+
+        match self {
+            Matches::Elements(elements) => Ok(elements),
+            Matches::Values(_) => Err(Error::InvalidSelector { selector: selector.expression().to_string(), reason: "the XPath doesn't select elements".to_string() })
+        }
+    }
+
+    /// It runs elements, as extract says, and values through transforms, failures aside. Without transforms, matches are left as they are.
+    pub(crate) fn transform(self, name: &str, extract: &Extract, transforms: &[Transform], page_url: Option<&Url>, failures: &mut Vec<TransformFailure>) -> Matches<'a> {
+
+        if transforms.is_empty() {
+            return self;
+        }
+
+        let values = match self {
+            Matches::Elements(els) if extract.is_element() => els.iter().map(|el| Extract::Text.apply(el)).collect(),
+            Matches::Elements(els) => els.iter().map(|el| extract.apply(el)).collect(),
+            Matches::Values(values) => values
+        };
+
+        Matches::Values(transform_values(name, values, transforms, page_url, failures))
+    }
+
+    /// It stores the matches under name, as elements or as values, replacing whatever name held.
+    pub(crate) fn store(self, name: &str, elements: &mut HashMap<String, Vec<DOMElement>>, values: &mut HashMap<String, Vec<serde_json::Value>>) -> usize {
+
+        self.store_as(name, &Extract::Element, elements, values)
+    }
+
+    /// Same as store, keeping what extract says of each element.
+    pub(crate) fn store_as(self, name: &str, extract: &Extract, elements: &mut HashMap<String, Vec<DOMElement>>, values: &mut HashMap<String, Vec<serde_json::Value>>) -> usize {
+
+        match self {
+            Matches::Elements(els) if extract.is_element() => {
+                values.remove(name);
+                elements.insert(name.to_string(), els.iter().map(|el| build_dom_element(el, *extract == Extract::ElementHtml)).collect());
+                els.len()
+            },
+            Matches::Elements(els) => Matches::Values(els.iter().map(|el| extract.apply(el)).collect()).store_as(name, extract, elements, values),
+            Matches::Values(vals) => {
+                elements.remove(name);
+                let count = vals.len();
+                values.insert(name.to_string(), vals);
+                count
+            }
+        }
+    }
+}
+
+/// XPath numbers are floats: whole ones become JSON integers, NaN and infinities null.
+pub(crate) fn number_value(n: f64) -> serde_json::Value {
+
+    if n.fract() == 0.0 && n.abs() < i64::MAX as f64 {
+        return serde_json::Value::from(n as i64);
+    }
+
+    serde_json::Number::from_f64(n).map_or(serde_json::Value::Null, serde_json::Value::Number)
+}
+
+fn parse_css(selector: &str) -> Result<scraper::Selector> {
+
+    scraper::Selector::parse(selector)
+        .map_err(|e| Error::InvalidSelector { selector: selector.to_string(), reason: format!("{:?}", e) })
+}
+
+/// It copies an element, and its whole subtree, into the sxd document.
+fn mirror_element<'d>(document: &dom::Document<'d>, node: NodeRef<Node>) -> Option<dom::Element<'d>> {
+
+    let Node::Element(element) = node.value() else {
+        return None;
+    };
+
+    let mirrored = document.create_element(element.name());
+
+    for (k, v) in element.attrs() {
+        mirrored.set_attribute_value(k, v);
+    }
+
+    for child in node.children() {
+        match child.value() {
+            Node::Element(_) => {
+                if let Some(el) = mirror_element(document, child) {
+                    mirrored.append_child(el);
+                }
+            },
+            Node::Text(text) => mirrored.append_child(document.create_text(text)),
+            Node::Comment(comment) => mirrored.append_child(document.create_comment(comment)),
+            _ => ()
+        }
+    }
+
+    Some(mirrored)
+}
+
+/// The DOMElement of el, with its tag and HTML if html is set.
+pub fn build_dom_element(el: &ElementRef, html: bool) -> DOMElement {
+
+    let attrs_map: HashMap<String, String> = el.value().attrs()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+    DOMElement {
+        text: el.text().collect::<String>().trim().to_string(),
+        attrs: attrs_map,
+        tag: html.then(|| el.value().name().to_string()),
+        inner_html: html.then(|| el.inner_html()),
+        outer_html: html.then(|| el.html())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::rc::Rc;
+
+    use super::{HtmlDocument, Matches, Page, Selector};
+
+    const PAGE: &str = r#"<!DOCTYPE html><html><body>
+        <!-- a comment -->
+        <div class="q"><a href="/1">One</a><span>1</span></div>
+        text
+        <div class="q"><a href="/2">Two</a><span>2</span><div><a href="/3">Three</a></div></div>
+    </body></html>"#;
+
+    fn texts(matches: Matches) -> Vec<String> {
+        match matches {
+            Matches::Elements(els) => els.iter().map(|el| el.text().collect()).collect(),
+            Matches::Values(values) => values.iter().map(|v| v.as_str().unwrap_or_default().to_string()).collect()
+        }
+    }
+
+    #[test]
+    fn page_is_parsed_once_per_navigation() {
+
+        let page = Page::new(PAGE.to_string());
+        assert!(Rc::ptr_eq(&page.document(), &page.document()));
+
+        let next = Page::new(PAGE.to_string());
+        assert!(!Rc::ptr_eq(&page.document(), &next.document()));
+    }
+
+    #[test]
+    fn xpath_elements_map_back_to_parsed_ones() {
+
+        let document = HtmlDocument::parse(PAGE);
+
+        let links = document.evaluate("links", &Selector::parse("//a")).unwrap();
+        assert_eq!(texts(links), vec!["One", "Two", "Three"]);
+
+        // The mirror is reused by the following queries.
+        let nested = document.evaluate("nested", &Selector::parse("//div/div/a")).unwrap();
+        assert_eq!(texts(nested), vec!["Three"]);
+
+        let hrefs = document.evaluate("hrefs", &Selector::parse("//a/@href")).unwrap();
+        assert_eq!(texts(hrefs), vec!["/1", "/2", "/3"]);
+    }
+
+    #[test]
+    fn xpath_runs_relative_to_parsed_parents() {
+
+        let document = HtmlDocument::parse(PAGE);
+
+        let spans = document.evaluate_within("spans", &Selector::parse("div.q"), &Selector::parse("./span")).unwrap();
+        assert_eq!(texts(spans), vec!["1", "2"]);
+
+        let links = document.evaluate_within("links", &Selector::parse("//div[@class='q'][2]"), &Selector::parse(".//a")).unwrap();
+        assert_eq!(texts(links), vec!["Two", "Three"]);
+    }
+}
@@ -0,0 +1,245 @@
+use std::collections::HashSet;
+
+use scraper::ElementRef;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use super::{html::Matches, transform::Pattern};
+
+/// Query parameters added by ad and analytics platforms, dropped by strip_tracking. utm_ ones go by prefix.
+const TRACKING_PARAMS: &[&str] = &["fbclid", "gclid", "dclid", "gbraid", "wbraid", "msclkid", "mc_cid", "mc_eid", "yclid", "igshid", "_ga", "_hsenc", "_hsmi"];
+
+/// Links turns a target into the absolute URLs its elements link to, e.g. `links: { same_host: true, include: /questions/ }`. <br>
+/// Hrefs resolve against the page URL, or its `<base href>`. Only http and https links are kept, in page order.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Links {
+
+    /// Only links to the exact host of the page: www.example.com and blog.example.com are different hosts.
+    #[serde(default)]
+    pub same_host: bool,
+
+    /// Only links matching this regex.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub include: Option<Pattern>,
+
+    /// No links matching this regex.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exclude: Option<Pattern>,
+
+    /// Each link once, where it first shows up. On by default.
+    #[serde(default = "enabled")]
+    pub dedupe: bool,
+
+    /// Without their #fragment. On by default.
+    #[serde(default = "enabled")]
+    pub strip_fragment: bool,
+
+    /// Without tracking query parameters, e.g. utm_source or fbclid.
+    #[serde(default)]
+    pub strip_tracking: bool
+}
+
+fn enabled() -> bool {
+    true
+}
+
+impl Default for Links {
+    fn default() -> Self {
+        Links { same_host: false, include: None, exclude: None, dedupe: true, strip_fragment: true, strip_tracking: false }
+    }
+}
+
+impl Links {
+
+    pub fn new() -> Links {
+        Links::default()
+    }
+
+    pub fn set_same_host(&mut self, same_host: bool) -> &mut Self {
+        self.same_host = same_host;
+        self
+    }
+
+    pub fn set_include(&mut self, include: Pattern) -> &mut Self {
+        self.include = Some(include);
+        self
+    }
+
+    pub fn set_exclude(&mut self, exclude: Pattern) -> &mut Self {
+        self.exclude = Some(exclude);
+        self
+    }
+
+    pub fn set_dedupe(&mut self, dedupe: bool) -> &mut Self {
+        self.dedupe = dedupe;
+        self
+    }
+
+    pub fn set_strip_fragment(&mut self, strip_fragment: bool) -> &mut Self {
+        self.strip_fragment = strip_fragment;
+        self
+    }
+
+    pub fn set_strip_tracking(&mut self, strip_tracking: bool) -> &mut Self {
+        self.strip_tracking = strip_tracking;
+        self
+    }
+
+    /// The links of what a target matched: the href of elements, or the values of XPaths like //a/@href.
+    pub(crate) fn collect(&self, matches: Matches, page_url: &Url, base_url: &Url) -> Vec<serde_json::Value> {
+
+        let hrefs: Vec<String> = match matches {
+            Matches::Elements(els) => els.iter().filter_map(|el: &ElementRef| el.value().attr("href")).map(str::to_string).collect(),
+            Matches::Values(values) => values.into_iter().filter_map(|v| v.as_str().map(str::to_string)).collect()
+        };
+
+        let mut seen = HashSet::new();
+
+        hrefs.iter()
+            .filter_map(|href| self.resolve(href, page_url, base_url))
+            .filter(|url| !self.dedupe || seen.insert(url.clone()))
+            .map(serde_json::Value::String)
+            .collect()
+    }
+
+    /// The absolute URL of href, None if it's filtered out.
+    fn resolve(&self, href: &str, page_url: &Url, base_url: &Url) -> Option<String> {
+
+        let mut url = base_url.join(href.trim()).ok()?;
+
+        if url.scheme() != "http" && url.scheme() != "https" {
+            return None;
+        }
+
+        if self.same_host && url.host_str() != page_url.host_str() {
+            return None;
+        }
+
+        if self.strip_fragment {
+            url.set_fragment(None);
+        }
+
+        if self.strip_tracking && url.query().is_some() {
+
+            let kept: Vec<(String, String)> = url.query_pairs()
+                .filter(|(k, _)| !k.starts_with("utm_") && !TRACKING_PARAMS.contains(&k.as_ref()))
+                .map(|(k, v)| (k.into_owned(), v.into_owned()))
+                .collect();
+
+            match kept.is_empty() {
+                true => url.set_query(None),
+                false => { url.query_pairs_mut().clear().extend_pairs(kept); }
+            }
+        }
+
+        let url = url.to_string();
+
+        let included = self.include.as_ref().is_none_or(|include| include.is_match(&url));
+        let excluded = self.exclude.as_ref().is_some_and(|exclude| exclude.is_match(&url));
+
+        (included && !excluded).then_some(url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::scraping::{html::HtmlDocument, selector::Selector};
+
+    use super::*;
+
+    const PAGE: &str = r##"<html><body>
+        <a href="/q/1?utm_source=feed&id=3#answers">One</a>
+        <a href="q/2?fbclid=abc">Two</a>
+        <a href="/q/1?id=3">One again</a>
+        <a href="https://blog.example.com/q/9">Blog</a>
+        <a href="mailto:me@example.com">Mail</a>
+        <a href="javascript:void(0)">Script</a>
+        <a href="/tags/rust">Rust</a>
+        <a>No href</a>
+    </body></html>"##;
+
+    fn links(links: &Links, html: &str) -> Vec<String> {
+
+        let document = HtmlDocument::parse(html);
+        let page_url = Url::parse("https://www.example.com/questions/list").unwrap();
+
+        let elements = document.select("links", &Selector::parse("a")).unwrap();
+
+        links.collect(Matches::Elements(elements), &page_url, &document.base_url(&page_url))
+            .into_iter()
+            .map(|url| url.as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn links_are_absolute_deduped_and_without_fragments() {
+
+        assert_eq!(links(&Links::new(), PAGE), vec![
+            "https://www.example.com/q/1?utm_source=feed&id=3",
+            "https://www.example.com/questions/q/2?fbclid=abc",
+            "https://www.example.com/q/1?id=3",
+            "https://blog.example.com/q/9",
+            "https://www.example.com/tags/rust"
+        ]);
+    }
+
+    #[test]
+    fn fragments_and_duplicates_may_be_kept() {
+
+        let html = r##"<a href="/a#x">A</a><a href="/a#x">A</a>"##;
+
+        assert_eq!(links(Links::new().set_strip_fragment(false), html), vec!["https://www.example.com/a#x"]);
+        assert_eq!(links(Links::new().set_dedupe(false), html), vec!["https://www.example.com/a", "https://www.example.com/a"]);
+    }
+
+    #[test]
+    fn tracking_parameters_are_stripped() {
+
+        assert_eq!(links(Links::new().set_strip_tracking(true), PAGE), vec![
+            "https://www.example.com/q/1?id=3",
+            "https://www.example.com/questions/q/2",
+            "https://blog.example.com/q/9",
+            "https://www.example.com/tags/rust"
+        ]);
+    }
+
+    #[test]
+    fn same_host_is_the_exact_host() {
+
+        let urls = links(Links::new().set_same_host(true), PAGE);
+
+        assert_eq!(urls.len(), 4);
+        assert!(urls.iter().all(|url| url.starts_with("https://www.example.com/")));
+    }
+
+    #[test]
+    fn include_and_exclude_match_absolute_urls() {
+
+        let mut options = Links::new();
+        options.set_include(Pattern::new(r"/q/").unwrap()).set_exclude(Pattern::new(r"[?&]id=").unwrap());
+
+        assert_eq!(links(&options, PAGE), vec!["https://www.example.com/questions/q/2?fbclid=abc", "https://blog.example.com/q/9"]);
+    }
+
+    #[test]
+    fn base_href_is_honored() {
+
+        let html = r#"<html><head><base href="https://cdn.example.com/docs/"></head><body><a href="a.html">A</a><a href="/b">B</a></body></html>"#;
+
+        assert_eq!(links(&Links::new(), html), vec!["https://cdn.example.com/docs/a.html", "https://cdn.example.com/b"]);
+
+        // same_host still compares with the page, not the base.
+        assert!(links(Links::new().set_same_host(true), html).is_empty());
+    }
+
+    #[test]
+    fn values_are_hrefs_too() {
+
+        let page_url = Url::parse("https://www.example.com/").unwrap();
+        let values = vec![serde_json::json!("/a"), serde_json::json!(3), serde_json::json!("ftp://x.com/f")];
+
+        assert_eq!(Links::new().collect(Matches::Values(values), &page_url, &page_url), vec![serde_json::json!("https://www.example.com/a")]);
+    }
+}
@@ -0,0 +1,295 @@
+use std::collections::BTreeMap;
+
+use log::warn;
+use scraper::{ElementRef, Html};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// The structured data a page embeds for search engines and social networks, scraped by a `metadata: true` target.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PageMetadata {
+
+    /// The `<title>` of the page.
+    pub title: Option<String>,
+
+    /// The `<meta name="description">` of the page.
+    pub description: Option<String>,
+
+    /// The `<link rel="canonical">` URL, made absolute.
+    pub canonical: Option<String>,
+
+    /// Every `application/ld+json` block, parsed. Malformed ones are left out.
+    pub json_ld: Vec<serde_json::Value>,
+
+    /// The top-level schema.org microdata items, i.e. itemscope elements that aren't an itemprop of another.
+    pub microdata: Vec<MetadataItem>,
+
+    /// The top-level RDFa items, i.e. typeof elements that aren't a property of another.
+    pub rdfa: Vec<MetadataItem>,
+
+    /// `og:` meta tags, without the prefix, e.g. title or image. Repeated tags, e.g. og:image, keep every value.
+    pub open_graph: BTreeMap<String, Vec<String>>,
+
+    /// `twitter:` meta tags, without the prefix, e.g. card or site.
+    pub twitter: BTreeMap<String, String>
+}
+
+/// A microdata or RDFa item: its types and properties. A property value is text, a URL, or a nested item.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MetadataItem {
+
+    /// e.g. https://schema.org/Product, or Product for RDFa with a vocab.
+    pub types: Vec<String>,
+
+    /// The itemid, or RDFa resource, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+
+    pub properties: BTreeMap<String, Vec<serde_json::Value>>
+}
+
+/// It reads the metadata of html, resolving URLs against base_url if given.
+pub(crate) fn page_metadata(html: &Html, base_url: Option<&Url>) -> PageMetadata {
+
+    let root = html.root_element();
+    let absolute = |href: &str| base_url.and_then(|base| base.join(href.trim()).ok()).map_or_else(|| href.trim().to_string(), |url| url.to_string());
+
+    let mut metadata = PageMetadata::default();
+
+    for el in root.descendants().filter_map(ElementRef::wrap) {
+
+        let value = el.value();
+
+        match value.name() {
+            "title" if metadata.title.is_none() => metadata.title = Some(normalized_text(&el)),
+            "link" if value.attr("rel").is_some_and(|rel| rel.split_whitespace().any(|r| r.eq_ignore_ascii_case("canonical"))) => {
+                if let Some(href) = value.attr("href") {
+                    metadata.canonical.get_or_insert_with(|| absolute(href));
+                }
+            },
+            "script" if value.attr("type").is_some_and(|t| t.trim().eq_ignore_ascii_case("application/ld+json")) => {
+                match serde_json::from_str(&el.text().collect::<String>()) {
+                    Ok(json) => metadata.json_ld.push(json),
+                    Err(e) => warn!("Malformed JSON-LD block left out: {}", e)
+                }
+            },
+            "meta" => {
+                // OpenGraph uses property, Twitter cards name, and both show up in the wild.
+                let key = value.attr("property").or_else(|| value.attr("name")).unwrap_or_default().trim();
+                let content = value.attr("content").unwrap_or_default().trim().to_string();
+
+                if let Some(og) = key.strip_prefix("og:") {
+                    metadata.open_graph.entry(og.to_string()).or_default().push(content);
+                } else if let Some(twitter) = key.strip_prefix("twitter:") {
+                    metadata.twitter.entry(twitter.to_string()).or_insert(content);
+                } else if key.eq_ignore_ascii_case("description") && metadata.description.is_none() {
+                    metadata.description = Some(content);
+                }
+            },
+            _ => ()
+        }
+
+        // Items that are properties of others show up within them. Any other is top-level, even nested in another item.
+        if value.attr("itemscope").is_some() && value.attr("itemprop").is_none() {
+            metadata.microdata.push(microdata_item(el, &absolute));
+        }
+
+        if value.attr("typeof").is_some() && value.attr("property").is_none() {
+            metadata.rdfa.push(rdfa_item(el, &absolute));
+        }
+    }
+
+    metadata
+}
+
+fn microdata_item(scope: ElementRef, absolute: &dyn Fn(&str) -> String) -> MetadataItem {
+
+    let value = scope.value();
+
+    let mut item = MetadataItem {
+        types: value.attr("itemtype").unwrap_or_default().split_whitespace().map(str::to_string).collect(),
+        id: value.attr("itemid").map(|id| id.trim().to_string()),
+        properties: BTreeMap::new()
+    };
+
+    collect_properties(scope, "itemprop", "itemscope", &mut item, &|el| match el.value().attr("itemscope") {
+        Some(_) => serde_json::to_value(microdata_item(el, absolute)).unwrap_or_default(),
+        None => serde_json::Value::String(microdata_value(el, absolute))
+    });
+
+    item
+}
+
+fn rdfa_item(scope: ElementRef, absolute: &dyn Fn(&str) -> String) -> MetadataItem {
+
+    let value = scope.value();
+    // The vocab applies to the whole subtree, nested items included.
+    let vocab = std::iter::once(scope).chain(scope.ancestors().filter_map(ElementRef::wrap))
+        .find_map(|el| el.value().attr("vocab"))
+        .map(str::trim)
+        .unwrap_or_default();
+
+    let mut item = MetadataItem {
+        types: value.attr("typeof").unwrap_or_default().split_whitespace().map(|t| format!("{}{}", vocab, t)).collect(),
+        id: value.attr("resource").or_else(|| value.attr("about")).map(absolute),
+        properties: BTreeMap::new()
+    };
+
+    collect_properties(scope, "property", "typeof", &mut item, &|el| match el.value().attr("typeof") {
+        Some(_) => serde_json::to_value(rdfa_item(el, absolute)).unwrap_or_default(),
+        None => serde_json::Value::String(rdfa_value(el, absolute))
+    });
+
+    item
+}
+
+/// It adds to item the properties found below scope, down to nested items, which are properties themselves but keep their own.
+fn collect_properties(scope: ElementRef, property: &str, nested: &str, item: &mut MetadataItem, value_of: &dyn Fn(ElementRef) -> serde_json::Value) {
+
+    for child in scope.children().filter_map(ElementRef::wrap) {
+
+        if let Some(names) = child.value().attr(property) {
+            let value = value_of(child);
+            for name in names.split_whitespace() {
+                item.properties.entry(name.to_string()).or_default().push(value.clone());
+            }
+        }
+
+        if child.value().attr(nested).is_none() {
+            collect_properties(child, property, nested, item, value_of);
+        }
+    }
+}
+
+/// The value of a microdata property, as the WHATWG spec reads it off its element.
+fn microdata_value(el: ElementRef, absolute: &dyn Fn(&str) -> String) -> String {
+
+    let value = el.value();
+    let attr = |name: &str| value.attr(name).unwrap_or_default().trim().to_string();
+
+    match value.name() {
+        "meta" => attr("content"),
+        "audio" | "embed" | "iframe" | "img" | "source" | "track" | "video" => absolute(&attr("src")),
+        "a" | "area" | "link" => absolute(&attr("href")),
+        "object" => absolute(&attr("data")),
+        "data" | "meter" => attr("value"),
+        "time" if value.attr("datetime").is_some() => attr("datetime"),
+        _ => normalized_text(&el)
+    }
+}
+
+/// The value of an RDFa property: its content, the resource it points to, or its text.
+fn rdfa_value(el: ElementRef, absolute: &dyn Fn(&str) -> String) -> String {
+
+    let value = el.value();
+
+    if let Some(content) = value.attr("content") {
+        return content.trim().to_string();
+    }
+
+    match ["resource", "href", "src"].iter().find_map(|name| value.attr(name)) {
+        Some(url) => absolute(url),
+        None => normalized_text(&el)
+    }
+}
+
+fn normalized_text(el: &ElementRef) -> String {
+    el.text().collect::<String>().split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+
+    use serde_json::json;
+
+    use super::*;
+
+    fn metadata(html: &str) -> PageMetadata {
+        page_metadata(&Html::parse_document(html), Url::parse("https://example.com/shop/shoe").ok().as_ref())
+    }
+
+    fn item(types: &[&str], properties: serde_json::Value) -> MetadataItem {
+        MetadataItem {
+            types: types.iter().map(|t| t.to_string()).collect(),
+            id: None,
+            properties: serde_json::from_value(properties).unwrap()
+        }
+    }
+
+    #[test]
+    fn head_tags_are_read() {
+
+        let metadata = metadata(r#"<html><head>
+            <title> Shoe  shop </title>
+            <meta name="description" content="Shoes for all">
+            <link rel="alternate canonical" href="../shoes/1">
+            <meta property="og:image" content="/a.png"><meta property="og:image" content="/b.png">
+            <meta name="twitter:card" content="summary"><meta name="twitter:card" content="ignored">
+        </head></html>"#);
+
+        assert_eq!(metadata.title.as_deref(), Some("Shoe shop"));
+        assert_eq!(metadata.description.as_deref(), Some("Shoes for all"));
+        assert_eq!(metadata.canonical.as_deref(), Some("https://example.com/shoes/1"));
+        assert_eq!(metadata.open_graph["image"], vec!["/a.png", "/b.png"]);
+        assert_eq!(metadata.twitter["card"], "summary");
+    }
+
+    #[test]
+    fn malformed_json_ld_is_left_out() {
+
+        let metadata = metadata(r#"<script type="application/ld+json">{"@type": "Product", "name": "Shoe"}</script>
+            <script type="application/ld+json">{"@type": "Product",</script>
+            <script type="Application/LD+JSON ">[1, 2]</script>"#);
+
+        assert_eq!(metadata.json_ld, vec![json!({"@type": "Product", "name": "Shoe"}), json!([1, 2])]);
+    }
+
+    #[test]
+    fn microdata_items_nest() {
+
+        let metadata = metadata(r#"<div itemscope itemtype="https://schema.org/Product" itemid="urn:shoe">
+            <span itemprop="name">Shoe</span>
+            <a itemprop="url" href="/shoe">link</a>
+            <div itemprop="offers" itemscope itemtype="https://schema.org/Offer"><meta itemprop="price" content="10"></div>
+            <div itemscope itemtype="https://schema.org/Review"><span itemprop="author">Ann</span></div>
+        </div>"#);
+
+        let mut product = item(&["https://schema.org/Product"], json!({
+            "name": ["Shoe"],
+            "url": ["https://example.com/shoe"],
+            "offers": [{"types": ["https://schema.org/Offer"], "properties": {"price": ["10"]}}]
+        }));
+        product.id = Some("urn:shoe".to_string());
+
+        assert_eq!(metadata.microdata, vec![product, item(&["https://schema.org/Review"], json!({"author": ["Ann"]}))]);
+    }
+
+    #[test]
+    fn rdfa_items_nest() {
+
+        let metadata = metadata(r#"<div vocab="https://schema.org/" typeof="Product">
+            <span property="name">Shoe</span>
+            <div property="offers" typeof="Offer"><span property="price" content="10">ten</span></div>
+            <div typeof="Review"><span property="author">Ann</span></div>
+        </div>"#);
+
+        assert_eq!(metadata.rdfa, vec![
+            item(&["https://schema.org/Product"], json!({
+                "name": ["Shoe"],
+                "offers": [{"types": ["https://schema.org/Offer"], "properties": {"price": ["10"]}}]
+            })),
+            item(&["https://schema.org/Review"], json!({"author": ["Ann"]}))
+        ]);
+    }
+
+    #[test]
+    fn rdfa_without_vocab_keeps_its_types() {
+
+        let metadata = metadata(r#"<div typeof="schema:Person" resource="/ann"><a property="schema:url" href="/ann/home">Ann</a></div>"#);
+
+        let mut person = item(&["schema:Person"], json!({"schema:url": ["https://example.com/ann/home"]}));
+        person.id = Some("https://example.com/ann".to_string());
+
+        assert_eq!(metadata.rdfa, vec![person]);
+    }
+}
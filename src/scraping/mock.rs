@@ -0,0 +1,406 @@
+use std::{collections::{BTreeMap, HashMap}, path::{Path, PathBuf}, rc::Rc, sync::{Arc, Mutex}};
+
+use crate::error::{Error, Result};
+
+use super::{Backend, ScrapingResult, ScreenshotFormat, render_elements, query, html::HtmlDocument, extract::Extract, links::Links, record::Field, table::Table, transform::Transform, selector::Selector};
+
+/// A backend call, as recorded by MockBackend.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MockStep {
+    Navigate(String),
+    QueryCss { name: String, selector: String },
+    QueryXPath { name: String, selector: String },
+    QueryWithin { name: String, parent: String, selector: String },
+    QueryExtract { name: String, parent: Option<String>, selector: String, extract: Extract, transforms: Vec<Transform> },
+    QueryLinks { name: String, parent: Option<String>, selector: String, links: Links },
+    QueryTable { name: String, parent: Option<String>, selector: String, table: Table },
+    QueryMetadata { name: String },
+    QueryContent { name: String, parent: Option<String>, selector: Option<String> },
+    QueryRecords { name: String, parent: Option<String>, selector: String, fields: BTreeMap<String, Field> },
+    Click { name: String, selector: String },
+    TypeInto { name: String, selector: String, text: String },
+    Screenshot { name: String, selector: String },
+    Wait(u64),
+    Save { targets: Vec<String>, flatten: bool, save_path: Option<String> }
+}
+
+#[derive(Default)]
+struct MockState {
+
+    /// HTML of every known page, by URL.
+    fixtures: HashMap<String, String>,
+
+    /// Page reached by clicking a selector, by selector.
+    clicks: HashMap<String, String>,
+
+    current_url: Option<String>,
+
+    steps: Vec<MockStep>,
+
+    /// JSON that save would have written, in call order.
+    saved: Vec<String>
+}
+
+/// MockBackend answers a pipeline from in-memory HTML fixtures, without any browser or network. <br>
+/// Clicks swap the current page to another fixture, and every backend call is recorded,
+/// so a ScrapingPipeline run can be asserted on in plain cargo test. <br>
+/// Clones share the same fixtures, steps and saved JSON: keep one to inspect what the pipeline did with the other.
+/// What a clone scrapes is only collected from that clone, as with any backend.
+#[derive(Default)]
+pub struct MockBackend {
+    state: Arc<Mutex<MockState>>,
+
+    scraped: ScrapingResult
+}
+
+impl Clone for MockBackend {
+    fn clone(&self) -> Self {
+        MockBackend { state: self.state.clone(), scraped: ScrapingResult::default() }
+    }
+}
+
+impl MockBackend {
+
+    pub fn new() -> MockBackend {
+        MockBackend::default()
+    }
+
+    /// It serves html when url is navigated to.
+    pub fn add_fixture<S: AsRef<str>>(&mut self, url: S, html: S) -> &mut MockBackend {
+
+        self.lock().fixtures.insert(url.as_ref().to_string(), html.as_ref().to_string());
+        self
+    }
+
+    /// It serves the content of an HTML file when url is navigated to.
+    pub fn add_fixture_file<S: AsRef<str>, P: AsRef<Path>>(&mut self, url: S, path: P) -> Result<&mut MockBackend> {
+
+        let html = std::fs::read_to_string(path)?;
+        self.lock().fixtures.insert(url.as_ref().to_string(), html);
+
+        Ok(self)
+    }
+
+    /// It makes a click on selector load the fixture of url, as a link would.
+    pub fn on_click<S: AsRef<str>>(&mut self, selector: S, url: S) -> &mut MockBackend {
+
+        self.lock().clicks.insert(selector.as_ref().to_string(), url.as_ref().to_string());
+        self
+    }
+
+    /// Every backend call received so far.
+    pub fn steps(&self) -> Vec<MockStep> {
+        self.lock().steps.clone()
+    }
+
+    /// Every JSON document the pipeline saved so far.
+    pub fn saved(&self) -> Vec<String> {
+        self.lock().saved.clone()
+    }
+
+    pub fn current_url(&self) -> Option<String> {
+        self.lock().current_url.clone()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MockState> {
+        // A panicking assertion mustn't hide the recorded steps from the next one.
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn record(&self, step: MockStep) {
+        self.lock().steps.push(step);
+    }
+
+    /// It fails like a real browser would if nothing matches selector on the current fixture.
+    fn ensure_element(&mut self, name: &str, selector: &str) -> Result<()> {
+
+        let selector = Selector::parse(selector);
+
+        self.document(name, Some(&selector))?.select(name, &selector).map(|_| ())
+    }
+}
+
+impl Backend for MockBackend {
+
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    fn navigate(&mut self, url: &str) -> Result<()> {
+
+        self.record(MockStep::Navigate(url.to_string()));
+
+        let mut state = self.lock();
+
+        if !state.fixtures.contains_key(url) {
+            state.current_url = None;
+            return Err(Error::Navigation { url: url.to_string(), reason: "no fixture for this URL".to_string() });
+        }
+
+        state.current_url = Some(url.to_string());
+
+        Ok(())
+    }
+
+    fn current_url(&self) -> Option<String> {
+        MockBackend::current_url(self)
+    }
+
+    /// The fixture of the current URL, parsed.
+    fn document(&mut self, _name: &str, _wait_for: Option<&Selector>) -> Result<Rc<HtmlDocument>> {
+
+        let state = self.lock();
+
+        let url = state.current_url.as_ref().ok_or(Error::NotNavigated)?;
+
+        Ok(Rc::new(HtmlDocument::parse(&state.fixtures[url])))
+    }
+
+    fn scraped(&mut self) -> &mut ScrapingResult {
+        &mut self.scraped
+    }
+
+    fn query_css(&mut self, name: &str, selector: &str) -> Result<usize> {
+
+        self.record(MockStep::QueryCss { name: name.to_string(), selector: selector.to_string() });
+        query::or_empty(query::css(self, name, selector), &mut self.scraped, name, true)
+    }
+
+    fn query_xpath(&mut self, name: &str, selector: &str) -> Result<usize> {
+
+        self.record(MockStep::QueryXPath { name: name.to_string(), selector: selector.to_string() });
+        query::or_empty(query::xpath(self, name, selector), &mut self.scraped, name, true)
+    }
+
+    fn query_within(&mut self, name: &str, parent: &str, selector: &str) -> Result<usize> {
+
+        self.record(MockStep::QueryWithin { name: name.to_string(), parent: parent.to_string(), selector: selector.to_string() });
+        query::or_empty(query::within(self, name, parent, selector), &mut self.scraped, name, true)
+    }
+
+    fn query_extract(&mut self, name: &str, parent: Option<&str>, selector: &str, extract: &Extract, transforms: &[Transform]) -> Result<usize> {
+
+        self.record(MockStep::QueryExtract { name: name.to_string(), parent: parent.map(str::to_string), selector: selector.to_string(), extract: extract.clone(), transforms: transforms.to_vec() });
+
+        let elements = extract.is_element() && transforms.is_empty();
+        query::or_empty(query::extract(self, name, parent, selector, extract, transforms), &mut self.scraped, name, elements)
+    }
+
+    fn query_links(&mut self, name: &str, parent: Option<&str>, selector: &str, links: &Links) -> Result<usize> {
+
+        self.record(MockStep::QueryLinks { name: name.to_string(), parent: parent.map(str::to_string), selector: selector.to_string(), links: links.clone() });
+        query::or_empty(query::links(self, name, parent, selector, links), &mut self.scraped, name, false)
+    }
+
+    fn query_table(&mut self, name: &str, parent: Option<&str>, selector: &str, table: &Table) -> Result<usize> {
+
+        self.record(MockStep::QueryTable { name: name.to_string(), parent: parent.map(str::to_string), selector: selector.to_string(), table: table.clone() });
+        query::or_empty(query::table(self, name, parent, selector, table), &mut self.scraped, name, false)
+    }
+
+    fn query_metadata(&mut self, name: &str) -> Result<()> {
+
+        self.record(MockStep::QueryMetadata { name: name.to_string() });
+        query::metadata(self, name)
+    }
+
+    fn query_content(&mut self, name: &str, parent: Option<&str>, selector: Option<&str>) -> Result<usize> {
+
+        self.record(MockStep::QueryContent { name: name.to_string(), parent: parent.map(str::to_string), selector: selector.map(str::to_string) });
+        query::or_empty(query::content(self, name, parent, selector), &mut self.scraped, name, false)
+    }
+
+    fn query_records(&mut self, name: &str, parent: Option<&str>, selector: &str, fields: &BTreeMap<String, Field>) -> Result<usize> {
+
+        self.record(MockStep::QueryRecords { name: name.to_string(), parent: parent.map(str::to_string), selector: selector.to_string(), fields: fields.clone() });
+        query::or_empty(query::records(self, name, parent, selector, fields), &mut self.scraped, name, false)
+    }
+
+    fn click(&mut self, name: &str, selector: &str) -> Result<()> {
+
+        self.record(MockStep::Click { name: name.to_string(), selector: selector.to_string() });
+        self.ensure_element(name, selector)?;
+
+        let target = self.lock().clicks.get(selector).cloned();
+
+        match target {
+            Some(url) => self.navigate(&url),
+            None => Ok(())
+        }
+    }
+
+    fn type_into(&mut self, name: &str, selector: &str, text: &str) -> Result<()> {
+
+        self.record(MockStep::TypeInto { name: name.to_string(), selector: selector.to_string(), text: text.to_string() });
+        self.ensure_element(name, selector)
+    }
+
+    fn screenshot(&mut self, name: &str, selector: &str, _format: ScreenshotFormat) -> Result<()> {
+
+        self.record(MockStep::Screenshot { name: name.to_string(), selector: selector.to_string() });
+        self.ensure_element(name, selector)?;
+
+        self.scraped.screenshots.insert(name.to_string(), vec![]);
+
+        Ok(())
+    }
+
+    fn wait(&mut self, seconds: u64) {
+        self.record(MockStep::Wait(seconds));
+    }
+
+    fn save(&self, targets: &[String], flatten: bool, save_path: Option<&str>) -> Result<PathBuf> {
+
+        self.record(MockStep::Save { targets: targets.to_vec(), flatten, save_path: save_path.map(str::to_string) });
+
+        let json = render_elements(&self.scraped.elements, &self.scraped.values, &self.scraped.metadata, targets, &flatten)?;
+
+        let mut state = self.lock();
+        state.saved.push(json);
+
+        Ok(match save_path {
+            Some(save_path) => PathBuf::from("mock").join(save_path),
+            None => PathBuf::from(format!("mock/{}.json", state.saved.len()))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::pipeline::{PipelineConfig, ScrapingPipeline};
+
+    use super::*;
+
+    const PAGE_1: &str = r#"
+    <html><body>
+        <a class="s-link" href="/questions/1">First question</a>
+        <a class="s-link" href="/questions/2">Second question</a>
+        <a title="Go to page 2" href="?page=2">2</a>
+    </body></html>"#;
+
+    const PAGE_2: &str = r#"
+    <html><body>
+        <a class="s-link" href="/questions/3">Third question</a>
+    </body></html>"#;
+
+    const PIPELINE: &str = r#"
+version: 2
+name: Questions
+url: page1
+headless: true
+out_dir: out
+timeout: 5
+targets:
+  questions:
+    selector: "//a[contains(@class, 's-link')]"
+  links:
+    selector: "//a[contains(@class, 's-link')]/@href"
+actions:
+  next:
+    type: CLICK
+    selector: "//a[contains(@title, 'Go to page 2')]"
+  save:
+    type: SAVE
+    flatten: false
+    targets:
+      - questions
+      - links
+steps:
+  - questions
+  - next
+  - links
+  - save
+"#;
+
+    fn mock() -> MockBackend {
+
+        let mut mock = MockBackend::new();
+        mock.add_fixture("page1", PAGE_1)
+            .add_fixture("page2", PAGE_2)
+            .on_click("//a[contains(@title, 'Go to page 2')]", "page2");
+
+        mock
+    }
+
+    #[test]
+    fn pipeline_runs_on_fixtures() {
+
+        let mock = mock();
+        let config = PipelineConfig::from_yaml(PIPELINE).unwrap();
+        let mut pipeline = ScrapingPipeline::with_backend(config, Box::new(mock.clone()));
+
+        let res = pipeline.run("page1").unwrap();
+
+        let texts: Vec<&str> = res.elements["questions"].iter().map(|e| e.text.as_str()).collect();
+        assert_eq!(texts, ["First question", "Second question"]);
+        assert_eq!(res.values["links"], [serde_json::json!("/questions/3")]);
+        assert_eq!(mock.current_url(), Some("page2".to_string()));
+    }
+
+    #[test]
+    fn pipeline_steps_are_recorded() {
+
+        let mock = mock();
+        let config = PipelineConfig::from_yaml(PIPELINE).unwrap();
+        let mut pipeline = ScrapingPipeline::with_backend(config, Box::new(mock.clone()));
+
+        pipeline.run("page1").unwrap();
+
+        let steps = mock.steps();
+
+        assert_eq!(steps.first(), Some(&MockStep::Navigate("page1".to_string())));
+        assert!(steps.contains(&MockStep::Click { name: "next".to_string(), selector: "//a[contains(@title, 'Go to page 2')]".to_string() }));
+        assert_eq!(steps.last(), Some(&MockStep::Save { targets: vec!["questions".to_string(), "links".to_string()], flatten: false, save_path: None }));
+
+        let click = steps.iter().position(|step| matches!(step, MockStep::Click { .. })).unwrap();
+        let links = steps.iter().position(|step| matches!(step, MockStep::QueryXPath { name, .. } if name == "links")).unwrap();
+        assert!(click < links);
+    }
+
+    #[test]
+    fn pipeline_saves_targets() {
+
+        let mock = mock();
+        let config = PipelineConfig::from_yaml(PIPELINE).unwrap();
+        let mut pipeline = ScrapingPipeline::with_backend(config, Box::new(mock.clone()));
+
+        pipeline.run("page1").unwrap();
+
+        let saved = mock.saved();
+        assert_eq!(saved.len(), 1);
+
+        let json: serde_json::Value = serde_json::from_str(&saved[0]).unwrap();
+        assert_eq!(json["questions"].as_array().map(Vec::len), Some(2));
+        assert_eq!(json["questions"][1]["text"], "Second question");
+        assert_eq!(json["links"], serde_json::json!(["/questions/3"]));
+    }
+
+    #[test]
+    fn click_without_page_stays() {
+
+        let mut mock = MockBackend::new();
+        mock.add_fixture("page1", PAGE_1);
+
+        let config = PipelineConfig::from_yaml(PIPELINE).unwrap();
+        let mut pipeline = ScrapingPipeline::with_backend(config, Box::new(mock.clone()));
+
+        let res = pipeline.run("page1").unwrap();
+
+        assert_eq!(mock.current_url(), Some("page1".to_string()));
+        assert_eq!(res.values["links"], [serde_json::json!("/questions/1"), serde_json::json!("/questions/2")]);
+    }
+
+    #[test]
+    fn missing_elements_fail_clicks() {
+
+        let mut mock = MockBackend::new();
+        mock.add_fixture("page1", PAGE_2);
+
+        let config = PipelineConfig::from_yaml(PIPELINE).unwrap();
+        let mut pipeline = ScrapingPipeline::with_backend(config, Box::new(mock.clone()));
+
+        assert!(pipeline.run("page1").is_err());
+        assert!(mock.saved().is_empty());
+    }
+}
@@ -1,2 +1,400 @@
+use std::{collections::{BTreeMap, HashMap}, path::PathBuf, rc::Rc};
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::error::{Error, Result};
+
+use extract::Extract;
+use links::Links;
+use metadata::PageMetadata;
+use record::Field;
+use table::Table;
+use transform::{Transform, TransformFailure};
+use selector::{Selector, SelectorKind};
+use html::HtmlDocument;
+
 pub mod simple;
-pub mod chrome;
\ No newline at end of file
+pub mod chrome;
+pub mod mock;
+pub mod async_chrome;
+pub mod rate_limit;
+pub mod robots;
+pub mod selector;
+pub mod record;
+pub mod extract;
+pub mod transform;
+pub mod links;
+pub mod table;
+pub mod metadata;
+pub mod content;
+pub mod scrape;
+pub(crate) mod html;
+pub(crate) mod query;
+
+#[derive(Clone, Serialize, Deserialize)]
+/// It contains all the metadata of a scraped element.
+pub struct DOMElement {
+    
+    pub text: String,
+
+    /// Serialized by name, so saved output doesn't change from run to run.
+    #[serde(serialize_with = "sorted_attrs")]
+    pub attrs: HashMap<String, String>,
+
+    /// Only kept with `extract: element_html`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inner_html: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outer_html: Option<String>
+}
+
+#[allow(clippy::upper_case_acronyms)]
+pub enum ScreenshotFormat {
+    JPEG,
+    PNG
+}
+
+/// What was scraped from a page, by target name. <br>
+/// It's serializable with serde, e.g. to ship it to another process as JSON, screenshots as base64 strings.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScrapingResult {
+
+    pub elements: HashMap<String, Vec<DOMElement>>,
+
+    /// What targets yielding values rather than elements scraped, e.g. //a/@href, //h1/text() or count(//a).
+    pub values: HashMap<String, Vec<serde_json::Value>>,
+
+    #[serde(with = "base64_screenshots")]
+    pub screenshots: HashMap<String, Vec<u8>>,
+
+    /// What metadata targets found, by target name.
+    pub metadata: HashMap<String, PageMetadata>,
+
+    /// Values the transforms of their target failed on, replaced by null.
+    pub failures: Vec<TransformFailure>,
+
+    /// Files written by the SAVE actions of a pipeline, in step order.
+    pub saved: Vec<PathBuf>
+}
+
+impl ScrapingResult {
+
+    /// It deserializes the targets into T, each target into the field of the same name, e.g. `titles: Vec<String>` for a titles target. <br>
+    /// A target is a list: of DOMElement, of values (e.g. strings, or records into structs), or a single PageMetadata for metadata targets, so fields are Vec<_> but for the latter. <br>
+    /// A name among both elements and values, say, is ambiguous: it fails with Error::Serialization.
+    pub fn deserialize_into<T: DeserializeOwned>(&self) -> Result<T> {
+
+        let mut targets = serde_json::Map::new();
+        let mut kinds: HashMap<&str, &str> = HashMap::new();
+
+        let elements = self.elements.iter().map(|(name, elements)| (name, "elements", serde_json::to_value(elements)));
+        let values = self.values.iter().map(|(name, values)| (name, "values", serde_json::to_value(values)));
+        let metadata = self.metadata.iter().map(|(name, metadata)| (name, "metadata", serde_json::to_value(metadata)));
+
+        for (name, kind, value) in elements.chain(values).chain(metadata) {
+
+            // A target is in one of them only, unless the result was put together by hand: neither should silently win.
+            if let Some(other) = kinds.insert(name, kind) {
+                return Err(Error::Serialization(format!("target {} is both in {} and {}", name, other, kind)));
+            }
+
+            targets.insert(name.clone(), value?);
+        }
+
+        Ok(serde_json::from_value(serde_json::Value::Object(targets))?)
+    }
+}
+
+fn sorted_attrs<S: serde::Serializer>(attrs: &HashMap<String, String>, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    attrs.iter().collect::<BTreeMap<_, _>>().serialize(serializer)
+}
+
+/// Screenshots as base64 strings, rather than arrays of bytes.
+mod base64_screenshots {
+
+    use std::collections::HashMap;
+
+    use serde::{Deserialize, Deserializer, Serializer, de, ser::SerializeMap};
+
+    pub fn serialize<S: Serializer>(screenshots: &HashMap<String, Vec<u8>>, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+
+        let mut map = serializer.serialize_map(Some(screenshots.len()))?;
+        for (name, data) in screenshots {
+            map.serialize_entry(name, &base64::encode(data))?;
+        }
+        map.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<HashMap<String, Vec<u8>>, D::Error> {
+
+        HashMap::<String, String>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(name, data)| match base64::decode(&data) {
+                Ok(data) => Ok((name, data)),
+                Err(e) => Err(de::Error::custom(format!("screenshot {} is not valid base64: {}", name, e)))
+            })
+            .collect()
+    }
+}
+
+/// The operations a ScrapingPipeline runs against a page. <br>
+/// Both the Chrome Scraper and the HTTP-only SimpleScraper implement it.
+/// Operations a backend can't perform (e.g. a CLICK without a browser) report Error::Unsupported.
+pub trait Backend {
+
+    /// A short name for error messages, e.g. "chrome" or "http".
+    fn name(&self) -> &'static str;
+
+    fn navigate(&mut self, url: &str) -> Result<()>;
+
+    /// The URL of the current page, None before navigating.
+    fn current_url(&self) -> Option<String>;
+
+    /// It returns the current page, parsed, which the query_* methods read. <br>
+    /// Backends rendering pages, e.g. Chrome, first wait for an element matching wait_for to show up, if given: its absence fails with Error::ElementNotFound.
+    fn document(&mut self, name: &str, wait_for: Option<&Selector>) -> Result<Rc<HtmlDocument>>;
+
+    /// What the queries stored since the last collect.
+    fn scraped(&mut self) -> &mut ScrapingResult;
+
+    /// It stores the elements matching a CSS selector under name, returning how many matched. <br>
+    /// No match is not an error: an empty list is stored.
+    fn query_css(&mut self, name: &str, selector: &str) -> Result<usize> {
+        query::or_empty(query::css(self, name, selector), self.scraped(), name, true)
+    }
+
+    /// Same as query_css, with an XPath. <br>
+    /// XPaths yielding attributes, text nodes, numbers, strings or booleans store them as values instead of elements.
+    fn query_xpath(&mut self, name: &str, selector: &str) -> Result<usize> {
+        query::or_empty(query::xpath(self, name, selector), self.scraped(), name, true)
+    }
+
+    /// Same as query_css, with a selector of any kind: css:, xpath: and text: prefixes pick it, see Selector.
+    fn query(&mut self, name: &str, selector: &str) -> Result<usize> {
+
+        match Selector::parse(selector).kind() {
+            SelectorKind::Css => self.query_css(name, selector),
+            SelectorKind::XPath | SelectorKind::Text => self.query_xpath(name, selector)
+        }
+    }
+
+    /// Same as query, but selector is evaluated relative to each element matching parent, e.g. ./span for the spans of each parent.
+    fn query_within(&mut self, name: &str, parent: &str, selector: &str) -> Result<usize> {
+        query::or_empty(query::within(self, name, parent, selector), self.scraped(), name, true)
+    }
+
+    /// Same as query, relative to the elements matching parent if any, keeping what extract says of each element, e.g. its href. <br>
+    /// Unless extract keeps elements, what it yields is stored as values, run through transforms if any: elements are then kept as text.
+    fn query_extract(&mut self, name: &str, parent: Option<&str>, selector: &str, extract: &Extract, transforms: &[Transform]) -> Result<usize> {
+        let elements = extract.is_element() && transforms.is_empty();
+        query::or_empty(query::extract(self, name, parent, selector, extract, transforms), self.scraped(), name, elements)
+    }
+
+    /// It stores under name the absolute URLs the elements matching selector link to, relative to the elements matching parent if any. <br>
+    /// links tells which to keep, see Links. URLs are stored as values.
+    fn query_links(&mut self, name: &str, parent: Option<&str>, selector: &str, links: &Links) -> Result<usize> {
+        query::or_empty(query::links(self, name, parent, selector, links), self.scraped(), name, false)
+    }
+
+    /// It stores under name a record per body row of the tables matching selector, relative to the elements matching parent if any. See Table. <br>
+    /// Records are stored as values.
+    fn query_table(&mut self, name: &str, parent: Option<&str>, selector: &str, table: &Table) -> Result<usize> {
+        query::or_empty(query::table(self, name, parent, selector, table), self.scraped(), name, false)
+    }
+
+    /// It stores under name the structured data of the whole page: JSON-LD, microdata, RDFa, OpenGraph and Twitter tags, canonical URL and title.
+    fn query_metadata(&mut self, name: &str) -> Result<()> {
+        query::metadata(self, name)
+    }
+
+    /// It stores under name the readable content, as Markdown and plain text, of each element matching selector, relative to the elements matching parent if any. <br>
+    /// Without a selector, it's the main article of the page. Contents are stored as values, see Content.
+    fn query_content(&mut self, name: &str, parent: Option<&str>, selector: Option<&str>) -> Result<usize> {
+        query::or_empty(query::content(self, name, parent, selector), self.scraped(), name, false)
+    }
+
+    /// It stores under name a record per element matching selector, relative to the elements matching parent if any, returning how many. <br>
+    /// Records are JSON objects of fields found relative to their element, see Field, and are stored as values.
+    fn query_records(&mut self, name: &str, parent: Option<&str>, selector: &str, fields: &BTreeMap<String, Field>) -> Result<usize> {
+        query::or_empty(query::records(self, name, parent, selector, fields), self.scraped(), name, false)
+    }
+
+    fn click(&mut self, _name: &str, _selector: &str) -> Result<()> {
+        Err(self.unsupported("CLICK"))
+    }
+
+    fn type_into(&mut self, _name: &str, _selector: &str, _text: &str) -> Result<()> {
+        Err(self.unsupported("TYPE_INTO"))
+    }
+
+    fn screenshot(&mut self, _name: &str, _selector: &str, _format: ScreenshotFormat) -> Result<()> {
+        Err(self.unsupported("SCREENSHOT"))
+    }
+
+    fn wait(&mut self, seconds: u64) {
+        std::thread::sleep(std::time::Duration::from_secs(seconds));
+    }
+
+    /// It writes the given targets as JSON, returning the path of the written file. <br>
+    /// The file is save_path, if given, in the save directory, or is named after the current URL.
+    fn save(&self, targets: &[String], flatten: bool, save_path: Option<&str>) -> Result<PathBuf>;
+
+    /// It returns everything scraped since the last call, and forgets it.
+    fn collect(&mut self) -> ScrapingResult {
+        std::mem::take(self.scraped())
+    }
+
+    /// It trades the current page for a fresh one, releasing what the previous pages held on to. <br>
+    /// Long-lived backends, e.g. pooled ones, call it every few pages.
+    fn recycle(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn unsupported(&self, operation: &str) -> Error {
+        Error::Unsupported { backend: self.name().to_string(), operation: operation.to_string() }
+    }
+}
+
+/// It writes the given targets of a page as JSON in save_dir, returning the path of the written file. <br>
+/// The file is named after the last segment of the page URL.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn save_elements(elements: &HashMap<String, Vec<DOMElement>>, values: &HashMap<String, Vec<serde_json::Value>>, metadata: &HashMap<String, PageMetadata>, current_url: Option<&String>, save_dir: &str, targets: &[String], flatten: &bool, save_path: Option<&str>) -> Result<PathBuf> {
+
+    let curr_url = current_url.ok_or(Error::NotNavigated)?;
+    let name = curr_url.rsplit('/').next().unwrap_or_default();
+    let name = name.split_once('.').unwrap_or((name, ""));
+    let name = name.0.to_string();
+
+    let options = sanitize_filename::Options {
+        truncate: false, // true by default, truncates to 255 bytes
+        windows: false, // default value depends on the OS, removes reserved names like `con` from start of strings on Windows
+        replacement: "_" // str to replace sanitized chars/strings
+    };
+
+    let name = sanitize_filename::sanitize_with_options(name, options);
+    // $URL$ in save_path stands for the name taken from the URL.
+    let file_name = match save_path {
+        Some(save_path) => save_path.replace("$URL$", &name),
+        None => format!("{}.json", name)
+    };
+
+    let save_path = std::path::Path::new(save_dir).join(file_name);
+
+    if let Some(parent) = save_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    
+    //println!("{}", &save_path);
+    let s = render_elements(elements, values, metadata, targets, flatten)?;
+
+    std::fs::write(&save_path, s)?;
+    
+    Ok(save_path)
+}
+
+/// It serializes the given targets as pretty JSON: a map of target name to elements or values, or a single list if flatten is set.
+pub(crate) fn render_elements(elements: &HashMap<String, Vec<DOMElement>>, values: &HashMap<String, Vec<serde_json::Value>>, metadata: &HashMap<String, PageMetadata>, targets: &[String], flatten: &bool) -> Result<String> {
+
+    let mut els = serde_json::Map::new();
+
+    for target in targets {
+        if let Some(target_els) = elements.get(target) {
+            els.insert(target.clone(), serde_json::to_value(target_els)?);
+        } else if let Some(target_values) = values.get(target) {
+            els.insert(target.clone(), serde_json::Value::from(target_values.clone()));
+        } else if let Some(target_metadata) = metadata.get(target) {
+            els.insert(target.clone(), serde_json::to_value(target_metadata)?);
+        }
+    }
+
+    let s = if *flatten {
+
+        let els = els.into_iter()
+            .flat_map(|(_, v)| match v {
+                serde_json::Value::Array(items) => items,
+                v => vec![v]
+            })
+            .collect::<Vec<serde_json::Value>>();
+        serde_json::to_string_pretty(&els)?
+    } else {
+        serde_json::to_string_pretty(&els)?
+    };
+
+    Ok(s)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn scraped() -> ScrapingResult {
+
+        let mut scraped = ScrapingResult::default();
+
+        scraped.elements.insert("links".to_string(), vec![DOMElement {
+            text: "One".to_string(),
+            attrs: HashMap::from([("href".to_string(), "/1".to_string())]),
+            tag: None,
+            inner_html: None,
+            outer_html: None
+        }]);
+        scraped.values.insert("titles".to_string(), vec![serde_json::json!("First"), serde_json::json!("Second")]);
+        scraped.screenshots.insert("page".to_string(), vec![0x89, b'P', b'N', b'G', 0, 255, 10]);
+        scraped.metadata.insert("meta".to_string(), PageMetadata { title: Some("Page".to_string()), ..PageMetadata::default() });
+        scraped.saved.push(PathBuf::from("out/page.json"));
+
+        scraped
+    }
+
+    #[derive(Deserialize)]
+    struct Page {
+        links: Vec<DOMElement>,
+        titles: Vec<String>,
+        meta: PageMetadata
+    }
+
+    #[test]
+    fn round_trips_through_json() {
+
+        let json = serde_json::to_string(&scraped()).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(value["screenshots"]["page"], "iVBORwD/Cg==");
+
+        let back: ScrapingResult = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(back.screenshots["page"], scraped().screenshots["page"]);
+        assert_eq!(back.saved, vec![PathBuf::from("out/page.json")]);
+        assert_eq!(serde_json::to_value(&back).unwrap(), value);
+
+        let page: Page = back.deserialize_into().unwrap();
+
+        assert_eq!((page.links[0].text.as_str(), page.links[0].attrs["href"].as_str()), ("One", "/1"));
+        assert_eq!(page.titles, vec!["First", "Second"]);
+        assert_eq!(page.meta.title.as_deref(), Some("Page"));
+    }
+
+    #[test]
+    fn invalid_screenshots_fail() {
+        assert!(serde_json::from_str::<ScrapingResult>(r#"{"screenshots": {"page": "not base64!"}}"#).is_err());
+    }
+
+    #[test]
+    fn targets_in_two_places_fail() {
+
+        let mut scraped = scraped();
+        scraped.values.insert("links".to_string(), vec![]);
+
+        match scraped.deserialize_into::<Page>() {
+            Err(Error::Serialization(reason)) => assert_eq!(reason, "target links is both in elements and values"),
+            Err(e) => panic!("expected a serialization error, got {}", e),
+            Ok(_) => panic!("expected a serialization error")
+        }
+    }
+}
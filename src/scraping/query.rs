@@ -0,0 +1,183 @@
+use std::collections::BTreeMap;
+
+use url::Url;
+
+use crate::error::{Error, Result};
+
+use super::{Backend, ScrapingResult, extract::Extract, html::{HtmlDocument, Matches}, links::Links, record::Field, scrape::Scrape, selector::{Selector, SelectorKind}, table::Table, transform::Transform};
+
+/// It turns a query finding nothing into an empty list stored under name, of elements if elements were looked for, else of values. <br>
+/// Any other result is returned as is.
+pub(crate) fn or_empty(found: Result<usize>, scraped: &mut ScrapingResult, name: &str, elements: bool) -> Result<usize> {
+
+    match found {
+        Err(Error::ElementNotFound { .. }) if elements => {
+            scraped.values.remove(name);
+            scraped.elements.insert(name.to_string(), vec![]);
+            Ok(0)
+        },
+        Err(Error::ElementNotFound { .. }) => {
+            scraped.elements.remove(name);
+            scraped.values.insert(name.to_string(), vec![]);
+            Ok(0)
+        },
+        found => found
+    }
+}
+
+/// It stores the elements matching a CSS selector under name, returning how many matched.
+pub(crate) fn css<B: Backend + ?Sized>(backend: &mut B, name: &str, selector: &str) -> Result<usize> {
+
+    let selector = Selector::parse_as(selector, SelectorKind::Css);
+    selector.expect(SelectorKind::Css)?;
+
+    let document = backend.document(name, Some(&selector))?;
+    let elements = document.select(name, &selector)?;
+
+    let scraped = backend.scraped();
+    Ok(Matches::Elements(elements).store(name, &mut scraped.elements, &mut scraped.values))
+}
+
+/// Same as css, with an XPath, which may yield values rather than elements.
+pub(crate) fn xpath<B: Backend + ?Sized>(backend: &mut B, name: &str, selector: &str) -> Result<usize> {
+
+    let selector = Selector::parse_as(selector, SelectorKind::XPath);
+    selector.expect(SelectorKind::XPath)?;
+
+    let document = backend.document(name, Some(&selector))?;
+    let matches = document.evaluate(name, &selector)?;
+
+    let scraped = backend.scraped();
+    Ok(matches.store(name, &mut scraped.elements, &mut scraped.values))
+}
+
+/// Same as xpath, with a selector of any kind, relative to each element matching parent.
+pub(crate) fn within<B: Backend + ?Sized>(backend: &mut B, name: &str, parent: &str, selector: &str) -> Result<usize> {
+
+    let parent = Selector::parse(parent);
+
+    let document = backend.document(name, Some(&parent))?;
+    let matches = document.evaluate_within(name, &parent, &Selector::parse(selector))?;
+
+    let scraped = backend.scraped();
+    Ok(matches.store(name, &mut scraped.elements, &mut scraped.values))
+}
+
+/// It stores what extract says of each element matching selector, relative to the elements matching parent if any, run through transforms.
+pub(crate) fn extract<B: Backend + ?Sized>(backend: &mut B, name: &str, parent: Option<&str>, selector: &str, extract: &Extract, transforms: &[Transform]) -> Result<usize> {
+
+    let (parent, selector) = (parent.map(Selector::parse), Selector::parse(selector));
+
+    let document = backend.document(name, Some(parent.as_ref().unwrap_or(&selector)))?;
+    let matches = document.evaluate_from(name, parent.as_ref(), &selector)?;
+
+    let base_url = base_url(backend, &document);
+
+    let scraped = backend.scraped();
+    Ok(matches
+        .transform(name, extract, transforms, base_url.as_ref(), &mut scraped.failures)
+        .store_as(name, extract, &mut scraped.elements, &mut scraped.values))
+}
+
+/// It stores the absolute URLs the elements matching selector link to, relative to the elements matching parent if any, as links says.
+pub(crate) fn links<B: Backend + ?Sized>(backend: &mut B, name: &str, parent: Option<&str>, selector: &str, links: &Links) -> Result<usize> {
+
+    let (parent, selector) = (parent.map(Selector::parse), Selector::parse(selector));
+
+    let document = backend.document(name, Some(parent.as_ref().unwrap_or(&selector)))?;
+    let matches = document.evaluate_from(name, parent.as_ref(), &selector)?;
+
+    let url = backend.current_url().ok_or(Error::NotNavigated)?;
+    let page_url = Url::parse(&url).map_err(|e| Error::Navigation { url, reason: e.to_string() })?;
+
+    let urls = links.collect(matches, &page_url, &document.base_url(&page_url));
+
+    let scraped = backend.scraped();
+    Ok(Matches::Values(urls).store(name, &mut scraped.elements, &mut scraped.values))
+}
+
+/// It stores a record per body row of the tables matching selector, relative to the elements matching parent if any.
+pub(crate) fn table<B: Backend + ?Sized>(backend: &mut B, name: &str, parent: Option<&str>, selector: &str, table: &Table) -> Result<usize> {
+
+    let (parent, selector) = (parent.map(Selector::parse), Selector::parse(selector));
+
+    let document = backend.document(name, Some(parent.as_ref().unwrap_or(&selector)))?;
+    let matches = document.evaluate_from(name, parent.as_ref(), &selector)?;
+
+    let scraped = backend.scraped();
+    Ok(Matches::Values(table.collect(matches)).store(name, &mut scraped.elements, &mut scraped.values))
+}
+
+/// It stores the structured data of the whole page under name.
+pub(crate) fn metadata<B: Backend + ?Sized>(backend: &mut B, name: &str) -> Result<()> {
+
+    let document = backend.document(name, None)?;
+    let base_url = base_url(backend, &document);
+
+    let scraped = backend.scraped();
+    scraped.elements.remove(name);
+    scraped.values.remove(name);
+    scraped.metadata.insert(name.to_string(), document.metadata(base_url.as_ref()));
+
+    Ok(())
+}
+
+/// It stores the readable content of each element matching selector, relative to the elements matching parent if any, or of the main article.
+pub(crate) fn content<B: Backend + ?Sized>(backend: &mut B, name: &str, parent: Option<&str>, selector: Option<&str>) -> Result<usize> {
+
+    let (parent, selector) = (parent.map(Selector::parse), selector.map(Selector::parse));
+
+    // The main article needs the whole page, not an element to wait for.
+    let wait_for = selector.as_ref().map(|selector| parent.as_ref().unwrap_or(selector));
+
+    let document = backend.document(name, wait_for)?;
+    let base_url = base_url(backend, &document);
+
+    let contents = document.content(name, parent.as_ref(), selector.as_ref(), base_url.as_ref())?;
+    let values = contents.iter().map(serde_json::to_value).collect::<std::result::Result<Vec<_>, _>>()?;
+
+    let scraped = backend.scraped();
+    Ok(Matches::Values(values).store(name, &mut scraped.elements, &mut scraped.values))
+}
+
+/// It stores a record of fields per element matching selector, relative to the elements matching parent if any.
+pub(crate) fn records<B: Backend + ?Sized>(backend: &mut B, name: &str, parent: Option<&str>, selector: &str, fields: &BTreeMap<String, Field>) -> Result<usize> {
+
+    let (parent, selector) = (parent.map(Selector::parse), Selector::parse(selector));
+
+    let document = backend.document(name, Some(parent.as_ref().unwrap_or(&selector)))?;
+    let base_url = base_url(backend, &document);
+
+    let scraped = backend.scraped();
+    let records = document.records(name, parent.as_ref(), &selector, fields, base_url.as_ref(), &mut scraped.failures)?;
+
+    Ok(Matches::Values(records).store(name, &mut scraped.elements, &mut scraped.values))
+}
+
+/// It scrapes a T out of each of its containers on the current page, none if no container matches.
+pub(crate) fn scrape<T: Scrape, B: Backend + ?Sized>(backend: &mut B) -> Result<Vec<T>> {
+
+    let selector = Selector::parse(&T::selector());
+
+    let document = match backend.document(T::NAME, Some(&selector)) {
+        Err(Error::ElementNotFound { .. }) => return Ok(vec![]),
+        document => document?
+    };
+
+    let base_url = base_url(backend, &document);
+
+    let records = match document.records(T::NAME, None, &selector, &T::fields(), base_url.as_ref(), &mut backend.scraped().failures) {
+        Err(Error::ElementNotFound { .. }) => vec![],
+        records => records?
+    };
+
+    records.iter().map(T::from_record).collect()
+}
+
+/// The URL links of the current page resolve against, if it has a valid one.
+fn base_url<B: Backend + ?Sized>(backend: &B, document: &HtmlDocument) -> Option<Url> {
+
+    backend.current_url()
+        .and_then(|url| Url::parse(&url).ok())
+        .map(|url| document.base_url(&url))
+}
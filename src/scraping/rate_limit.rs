@@ -0,0 +1,353 @@
+use std::{collections::HashMap, sync::{Arc, Condvar, Mutex}, time::{Duration, Instant, SystemTime}};
+
+use log::info;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Serialize, Deserialize};
+
+/// RateLimit is how politely a host is scraped. <br>
+/// In a pipeline file, it's the optional rate_limit section, e.g.
+/// ```yaml
+/// rate_limit:
+///   per_host: 2
+///   min_delay_ms: 1000
+///   jitter_ms: 500
+/// ```
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimit {
+
+    /// Navigations running at the same time on one host. 0 means no cap.
+    pub per_host: usize,
+
+    /// Minimum time between the start of two navigations to the same host.
+    pub min_delay_ms: u64,
+
+    /// Random extra delay added to min_delay_ms, up to this.
+    pub jitter_ms: u64,
+
+    /// Whether a 429 or 503 answer pauses its host for as long as its Retry-After header says.
+    pub retry_after: bool,
+
+    /// Longest pause a Retry-After header can cause, in seconds.
+    pub max_retry_after: u64
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self { per_host: 0, min_delay_ms: 0, jitter_ms: 0, retry_after: true, max_retry_after: 300 }
+    }
+}
+
+impl RateLimit {
+
+    pub fn set_per_host(&mut self, per_host: usize) -> &mut RateLimit {
+        self.per_host = per_host;
+        self
+    }
+
+    pub fn set_min_delay_ms(&mut self, min_delay_ms: u64) -> &mut RateLimit {
+        self.min_delay_ms = min_delay_ms;
+        self
+    }
+
+    pub fn set_jitter_ms(&mut self, jitter_ms: u64) -> &mut RateLimit {
+        self.jitter_ms = jitter_ms;
+        self
+    }
+
+    pub fn set_retry_after(&mut self, retry_after: bool) -> &mut RateLimit {
+        self.retry_after = retry_after;
+        self
+    }
+
+    pub fn set_max_retry_after(&mut self, max_retry_after: u64) -> &mut RateLimit {
+        self.max_retry_after = max_retry_after;
+        self
+    }
+}
+
+struct HostState {
+    active: usize,
+
+    /// Navigations to the host can't start before this.
+    next_slot: Instant,
+
+    /// The host's robots.txt Crawl-delay, if it's stricter than min_delay_ms.
+    crawl_delay: Duration
+}
+
+/// HostLimiter enforces a RateLimit, host by host, across every scraper sharing it. <br>
+/// Scrapers built from the same builder share the same HostLimiter, and so do the backends of a PipelineRunner.
+pub struct HostLimiter {
+    rate_limit: RateLimit,
+    hosts: Mutex<HashMap<String, HostState>>,
+    released: Condvar
+}
+
+/// The right to navigate to a host, until dropped.
+pub struct HostPermit {
+    limiter: Arc<HostLimiter>,
+    host: Option<String>
+}
+
+impl HostLimiter {
+
+    pub fn new(rate_limit: RateLimit) -> HostLimiter {
+        HostLimiter { rate_limit, hosts: Mutex::default(), released: Condvar::new() }
+    }
+
+    pub fn rate_limit(&self) -> &RateLimit {
+        &self.rate_limit
+    }
+
+    /// It waits until url's host can take one more navigation. <br>
+    /// URLs without a host (e.g. file://) are never limited.
+    pub fn acquire(self: &Arc<Self>, url: &str) -> HostPermit {
+
+        let host = match host_of(url) {
+            Some(host) => host,
+            None => return HostPermit { limiter: self.clone(), host: None }
+        };
+
+        let t0 = Instant::now();
+        let mut hosts = self.lock();
+
+        loop {
+            let now = Instant::now();
+            let state = hosts.entry(host.clone()).or_insert(HostState { active: 0, next_slot: now, crawl_delay: Duration::ZERO });
+
+            if self.rate_limit.per_host > 0 && state.active >= self.rate_limit.per_host {
+                hosts = self.released.wait(hosts).unwrap_or_else(|e| e.into_inner());
+                continue;
+            }
+
+            if state.next_slot > now {
+                let wait = state.next_slot - now;
+                hosts = self.released.wait_timeout(hosts, wait).unwrap_or_else(|e| e.into_inner()).0;
+                continue;
+            }
+
+            state.active += 1;
+            state.next_slot = now + self.delay(state.crawl_delay);
+            break;
+        }
+
+        if t0.elapsed() > Duration::from_millis(100) {
+            info!("[RATE_LIMIT] Waited {:.2?} for {}", t0.elapsed(), host);
+        }
+
+        HostPermit { limiter: self.clone(), host: Some(host) }
+    }
+
+    /// It holds off url's host for the given time, e.g. the Retry-After of a 429 answer.
+    pub fn pause(&self, url: &str, retry_after: Duration) {
+
+        if !self.rate_limit.retry_after {
+            return;
+        }
+
+        if let Some(host) = host_of(url) {
+
+            let until = Instant::now() + retry_after.min(Duration::from_secs(self.rate_limit.max_retry_after));
+            let mut hosts = self.lock();
+
+            let state = hosts.entry(host).or_insert(HostState { active: 0, next_slot: until, crawl_delay: Duration::ZERO });
+            state.next_slot = state.next_slot.max(until);
+        }
+    }
+
+    /// It spaces out the navigations to url's host by at least crawl_delay, as its robots.txt asks.
+    pub fn set_crawl_delay(&self, url: &str, crawl_delay: Duration) {
+
+        if let Some(host) = host_of(url) {
+
+            let mut hosts = self.lock();
+
+            let state = hosts.entry(host).or_insert(HostState { active: 0, next_slot: Instant::now(), crawl_delay });
+            state.crawl_delay = crawl_delay;
+        }
+    }
+
+    fn delay(&self, crawl_delay: Duration) -> Duration {
+
+        let jitter = match self.rate_limit.jitter_ms {
+            0 => 0,
+            max => StdRng::from_entropy().gen_range(0..=max)
+        };
+
+        Duration::from_millis(self.rate_limit.min_delay_ms).max(crawl_delay) + Duration::from_millis(jitter)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, HostState>> {
+        self.hosts.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Drop for HostPermit {
+    fn drop(&mut self) {
+
+        if let Some(host) = &self.host {
+
+            if let Some(state) = self.limiter.lock().get_mut(host) {
+                state.active -= 1;
+            }
+
+            self.limiter.released.notify_all();
+        }
+    }
+}
+
+fn host_of(url: &str) -> Option<String> {
+    url::Url::parse(url).ok()?.host_str().map(|h| h.to_lowercase())
+}
+
+/// Whether an HTTP status asks the client to slow down.
+pub(crate) fn is_throttling(status: u16) -> bool {
+    status == 429 || status == 503
+}
+
+/// It reads a Retry-After header, given either in seconds or as an HTTP date.
+pub(crate) fn parse_retry_after(value: &str) -> Option<Duration> {
+
+    let value = value.trim();
+
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = httpdate::parse_http_date(value).ok()?;
+
+    Some(date.duration_since(SystemTime::now()).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::thread;
+
+    use super::*;
+
+    const URL: &str = "https://example.com/a";
+
+    fn limiter(configure: impl FnOnce(&mut RateLimit) -> &mut RateLimit) -> Arc<HostLimiter> {
+
+        let mut rate_limit = RateLimit::default();
+        configure(&mut rate_limit);
+
+        Arc::new(HostLimiter::new(rate_limit))
+    }
+
+    /// How long acquiring URL takes, the permit dropped right away.
+    fn wait(limiter: &Arc<HostLimiter>, url: &str) -> Duration {
+
+        let t0 = Instant::now();
+        drop(limiter.acquire(url));
+
+        t0.elapsed()
+    }
+
+    #[test]
+    fn navigations_are_spaced_by_min_delay() {
+
+        let limiter = limiter(|r| r.set_min_delay_ms(100));
+
+        assert!(wait(&limiter, URL) < Duration::from_millis(500));
+        assert!(wait(&limiter, URL) >= Duration::from_millis(100));
+
+        // Other hosts have slots of their own.
+        assert!(wait(&limiter, "https://other.com/") < Duration::from_millis(500));
+    }
+
+    #[test]
+    fn per_host_serialises_navigations() {
+
+        let limiter = limiter(|r| r.set_per_host(1));
+        let permit = limiter.acquire(URL);
+
+        let waiting = {
+            let limiter = limiter.clone();
+            thread::spawn(move || wait(&limiter, "https://EXAMPLE.com/b"))
+        };
+
+        thread::sleep(Duration::from_millis(150));
+        drop(permit);
+
+        assert!(waiting.join().unwrap() >= Duration::from_millis(150));
+    }
+
+    #[test]
+    fn no_cap_lets_navigations_overlap() {
+
+        let limiter = limiter(|r| r);
+        let _permit = limiter.acquire(URL);
+
+        let waiting = {
+            let limiter = limiter.clone();
+            thread::spawn(move || wait(&limiter, URL))
+        };
+
+        assert!(waiting.join().unwrap() < Duration::from_millis(500));
+    }
+
+    #[test]
+    fn stricter_crawl_delays_win() {
+
+        let limiter = limiter(|r| r.set_min_delay_ms(10));
+        limiter.set_crawl_delay(URL, Duration::from_millis(120));
+
+        wait(&limiter, URL);
+        assert!(wait(&limiter, URL) >= Duration::from_millis(120));
+    }
+
+    #[test]
+    fn pauses_hold_hosts_off() {
+
+        let limiter = limiter(|r| r);
+        limiter.pause(URL, Duration::from_millis(120));
+
+        assert!(wait(&limiter, URL) >= Duration::from_millis(120));
+    }
+
+    #[test]
+    fn pauses_are_capped_or_ignored() {
+
+        let capped = limiter(|r| r.set_max_retry_after(0));
+        capped.pause(URL, Duration::from_secs(60));
+        assert!(wait(&capped, URL) < Duration::from_millis(500));
+
+        let ignored = limiter(|r| r.set_retry_after(false));
+        ignored.pause(URL, Duration::from_secs(60));
+        assert!(wait(&ignored, URL) < Duration::from_millis(500));
+    }
+
+    #[test]
+    fn urls_without_host_are_not_limited() {
+
+        let limiter = limiter(|r| r.set_per_host(1).set_min_delay_ms(1000));
+        let _permit = limiter.acquire("file:///tmp/a.html");
+
+        assert!(wait(&limiter, "file:///tmp/a.html") < Duration::from_millis(500));
+    }
+
+    #[test]
+    fn retry_after_is_read_in_seconds_or_as_a_date() {
+
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after(" 0 "), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("soon"), None);
+        assert_eq!(parse_retry_after("-5"), None);
+
+        // Dates in the past are no wait at all.
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), Some(Duration::ZERO));
+
+        let later = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(90));
+        let wait = parse_retry_after(&later).unwrap();
+        assert!(wait > Duration::from_secs(85) && wait <= Duration::from_secs(90), "{:?}", wait);
+    }
+
+    #[test]
+    fn throttling_statuses() {
+        assert!(is_throttling(429) && is_throttling(503));
+        assert!(!is_throttling(500) && !is_throttling(200));
+    }
+}
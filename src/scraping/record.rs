@@ -0,0 +1,153 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Deserializer, Serialize, de::{self, MapAccess, Visitor}};
+
+use crate::error::{Error, Result};
+
+use url::Url;
+
+use super::{extract::Extract, html::{Matches, Mirror}, selector::Selector, transform::{Transform, TransformFailure, transform_value}};
+
+/// A named part of a record, found relative to its container, e.g. `title: h2 a` or `votes: ./span/@data-votes`. <br>
+/// It's the text of the first element matching selector, or null if none does, unless extract keeps something else of it. <br>
+/// With all, it's the list of every match instead, and with fields, matches are records themselves.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Field {
+    pub selector: String,
+
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub all: bool,
+
+    #[serde(skip_serializing_if = "is_text")]
+    pub extract: Extract,
+
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, Field>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub transform: Vec<Transform>
+}
+
+impl Field {
+
+    pub fn new(selector: &str) -> Field {
+        Field { selector: selector.to_string(), all: false, extract: Extract::Text, fields: BTreeMap::new(), transform: vec![] }
+    }
+
+    /// It keeps this of each match instead of its text, e.g. Extract::Attr("href").
+    pub fn set_extract(&mut self, extract: Extract) -> &mut Self {
+        self.extract = extract;
+        self
+    }
+
+    /// It keeps every match instead of the first one.
+    pub fn set_all(&mut self, all: bool) -> &mut Self {
+        self.all = all;
+        self
+    }
+
+    /// It runs the field through transform, after the others, e.g. Transform::ParseInt.
+    pub fn add_transform(&mut self, transform: Transform) -> &mut Self {
+        self.transform.push(transform);
+        self
+    }
+
+    /// It turns each match into a record of these fields.
+    pub fn add_field(&mut self, name: &str, field: Field) -> &mut Self {
+        self.fields.insert(name.to_string(), field);
+        self
+    }
+}
+
+/// Field as written in full, next to its selector-only shorthand.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FieldDefinition {
+    selector: String,
+
+    #[serde(default)]
+    all: bool,
+
+    #[serde(default = "text")]
+    extract: Extract,
+
+    #[serde(default)]
+    fields: BTreeMap<String, Field>,
+
+    #[serde(default)]
+    transform: Vec<Transform>
+}
+
+fn text() -> Extract {
+    Extract::Text
+}
+
+fn is_text(extract: &Extract) -> bool {
+    *extract == Extract::Text
+}
+
+impl<'de> Deserialize<'de> for Field {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Field, D::Error> {
+
+        struct FieldVisitor;
+
+        impl<'de> Visitor<'de> for FieldVisitor {
+            type Value = Field;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a selector, or a map with a selector and optionally all, extract, fields and transform")
+            }
+
+            fn visit_str<E: de::Error>(self, selector: &str) -> std::result::Result<Field, E> {
+                Ok(Field::new(selector))
+            }
+
+            fn visit_map<M: MapAccess<'de>>(self, map: M) -> std::result::Result<Field, M::Error> {
+
+                let definition = FieldDefinition::deserialize(de::value::MapAccessDeserializer::new(map))?;
+
+                Ok(Field { selector: definition.selector, all: definition.all, extract: definition.extract, fields: definition.fields, transform: definition.transform })
+            }
+        }
+
+        deserializer.deserialize_any(FieldVisitor)
+    }
+}
+
+/// It builds the record of fields of each container, in order. Failing field transforms are reported as target.field of the record.
+pub(crate) fn extract_records<'h>(mirror: &Mirror<'_, 'h>, target: &str, containers: &[scraper::ElementRef<'h>], fields: &BTreeMap<String, Field>, page_url: Option<&Url>, failures: &mut Vec<TransformFailure>) -> Result<Vec<serde_json::Value>> {
+
+    containers.iter()
+        .enumerate()
+        .map(|(index, container)| extract_record(mirror, target, index, container, fields, page_url, failures))
+        .collect()
+}
+
+fn extract_record<'h>(mirror: &Mirror<'_, 'h>, target: &str, index: usize, container: &scraper::ElementRef<'h>, fields: &BTreeMap<String, Field>, page_url: Option<&Url>, failures: &mut Vec<TransformFailure>) -> Result<serde_json::Value> {
+
+    let mut record = serde_json::Map::new();
+
+    for (name, field) in fields {
+
+        let selector = Selector::parse(&field.selector);
+        let path = format!("{}.{}", target, name);
+
+        let values = match mirror.evaluate(name, &selector, Some(std::slice::from_ref(container))) {
+            Ok(Matches::Elements(elements)) if field.fields.is_empty() => elements.iter().map(|el| field.extract.apply(el)).collect(),
+            Ok(Matches::Elements(elements)) => extract_records(mirror, &path, &elements, &field.fields, page_url, failures)?,
+            Ok(Matches::Values(values)) => values,
+            // A missing field is null, not a missing record.
+            Err(Error::ElementNotFound { .. }) => vec![],
+            Err(e) => return Err(e)
+        };
+
+        let value = match field.all {
+            true => serde_json::Value::Array(values),
+            false => values.into_iter().next().unwrap_or(serde_json::Value::Null)
+        };
+
+        record.insert(name.clone(), transform_value(&path, index, value, &field.transform, page_url, failures));
+    }
+
+    Ok(serde_json::Value::Object(record))
+}
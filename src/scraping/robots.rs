@@ -0,0 +1,386 @@
+use std::{collections::HashMap, sync::{Arc, Mutex}, time::{Duration, Instant}};
+
+use log::{info, warn};
+use reqwest::blocking::Client;
+use serde::{Serialize, Deserialize};
+
+use crate::{proxy::SimpleProxy, error::{Error, Result}};
+
+use super::rate_limit::HostLimiter;
+
+/// How long a robots.txt is trusted before being fetched again.
+const ROBOTS_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How long an unreachable robots.txt keeps its host off limits before being tried again.
+const UNREACHABLE_TTL: Duration = Duration::from_secs(60);
+
+/// RobotsConfig is the optional robots section of a pipeline file, e.g.
+/// ```yaml
+/// robots:
+///   user_agent: mybot
+/// ```
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RobotsConfig {
+
+    /// The product token matched against the User-agent lines of robots.txt.
+    pub user_agent: String
+}
+
+impl Default for RobotsConfig {
+    fn default() -> Self {
+        Self { user_agent: "webscrape".to_string() }
+    }
+}
+
+/// The rules of one robots.txt that apply to our user agent.
+struct RobotsRules {
+    /// (allow, path pattern)
+    rules: Vec<(bool, String)>,
+    crawl_delay: Option<Duration>,
+    expires: Instant
+}
+
+/// RobotsTxt makes scrapers comply with robots.txt. <br>
+/// Each host's robots.txt is fetched on the first navigation to it and cached, and every navigation is checked
+/// against the rules of user_agent: disallowed URLs fail with Error::Disallowed without being fetched,
+/// and Crawl-delay spaces out the navigations to the host. <br>
+/// Scrapers built from the same builder share the same RobotsTxt, and so do the backends of a PipelineRunner.
+pub struct RobotsTxt {
+    user_agent: String,
+    client: Client,
+    hosts: Mutex<HashMap<String, Arc<RobotsRules>>>
+}
+
+impl RobotsTxt {
+
+    /// robots.txt files are downloaded through the first proxy, if any.
+    pub fn new(user_agent: &str, proxies: &[SimpleProxy]) -> Result<RobotsTxt> {
+
+        let mut client = Client::builder()
+            .timeout(Duration::from_secs(10))
+            .user_agent(user_agent);
+
+        if let Some(proxy) = proxies.first() {
+            client = client.proxy(proxy.to_reqwest_proxy()?);
+        }
+
+        let client = client.build().map_err(|e| Error::Launch(e.to_string()))?;
+
+        Ok(RobotsTxt { user_agent: user_agent.to_lowercase(), client, hosts: Mutex::default() })
+    }
+
+    pub fn user_agent(&self) -> &str {
+        &self.user_agent
+    }
+
+    /// Whether the robots.txt of url's host lets us fetch url. URLs without a host (e.g. file://) always are.
+    pub fn is_allowed(&self, url: &str) -> bool {
+
+        match (url::Url::parse(url), self.rules(url)) {
+            (Ok(parsed), Some(rules)) => rules.allows(&path_of(&parsed)),
+            _ => true
+        }
+    }
+
+    /// The Crawl-delay of url's host, if its robots.txt sets one.
+    pub fn crawl_delay(&self, url: &str) -> Option<Duration> {
+        self.rules(url).and_then(|r| r.crawl_delay)
+    }
+
+    /// It fails with Error::Disallowed if url is off limits, and makes host_limiter apply the host's Crawl-delay.
+    pub(crate) fn check(&self, url: &str, host_limiter: Option<&Arc<HostLimiter>>) -> Result<()> {
+
+        if !self.is_allowed(url) {
+            info!("[ROBOTS] {} is disallowed, skipping", url);
+            return Err(Error::Disallowed { url: url.to_string() });
+        }
+
+        if let (Some(limiter), Some(delay)) = (host_limiter, self.crawl_delay(url)) {
+            limiter.set_crawl_delay(url, delay);
+        }
+
+        Ok(())
+    }
+
+    fn rules(&self, url: &str) -> Option<Arc<RobotsRules>> {
+
+        let parsed = url::Url::parse(url).ok()?;
+        parsed.host_str()?;
+
+        let origin = parsed.origin().ascii_serialization();
+
+        if let Some(rules) = self.lock().get(&origin).filter(|r| r.expires > Instant::now()) {
+            return Some(rules.clone());
+        }
+
+        // Fetched without holding the lock, so other hosts aren't held up meanwhile.
+        let rules = Arc::new(self.fetch(&origin));
+        self.lock().insert(origin, rules.clone());
+
+        Some(rules)
+    }
+
+    fn fetch(&self, origin: &str) -> RobotsRules {
+
+        let robots_url = format!("{}/robots.txt", origin);
+
+        match self.client.get(&robots_url).send() {
+            Ok(resp) if resp.status().is_success() => {
+                let content = resp.text().unwrap_or_default();
+                info!("[ROBOTS] Fetched {}", robots_url);
+
+                RobotsRules::parse(&content, &self.user_agent)
+            },
+            // No robots.txt: everything is allowed.
+            Ok(resp) if resp.status().is_client_error() => RobotsRules::allow_all(),
+            Ok(resp) => {
+                warn!("[ROBOTS] {} answered HTTP {}, keeping off the host for now", robots_url, resp.status());
+                RobotsRules::disallow_all()
+            },
+            Err(e) => {
+                warn!("[ROBOTS] Couldn't fetch {}, keeping off the host for now: {}", robots_url, e);
+                RobotsRules::disallow_all()
+            }
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Arc<RobotsRules>>> {
+        self.hosts.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl RobotsRules {
+
+    fn allow_all() -> RobotsRules {
+        RobotsRules { rules: vec![], crawl_delay: None, expires: Instant::now() + ROBOTS_TTL }
+    }
+
+    fn disallow_all() -> RobotsRules {
+        RobotsRules { rules: vec![(false, "/".to_string())], crawl_delay: None, expires: Instant::now() + UNREACHABLE_TTL }
+    }
+
+    /// It keeps the groups naming user_agent, or the * groups if none does.
+    fn parse(content: &str, user_agent: &str) -> RobotsRules {
+
+        struct Group {
+            agents: Vec<String>,
+            rules: Vec<(bool, String)>,
+            crawl_delay: Option<Duration>
+        }
+
+        let mut groups: Vec<Group> = vec![];
+        let mut in_rules = false;
+
+        for line in content.lines() {
+
+            let line = line.split('#').next().unwrap_or_default().trim();
+
+            let (key, value) = match line.split_once(':') {
+                Some((key, value)) => (key.trim().to_lowercase(), value.trim()),
+                None => continue
+            };
+
+            if key == "user-agent" {
+                // A User-agent line after some rules starts a new group.
+                if in_rules || groups.is_empty() {
+                    groups.push(Group { agents: vec![], rules: vec![], crawl_delay: None });
+                    in_rules = false;
+                }
+
+                groups.last_mut().unwrap().agents.push(value.to_lowercase());
+                continue;
+            }
+
+            let group = match groups.last_mut() {
+                Some(group) => group,
+                None => continue
+            };
+
+            // Other records, e.g. Sitemap, belong to no group: they don't end the list of its agents.
+            match key.as_str() {
+                "allow" | "disallow" => {
+                    if !value.is_empty() {
+                        group.rules.push((key == "allow", value.to_string()));
+                    }
+                    in_rules = true;
+                },
+                "crawl-delay" => {
+                    group.crawl_delay = value.parse::<f64>().ok().filter(|d| (0.0..86400.0).contains(d)).map(Duration::from_secs_f64);
+                    in_rules = true;
+                },
+                _ => ()
+            }
+        }
+
+        // Groups name product tokens, e.g. mybot for mybot/1.0.
+        let token = |agent: &str| agent.split('/').next().unwrap_or_default().trim().to_lowercase();
+
+        let named = |g: &&Group| g.agents.iter().any(|a| a != "*" && token(a) == token(user_agent));
+        let wildcard = |g: &&Group| g.agents.iter().any(|a| a == "*");
+
+        let mut matching = groups.iter().filter(named).collect::<Vec<&Group>>();
+
+        if matching.is_empty() {
+            matching = groups.iter().filter(wildcard).collect();
+        }
+
+        RobotsRules {
+            rules: matching.iter().flat_map(|g| g.rules.clone()).collect(),
+            crawl_delay: matching.iter().filter_map(|g| g.crawl_delay).max(),
+            expires: Instant::now() + ROBOTS_TTL
+        }
+    }
+
+    /// The longest matching rule wins, Allow on ties. No matching rule means allowed.
+    fn allows(&self, path: &str) -> bool {
+
+        self.rules.iter()
+            .filter(|(_, pattern)| pattern_matches(pattern, path))
+            .max_by_key(|(allow, pattern)| (pattern.len(), *allow))
+            .is_none_or(|(allow, _)| *allow)
+    }
+}
+
+fn path_of(url: &url::Url) -> String {
+
+    match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string()
+    }
+}
+
+/// robots.txt patterns: a path prefix, where * matches anything and a final $ anchors the end.
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false)
+    };
+
+    let parts = pattern.split('*').collect::<Vec<&str>>();
+
+    if !path.starts_with(parts[0]) {
+        return false;
+    }
+
+    let mut pos = parts[0].len();
+
+    for (i, part) in parts.iter().enumerate().skip(1) {
+
+        if anchored && i == parts.len() - 1 {
+            return path[pos..].ends_with(part);
+        }
+
+        match path[pos..].find(part) {
+            Some(j) => pos += j + part.len(),
+            None => return false
+        }
+    }
+
+    !anchored || pos == path.len()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn allows(rules: &RobotsRules, url: &str) -> bool {
+        rules.allows(&path_of(&url::Url::parse(url).unwrap()))
+    }
+
+    #[test]
+    fn named_groups_win_over_the_wildcard() {
+
+        let content = "User-agent: *\nDisallow: /\n\nUser-agent: MyBot\nDisallow: /private\n";
+
+        let mine = RobotsRules::parse(content, "mybot");
+        assert!(allows(&mine, "https://a.com/public"));
+        assert!(!allows(&mine, "https://a.com/private/x"));
+
+        let other = RobotsRules::parse(content, "otherbot");
+        assert!(!allows(&other, "https://a.com/public"));
+    }
+
+    #[test]
+    fn product_tokens_ignore_versions() {
+
+        let content = "User-agent: mybot/2.0\nDisallow: /a\n\nUser-agent: *\nDisallow: /b\n";
+
+        let rules = RobotsRules::parse(content, "MyBot/1.0");
+        assert!(!allows(&rules, "https://a.com/a"));
+        assert!(allows(&rules, "https://a.com/b"));
+    }
+
+    #[test]
+    fn agents_of_a_group_share_its_rules() {
+
+        let content = "User-agent: a\nSitemap: https://a.com/sitemap.xml\nUser-agent: b\nDisallow: /x\n";
+
+        assert!(!allows(&RobotsRules::parse(content, "a"), "https://a.com/x"));
+        assert!(!allows(&RobotsRules::parse(content, "b"), "https://a.com/x"));
+    }
+
+    #[test]
+    fn longest_match_wins_allow_on_ties() {
+
+        let rules = RobotsRules::parse("User-agent: *\nDisallow: /shop\nAllow: /shop/public\nDisallow: /page\nAllow: /page\n", "bot");
+
+        assert!(!allows(&rules, "https://a.com/shop/cart"));
+        assert!(allows(&rules, "https://a.com/shop/public/1"));
+        assert!(allows(&rules, "https://a.com/page"));
+        assert!(allows(&rules, "https://a.com/other"));
+    }
+
+    #[test]
+    fn empty_disallow_allows_everything() {
+        assert!(allows(&RobotsRules::parse("User-agent: *\nDisallow:\n", "bot"), "https://a.com/x"));
+    }
+
+    #[test]
+    fn wildcards_and_anchors() {
+
+        assert!(pattern_matches("/*.pdf$", "/docs/a.pdf"));
+        assert!(!pattern_matches("/*.pdf$", "/docs/a.pdf?x=1"));
+        assert!(!pattern_matches("/*.pdf$", "/docs/a.pdfs"));
+        assert!(pattern_matches("/*.pdf", "/docs/a.pdfs"));
+        assert!(pattern_matches("/a*b*c", "/a-x-b-y-c-z"));
+        assert!(!pattern_matches("/a*b*c", "/a-x-c-y-b"));
+        assert!(pattern_matches("/exact$", "/exact"));
+        assert!(!pattern_matches("/exact$", "/exact/more"));
+        assert!(pattern_matches("*", "/anything"));
+    }
+
+    #[test]
+    fn queries_are_part_of_the_path() {
+
+        let rules = RobotsRules::parse("User-agent: *\nDisallow: /*?session=\nDisallow: /search?q=\n", "bot");
+
+        assert!(!allows(&rules, "https://a.com/list?session=1"));
+        assert!(!allows(&rules, "https://a.com/search?q=rust"));
+        assert!(allows(&rules, "https://a.com/search"));
+        assert!(allows(&rules, "https://a.com/list?page=2"));
+    }
+
+    #[test]
+    fn crawl_delays_out_of_range_are_ignored() {
+
+        let delay = |value: &str| RobotsRules::parse(&format!("User-agent: *\nCrawl-delay: {}\n", value), "bot").crawl_delay;
+
+        assert_eq!(delay("2"), Some(Duration::from_secs(2)));
+        assert_eq!(delay("0.5"), Some(Duration::from_millis(500)));
+        assert_eq!(delay("-1"), None);
+        assert_eq!(delay("86400"), None);
+        assert_eq!(delay("soon"), None);
+    }
+
+    #[test]
+    fn comments_and_rules_before_any_agent_are_ignored() {
+
+        let rules = RobotsRules::parse("Disallow: /\n# User-agent: bot\nUser-agent: * # everyone\nDisallow: /x # not x\n", "bot");
+
+        assert!(allows(&rules, "https://a.com/y"));
+        assert!(!allows(&rules, "https://a.com/x"));
+    }
+}
@@ -0,0 +1,161 @@
+use std::{collections::BTreeMap, fmt::Display, str::FromStr};
+
+use crate::error::{Error, Result};
+
+use super::record::Field;
+
+/// Scrape turns a type into records of a page, implemented by `#[derive(Scrape)]`, e.g.
+/// ```ignore
+/// #[derive(Scrape)]
+/// #[css("div.question")]
+/// struct Question {
+///     #[css("h3 a")]
+///     title: String,
+///     #[css("h3 a")]
+///     #[attr("href")]
+///     link: String,
+///     #[css("span.votes")]
+///     votes: Option<i64>,
+///     #[css("a.post-tag")]
+///     tags: Vec<String>
+/// }
+///
+/// let questions: Vec<Question> = scraper.try_navigate_to(url)?.try_scrape()?;
+/// ```
+///
+/// Every field needs a selector:
+/// ```compile_fail
+/// # use webscrape::Scrape;
+/// #[derive(Scrape)]
+/// struct Question {
+///     title: String
+/// }
+/// ```
+///
+/// Fields have names:
+/// ```compile_fail
+/// # use webscrape::Scrape;
+/// #[derive(Scrape)]
+/// struct Question(#[css("h3")] String);
+/// ```
+///
+/// A selector is either CSS or XPath:
+/// ```compile_fail
+/// # use webscrape::Scrape;
+/// #[derive(Scrape)]
+/// struct Question {
+///     #[css("h3")]
+///     #[xpath("//h3")]
+///     title: String
+/// }
+/// ```
+///
+/// A struct deriving Scrape can't be parsed with FromStr too, as both make it a ScrapeField:
+/// ```compile_fail
+/// # use webscrape::Scrape;
+/// #[derive(Scrape)]
+/// struct Tag {
+///     #[css("a")]
+///     name: String
+/// }
+///
+/// impl std::str::FromStr for Tag {
+///     type Err = String;
+///
+///     fn from_str(s: &str) -> Result<Tag, String> {
+///         Ok(Tag { name: s.to_string() })
+///     }
+/// }
+/// ```
+///
+/// Without FromStr, it's fine:
+/// ```
+/// # use webscrape::Scrape;
+/// #[derive(Scrape)]
+/// struct Tag {
+///     #[css("a")]
+///     name: String
+/// }
+///
+/// #[derive(Scrape)]
+/// #[css("div.question")]
+/// struct Question {
+///     #[css("h3")]
+///     title: String,
+///     #[css("div.tags")]
+///     tags: Vec<Tag>
+/// }
+/// ```
+pub trait Scrape: Sized {
+
+    /// The name of the type, for errors.
+    const NAME: &'static str;
+
+    /// The selector of the containers, one per value: the whole page if the type has none.
+    fn selector() -> String;
+
+    /// A field per struct field, evaluated relative to each container.
+    fn fields() -> BTreeMap<String, Field>;
+
+    /// It builds a value out of a record scraped with fields(). Values that don't parse fail with Error::Parse.
+    fn from_record(record: &serde_json::Value) -> Result<Self>;
+}
+
+/// What a struct field deriving Scrape can hold: a type parsed with FromStr, or a struct deriving Scrape itself.
+pub trait ScrapeField: Sized {
+
+    /// The fields of a struct, scraped relative to each match. None for values.
+    fn fields() -> BTreeMap<String, Field> {
+        BTreeMap::new()
+    }
+
+    /// It builds a value out of what a field matched, or tells why it can't.
+    fn from_value(value: &serde_json::Value) -> std::result::Result<Self, String>;
+}
+
+impl<T: FromStr> ScrapeField for T where T::Err: Display {
+
+    fn from_value(value: &serde_json::Value) -> std::result::Result<T, String> {
+
+        let text = match value {
+            serde_json::Value::String(text) => text.clone(),
+            serde_json::Value::Number(_) | serde_json::Value::Bool(_) => value.to_string(),
+            other => return Err(format!("expected a single value, found {}", other))
+        };
+
+        text.parse::<T>().map_err(|e| format!("{} (value: {})", e, text))
+    }
+}
+
+fn parse<T: ScrapeField>(value: &serde_json::Value, name: &str, field: &str) -> Result<T> {
+
+    T::from_value(value).map_err(|reason| Error::Parse { field: format!("{}.{}", name, field), reason })
+}
+
+/// The value of field, which must have matched.
+pub fn required<T: ScrapeField>(record: &serde_json::Value, name: &str, field: &str) -> Result<T> {
+
+    match record.get(field) {
+        None | Some(serde_json::Value::Null) => Err(Error::Parse { field: format!("{}.{}", name, field), reason: "nothing matched".to_string() }),
+        Some(value) => parse(value, name, field)
+    }
+}
+
+/// The value of field, None if it didn't match.
+pub fn optional<T: ScrapeField>(record: &serde_json::Value, name: &str, field: &str) -> Result<Option<T>> {
+
+    match record.get(field) {
+        None | Some(serde_json::Value::Null) => Ok(None),
+        Some(value) => parse(value, name, field).map(Some)
+    }
+}
+
+/// Every value of field, in page order.
+pub fn many<T: ScrapeField>(record: &serde_json::Value, name: &str, field: &str) -> Result<Vec<T>> {
+
+    match record.get(field) {
+        None | Some(serde_json::Value::Null) => Ok(vec![]),
+        Some(serde_json::Value::Array(values)) => values.iter().map(|value| parse(value, name, field)).collect(),
+        Some(value) => parse(value, name, field).map(|value| vec![value])
+    }
+}
@@ -0,0 +1,159 @@
+use std::fmt::Display;
+
+use sxd_xpath::Factory;
+
+use crate::error::{Error, Result};
+
+/// How a selector string is evaluated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SelectorKind {
+    Css,
+    XPath,
+
+    /// Elements with a text containing the given one, whitespace aside.
+    Text
+}
+
+/// Selector is a selector string, told apart by its css:, xpath: or text: prefix, e.g. xpath:(//a)[1] or text:Next page. <br>
+/// Without a prefix, it's an XPath if it starts like one (/, ./, ../, @, a parenthesis or a function call) or is ., a CSS selector otherwise.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Selector {
+    kind: SelectorKind,
+    expression: String
+}
+
+impl Selector {
+
+    pub fn parse(selector: &str) -> Selector {
+
+        match strip_prefix(selector) {
+            Some(selector) => selector,
+            None => {
+                // No CSS selector starts with a path, an attribute, a parenthesis or a function call, e.g. count(//a), or is a lone dot.
+                let function_call = selector.split_once('(')
+                    .is_some_and(|(function, _)| !function.is_empty() && function.chars().all(|c| c.is_ascii_alphabetic() || c == '-'));
+
+                let looks_like_xpath = function_call || selector == "." || ["/", "./", "../", "@", "("].iter().any(|start| selector.starts_with(start));
+                let kind = if looks_like_xpath { SelectorKind::XPath } else { SelectorKind::Css };
+
+                Selector { kind, expression: selector.to_string() }
+            }
+        }
+    }
+
+    /// Like parse, but a selector without a prefix is of the given kind, e.g. an XPath for find_elements_by_xpath. <br>
+    /// Unprefixed CSS selectors still go through the heuristic: none starts like an XPath.
+    pub fn parse_as(selector: &str, kind: SelectorKind) -> Selector {
+
+        match (strip_prefix(selector), kind) {
+            (Some(selector), _) => selector,
+            (None, SelectorKind::Css) => Selector::parse(selector),
+            (None, kind) => Selector { kind, expression: selector.to_string() }
+        }
+    }
+
+    pub fn css(expression: &str) -> Selector {
+        Selector { kind: SelectorKind::Css, expression: expression.to_string() }
+    }
+
+    pub fn xpath(expression: &str) -> Selector {
+        Selector { kind: SelectorKind::XPath, expression: expression.to_string() }
+    }
+
+    pub fn text(text: &str) -> Selector {
+        Selector { kind: SelectorKind::Text, expression: text.to_string() }
+    }
+
+    pub fn kind(&self) -> SelectorKind {
+        self.kind
+    }
+
+    /// The selector without its prefix.
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+
+    /// The XPath evaluating XPath and text selectors, None for CSS selectors. <br>
+    /// Text selectors look below the context node, so they work relative to a parent too.
+    pub fn to_xpath(&self) -> Option<String> {
+
+        match self.kind {
+            SelectorKind::Css => None,
+            SelectorKind::XPath => Some(self.expression.clone()),
+            SelectorKind::Text => {
+                let text = self.expression.split_whitespace().collect::<Vec<&str>>().join(" ");
+                Some(format!(".//*[text()[contains(normalize-space(.), {})]]", xpath_literal(&text)))
+            }
+        }
+    }
+
+    /// It checks that the selector parses.
+    pub fn check(&self) -> Result<()> {
+
+        let invalid = |reason: String| Error::InvalidSelector { selector: self.expression.clone(), reason };
+
+        match self.to_xpath() {
+            Some(xpath) => {
+                Factory::new()
+                    .build(&xpath)
+                    .map_err(|e| invalid(e.to_string()))?
+                    .ok_or_else(|| invalid("empty XPath".to_string()))?;
+            },
+            None => {
+                scraper::Selector::parse(&self.expression).map_err(|e| invalid(format!("{:?}", e)))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// It fails unless the selector is of the given kind, e.g. an XPath given to find_elements_by_css. <br>
+    /// Text selectors count as XPaths.
+    pub(crate) fn expect(&self, kind: SelectorKind) -> Result<&Selector> {
+
+        let found = match self.kind {
+            SelectorKind::Css => "a CSS selector",
+            _ => "an XPath"
+        };
+
+        match (kind, self.kind) {
+            (SelectorKind::Css, SelectorKind::Css) | (SelectorKind::XPath | SelectorKind::Text, SelectorKind::XPath | SelectorKind::Text) => Ok(self),
+            (SelectorKind::Css, _) => Err(Error::InvalidSelector { selector: self.expression.clone(), reason: format!("expected a CSS selector, found {}", found) }),
+            _ => Err(Error::InvalidSelector { selector: self.expression.clone(), reason: format!("expected an XPath, found {}", found) })
+        }
+    }
+}
+
+impl Display for Selector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+
+        match self.kind {
+            SelectorKind::Css => write!(f, "css:{}", self.expression),
+            SelectorKind::XPath => write!(f, "xpath:{}", self.expression),
+            SelectorKind::Text => write!(f, "text:{}", self.expression)
+        }
+    }
+}
+
+fn strip_prefix(selector: &str) -> Option<Selector> {
+
+    [("css:", SelectorKind::Css), ("xpath:", SelectorKind::XPath), ("text:", SelectorKind::Text)]
+        .into_iter()
+        .find_map(|(prefix, kind)| selector.strip_prefix(prefix).map(|expression| Selector { kind, expression: expression.trim().to_string() }))
+}
+
+/// It quotes text as an XPath string. XPath 1.0 has no escapes, so text with both kinds of quotes is concatenated.
+fn xpath_literal(text: &str) -> String {
+
+    if !text.contains('\'') {
+        return format!("'{}'", text);
+    }
+
+    if !text.contains('"') {
+        return format!("\"{}\"", text);
+    }
+
+    let parts = text.split('\'').map(|part| format!("'{}'", part)).collect::<Vec<String>>();
+
+    format!("concat({})", parts.join(", \"'\", "))
+}
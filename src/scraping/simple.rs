@@ -1,5 +1,461 @@
+use std::{collections::BTreeMap, path::PathBuf, rc::Rc, sync::Arc, time::Duration};
 
+use log::{info, warn};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use reqwest::blocking::Client;
+
+use crate::{proxy::SimpleProxy, error::{Error, Result}};
+
+use super::{Backend, ScrapingResult, save_elements, query, html::{HtmlDocument, Page}, rate_limit::{HostLimiter, RateLimit, is_throttling, parse_retry_after}, robots::RobotsTxt, extract::Extract, links::Links, record::Field, scrape::Scrape, table::Table, selector::Selector, transform::Transform};
+
+/// SimpleScraper is the browser-free counterpart of Scraper. <br>
+/// It fetches pages over plain HTTP and queries the static HTML, without running any JavaScript.
+/// Use it for static pages: it costs a single HTTP request per page.
 pub struct SimpleScraper {
+    pub proxy: Vec<SimpleProxy>,
+
+    pub default_timeout: u64,
+
+    /// One client per proxy, or a single direct client if no proxy is set.
+    clients: Vec<Client>,
+
+    current_url: Option<String>,
+
+    /// The current page, parsed by the first query on it.
+    page: Option<Page>,
+
+    /// What was scraped since the last collect.
+    scraped: ScrapingResult,
+
+    save_dir: String,
+
+    host_limiter: Option<Arc<HostLimiter>>,
+
+    robots: Option<Arc<RobotsTxt>>
+}
+
+/// Just a builder for the SimpleScraper struct. <br>
+/// You can use it to build new SimpleScraper instances.
+#[derive(Clone)]
+pub struct SimpleScraperBuilder {
+    pub proxies: Vec<SimpleProxy>,
+    pub default_timeout: u64,
+    pub save_dir: String,
+    pub host_limiter: Option<Arc<HostLimiter>>,
+    pub robots: Option<Arc<RobotsTxt>>
+}
+
+impl Default for SimpleScraperBuilder {
+    fn default() -> Self {
+
+        let save_dir = ".scraping_results/".to_string();
+        Self { proxies: vec![], default_timeout: 5, save_dir, host_limiter: None, robots: None }
+    }
+}
+
+impl SimpleScraperBuilder {
+
+    pub fn set_default_timeout(&mut self, default_timeout: u64) -> &mut SimpleScraperBuilder {
+        self.default_timeout = default_timeout;
+        self
+    }
+
+    pub fn set_proxies(&mut self, proxies: Vec<SimpleProxy>) -> &mut SimpleScraperBuilder {
+        self.proxies = proxies;
+        self
+    }
+
+    pub fn set_save_dir(&mut self, save_dir: String) -> &mut SimpleScraperBuilder {
+        self.save_dir = save_dir;
+        self
+    }
+
+    /// Scrapers built from now on share the per-host limits of rate_limit.
+    pub fn set_rate_limit(&mut self, rate_limit: RateLimit) -> &mut SimpleScraperBuilder {
+        self.host_limiter = Some(Arc::new(HostLimiter::new(rate_limit)));
+        self
+    }
+
+    /// Scrapers built from now on share the per-host limits of host_limiter, e.g. with Chrome Scrapers.
+    pub fn set_host_limiter(&mut self, host_limiter: Arc<HostLimiter>) -> &mut SimpleScraperBuilder {
+        self.host_limiter = Some(host_limiter);
+        self
+    }
+
+    /// Scrapers built from now on comply with robots.txt, as user_agent. See RobotsTxt.
+    pub fn set_robots_txt(&mut self, user_agent: &str) -> Result<&mut SimpleScraperBuilder> {
+        let robots = RobotsTxt::new(user_agent, &self.proxies)?;
+        Ok(self.set_robots(Arc::new(robots)))
+    }
+
+    /// Scrapers built from now on comply with robots.txt, sharing the cache of robots, e.g. with Chrome Scrapers.
+    pub fn set_robots(&mut self, robots: Arc<RobotsTxt>) -> &mut SimpleScraperBuilder {
+
+        // Crawl-delay needs a limiter to be applied.
+        if self.host_limiter.is_none() {
+            self.host_limiter = Some(Arc::new(HostLimiter::new(RateLimit::default())));
+        }
+
+        self.robots = Some(robots);
+        self
+    }
+
+    /// It materializes a new SimpleScraper instance with the provided properties.
+    ///
+    /// Panics if a proxy is malformed, see try_build for a fallible version.
+    pub fn build(&self) -> SimpleScraper {
+
+        match self.try_build() {
+            Ok(scraper) => scraper,
+            Err(e) => panic!("{}", e)
+        }
+    }
+
+    /// It materializes a new SimpleScraper instance with the provided properties, reporting malformed proxies.
+    pub fn try_build(&self) -> Result<SimpleScraper> {
+
+        std::fs::create_dir_all(&self.save_dir).unwrap_or(());
+
+        let timeout = Duration::from_secs(self.default_timeout);
+
+        let clients = match self.proxies.is_empty() {
+            true => vec![Client::builder().timeout(timeout).build().map_err(|e| Error::Launch(e.to_string()))?],
+            false => self.proxies.iter()
+                .map(|proxy| {
+                    Client::builder()
+                        .timeout(timeout)
+                        .proxy(proxy.to_reqwest_proxy()?)
+                        .build()
+                        .map_err(|e| Error::Proxy(e.to_string()))
+                })
+                .collect::<Result<Vec<Client>>>()?
+        };
+
+        Ok(SimpleScraper {
+            proxy: self.proxies.clone(),
+            default_timeout: self.default_timeout,
+            clients,
+            current_url: None,
+            page: None,
+            scraped: ScrapingResult::default(),
+            save_dir: self.save_dir.clone(),
+            host_limiter: self.host_limiter.clone(),
+            robots: self.robots.clone()
+        })
+    }
+}
+
+impl SimpleScraper {
+
+    pub fn navigate_to<S: AsRef<str> + Clone>(&mut self, url: S) -> &mut SimpleScraper {
+
+        if let Err(e) = self.try_navigate_to(url) {
+            warn!("{}", e);
+        }
+
+        self
+    }
+
+    /// It downloads the page, through a random proxy if any, and parses it. <br>
+    /// file:// URLs are read from disk.
+    pub fn try_navigate_to<S: AsRef<str> + Clone>(&mut self, url: S) -> Result<&mut SimpleScraper> {
+
+        let url = url.as_ref();
+        let t0 = std::time::Instant::now();
+        info!("[NAVIGATE_TO] Start");
+
+        self.current_url = None;
+        self.page = None;
+
+        let content = match url.strip_prefix("file://") {
+            Some(path) => std::fs::read_to_string(path)
+                .map_err(|e| Error::Navigation { url: url.to_string(), reason: e.to_string() })?,
+            None => self.fetch(url)?
+        };
+
+        self.page = Some(Page::new(content));
+        self.current_url = Some(url.to_string());
+
+        info!("[NAVIGATE_TO] DONE in {}", t0.elapsed().as_secs());
+
+        Ok(self)
+    }
+
+    fn fetch(&self, url: &str) -> Result<String> {
+
+        let mut rng: StdRng = SeedableRng::from_entropy();
+        let client = &self.clients[rng.gen_range(0..self.clients.len())];
+
+        let to_error = |e: reqwest::Error| {
+            if e.is_timeout() {
+                Error::Timeout { url: url.to_string() }
+            } else if e.is_connect() && !self.proxy.is_empty() {
+                Error::Proxy(format!("{}: {}", url, e))
+            } else {
+                Error::Navigation { url: url.to_string(), reason: e.to_string() }
+            }
+        };
+
+        if let Some(robots) = &self.robots {
+            robots.check(url, self.host_limiter.as_ref())?;
+        }
+
+        let _permit = self.host_limiter.as_ref().map(|l| l.acquire(url));
+
+        let resp = client.get(url).send().map_err(to_error)?;
+
+        let status = resp.status().as_u16();
+
+        if is_throttling(status) {
+
+            let retry_after = resp.headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(parse_retry_after);
+
+            if let (Some(limiter), Some(retry_after)) = (&self.host_limiter, retry_after) {
+                limiter.pause(url, retry_after);
+            }
+
+            return Err(Error::RateLimited { url: url.to_string(), status, retry_after });
+        }
+
+        if !resp.status().is_success() {
+            return Err(Error::Navigation { url: url.to_string(), reason: format!("HTTP {}", resp.status()) });
+        }
+
+        resp.text().map_err(to_error)
+    }
+
+    pub fn sleep(&self, seconds: u64) -> &SimpleScraper {
+        std::thread::sleep(std::time::Duration::from_secs(seconds));
+
+        self
+    }
+
+    pub fn collect(&mut self) -> ScrapingResult {
+        std::mem::take(&mut self.scraped)
+    }
+
+    pub fn find_elements_by_css<S: AsRef<str> + Clone>(&mut self, name: S, target: S) -> &mut SimpleScraper {
+
+        if let Err(e) = self.query_css(name.as_ref(), target.as_ref()) {
+            warn!("{}", e);
+        }
+
+        self
+    }
+
+    pub fn try_find_elements_by_css<S: AsRef<str> + Clone>(&mut self, name: S, target: S) -> Result<&mut SimpleScraper> {
+
+        query::css(self, name.as_ref(), target.as_ref())?;
+
+        Ok(self)
+    }
+
+    pub fn find_elements_by_xpath<S: AsRef<str> + Clone>(&mut self, name: S, target: S) -> &mut SimpleScraper {
+
+        if let Err(e) = self.query_xpath(name.as_ref(), target.as_ref()) {
+            warn!("{}", e);
+        }
+
+        self
+    }
+
+    pub fn try_find_elements_by_xpath<S: AsRef<str> + Clone>(&mut self, name: S, target: S) -> Result<&mut SimpleScraper> {
+
+        query::xpath(self, name.as_ref(), target.as_ref())?;
+
+        Ok(self)
+    }
+
+    pub fn find_elements_within<S: AsRef<str> + Clone>(&mut self, name: S, parent: S, target: S) -> &mut SimpleScraper {
+
+        if let Err(e) = self.query_within(name.as_ref(), parent.as_ref(), target.as_ref()) {
+            warn!("{}", e);
+        }
+
+        self
+    }
+
+    /// It finds the elements matching target relative to each element matching parent, e.g. ./span for the spans of each parent. <br>
+    /// Both are selectors of any kind, see Selector.
+    pub fn try_find_elements_within<S: AsRef<str> + Clone>(&mut self, name: S, parent: S, target: S) -> Result<&mut SimpleScraper> {
+
+        query::within(self, name.as_ref(), parent.as_ref(), target.as_ref())?;
+
+        Ok(self)
+    }
+
+    pub fn extract(&mut self, name: &str, parent: Option<&str>, target: &str, extract: &Extract, transforms: &[Transform]) -> &mut SimpleScraper {
+
+        if let Err(e) = self.query_extract(name, parent, target, extract, transforms) {
+            warn!("{}", e);
+        }
+
+        self
+    }
+
+    /// It finds the elements matching target, relative to the elements matching parent if any, and keeps what extract says of each, e.g. attr:href. <br>
+    /// Unless extract keeps elements, the results are stored as values, after going through transforms if any: elements are then kept as text. <br>
+    /// Values a transform fails on become null, and are reported in the failures of ScrapingResult.
+    pub fn try_extract(&mut self, name: &str, parent: Option<&str>, target: &str, extract: &Extract, transforms: &[Transform]) -> Result<&mut SimpleScraper> {
+
+        query::extract(self, name, parent, target, extract, transforms)?;
+
+        Ok(self)
+    }
+
+    pub fn find_links(&mut self, name: &str, parent: Option<&str>, target: &str, links: &Links) -> &mut SimpleScraper {
+
+        if let Err(e) = self.query_links(name, parent, target, links) {
+            warn!("{}", e);
+        }
+
+        self
+    }
+
+    /// It finds the absolute URLs the elements matching target link to, relative to the elements matching parent if any, and keeps the ones links allows. <br>
+    /// Elements give their href, and XPaths like //a/@href their values. URLs are stored as values.
+    pub fn try_find_links(&mut self, name: &str, parent: Option<&str>, target: &str, links: &Links) -> Result<&mut SimpleScraper> {
+
+        query::links(self, name, parent, target, links)?;
+
+        Ok(self)
+    }
+
+    pub fn find_table(&mut self, name: &str, parent: Option<&str>, target: &str, table: &Table) -> &mut SimpleScraper {
+
+        if let Err(e) = self.query_table(name, parent, target, table) {
+            warn!("{}", e);
+        }
+
+        self
+    }
+
+    /// It turns the tables matching target, relative to the elements matching parent if any, into a record per body row keyed by header cells. <br>
+    /// Records are stored as values, see Table.
+    pub fn try_find_table(&mut self, name: &str, parent: Option<&str>, target: &str, table: &Table) -> Result<&mut SimpleScraper> {
+
+        query::table(self, name, parent, target, table)?;
+
+        Ok(self)
+    }
+
+    pub fn find_metadata(&mut self, name: &str) -> &mut SimpleScraper {
+
+        if let Err(e) = self.try_find_metadata(name) {
+            warn!("{}", e);
+        }
+
+        self
+    }
+
+    /// It stores under name the structured data of the page: JSON-LD, microdata, RDFa, OpenGraph and Twitter tags, canonical URL and title.
+    pub fn try_find_metadata(&mut self, name: &str) -> Result<&mut SimpleScraper> {
+
+        query::metadata(self, name)?;
+
+        Ok(self)
+    }
+
+    pub fn find_content(&mut self, name: &str, parent: Option<&str>, target: Option<&str>) -> &mut SimpleScraper {
+
+        if let Err(e) = self.query_content(name, parent, target) {
+            warn!("{}", e);
+        }
+
+        self
+    }
+
+    /// It stores under name the readable content, as Markdown and plain text, of each element matching target, relative to the elements matching parent if any. <br>
+    /// Without a target, it's the main article of the page, found as readability does. Contents are stored as values, see Content.
+    pub fn try_find_content(&mut self, name: &str, parent: Option<&str>, target: Option<&str>) -> Result<&mut SimpleScraper> {
+
+        query::content(self, name, parent, target)?;
+
+        Ok(self)
+    }
+
+    pub fn find_records(&mut self, name: &str, parent: Option<&str>, target: &str, fields: &BTreeMap<String, Field>) -> &mut SimpleScraper {
+
+        if let Err(e) = self.query_records(name, parent, target, fields) {
+            warn!("{}", e);
+        }
+
+        self
+    }
+
+    /// It builds a record of fields for each element matching target, relative to the elements matching parent if any, see Field. <br>
+    /// Records are stored as values, e.g. {"title": "...", "votes": 3}.
+    pub fn try_find_records(&mut self, name: &str, parent: Option<&str>, target: &str, fields: &BTreeMap<String, Field>) -> Result<&mut SimpleScraper> {
+
+        query::records(self, name, parent, target, fields)?;
+
+        Ok(self)
+    }
+
+    /// Like try_scrape, but logging failures and returning no value instead.
+    pub fn scrape<T: Scrape>(&mut self) -> Vec<T> {
+
+        match self.try_scrape() {
+            Ok(values) => values,
+            Err(e) => {
+                warn!("{}", e);
+                vec![]
+            }
+        }
+    }
+
+    /// It scrapes a T out of each of its containers on the current page, see Scrape. None is found if no container matches. <br>
+    /// Values aren't stored: they're returned, typed.
+    pub fn try_scrape<T: Scrape>(&mut self) -> Result<Vec<T>> {
+        query::scrape(self)
+    }
+
+    pub fn save(&self, targets: &[String], flatten: &bool ) {
+
+        if let Err(e) = self.try_save(targets, flatten) {
+            warn!("{}", e);
+        }
+    }
+
+    /// It writes the given targets as JSON in the save directory, returning the path of the written file.
+    pub fn try_save(&self, targets: &[String], flatten: &bool ) -> Result<PathBuf> {
+
+        self.try_save_as(targets, flatten, None)
+    }
+
+    /// Like try_save, but into save_path, relative to the save directory. $URL$ in save_path is replaced by the name taken from the URL.
+    pub fn try_save_as(&self, targets: &[String], flatten: &bool, save_path: Option<&str>) -> Result<PathBuf> {
+
+        save_elements(&self.scraped.elements, &self.scraped.values, &self.scraped.metadata, self.current_url.as_ref(), &self.save_dir, targets, flatten, save_path)
+    }
+}
+
+impl Backend for SimpleScraper {
+
+    fn name(&self) -> &'static str {
+        "http"
+    }
+
+    fn navigate(&mut self, url: &str) -> Result<()> {
+        self.try_navigate_to(url).map(|_| ())
+    }
+
+    fn current_url(&self) -> Option<String> {
+        self.current_url.clone()
+    }
+
+    fn document(&mut self, _name: &str, _wait_for: Option<&Selector>) -> Result<Rc<HtmlDocument>> {
+        Ok(self.page.as_ref().ok_or(Error::NotNavigated)?.document())
+    }
+
+    fn scraped(&mut self) -> &mut ScrapingResult {
+        &mut self.scraped
+    }
 
-    
-}
\ No newline at end of file
+    fn save(&self, targets: &[String], flatten: bool, save_path: Option<&str>) -> Result<PathBuf> {
+        self.try_save_as(targets, &flatten, save_path)
+    }
+}
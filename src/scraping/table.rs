@@ -0,0 +1,324 @@
+use std::collections::{HashMap, HashSet};
+
+use scraper::ElementRef;
+use serde::{Deserialize, Serialize};
+
+use super::html::Matches;
+
+/// Table turns the `<table>` elements of a target into records, one per body row, keyed by header cells, e.g. `table: {}`. <br>
+/// Header rows are the `<thead>` ones, or else the leading rows made of `<th>` cells only.
+/// Spanning cells fill every row and column they span, and stacked header rows join their texts with " / ", e.g. Price / USD.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Table {
+
+    /// How many leading rows are headers, overriding thead and th. 0 keys cells by column, as column_1, column_2...
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header_rows: Option<usize>
+}
+
+/// A cell of the grid, spans expanded.
+#[derive(Clone)]
+struct Cell {
+    text: String,
+    header: bool
+}
+
+/// A row, as found in the table.
+struct Row<'a> {
+    cells: Vec<ElementRef<'a>>,
+    in_head: bool
+}
+
+impl Table {
+
+    pub fn new() -> Table {
+        Table::default()
+    }
+
+    pub fn set_header_rows(&mut self, header_rows: usize) -> &mut Self {
+        self.header_rows = Some(header_rows);
+        self
+    }
+
+    /// The records of every table among what a target matched, in order. Elements that aren't tables give the tables they contain.
+    pub(crate) fn collect(&self, matches: Matches) -> Vec<serde_json::Value> {
+
+        let Matches::Elements(elements) = matches else {
+            return vec![];
+        };
+
+        elements.iter()
+            .flat_map(|el| match el.value().name() {
+                "table" => vec![*el],
+                _ => el.descendants().filter_map(ElementRef::wrap).filter(|d| d.value().name() == "table").collect()
+            })
+            .flat_map(|table| self.records(table))
+            .collect()
+    }
+
+    fn records(&self, table: ElementRef) -> Vec<serde_json::Value> {
+
+        let rows = rows(table);
+        let grid = grid(&rows);
+
+        let header_rows = self.header_rows.unwrap_or_else(|| {
+            let head = rows.iter().take_while(|row| row.in_head).count();
+            match head {
+                0 => grid.iter().take_while(|row| !row.is_empty() && row.iter().all(|cell| cell.as_ref().is_some_and(|c| c.header))).count(),
+                head => head
+            }
+        }).min(grid.len());
+
+        let columns = grid.iter().map(Vec::len).max().unwrap_or(0);
+        let keys = keys(&grid[..header_rows], columns);
+
+        grid[header_rows..].iter()
+            .filter(|row| !row.is_empty())
+            .map(|row| {
+                let record = keys.iter()
+                    .enumerate()
+                    .map(|(i, key)| {
+                        let value = row.get(i).and_then(Option::as_ref).map_or(serde_json::Value::Null, |cell| serde_json::Value::String(cell.text.clone()));
+                        (key.clone(), value)
+                    })
+                    .collect();
+
+                serde_json::Value::Object(record)
+            })
+            .collect()
+    }
+}
+
+/// The rows of table, nested tables aside: direct ones, and the ones of its thead, tbody and tfoot.
+fn rows(table: ElementRef) -> Vec<Row> {
+
+    fn row(tr: ElementRef, in_head: bool) -> Row {
+        Row { cells: tr.children().filter_map(ElementRef::wrap).filter(|c| matches!(c.value().name(), "td" | "th")).collect(), in_head }
+    }
+
+    let mut rows = vec![];
+
+    for child in table.children().filter_map(ElementRef::wrap) {
+        match child.value().name() {
+            "tr" => rows.push(row(child, false)),
+            section @ ("thead" | "tbody" | "tfoot") => {
+                for tr in child.children().filter_map(ElementRef::wrap).filter(|c| c.value().name() == "tr") {
+                    rows.push(row(tr, section == "thead"));
+                }
+            },
+            _ => ()
+        }
+    }
+
+    rows
+}
+
+/// The cells of every row by column, spans expanded: a cell spanning 2 rows shows up in both.
+fn grid(rows: &[Row]) -> Vec<Vec<Option<Cell>>> {
+
+    let mut grid: Vec<Vec<Option<Cell>>> = vec![vec![]; rows.len()];
+
+    for (r, row) in rows.iter().enumerate() {
+
+        let mut column = 0;
+
+        for el in &row.cells {
+
+            // Columns taken by cells spanning from the rows above.
+            while grid[r].get(column).is_some_and(Option::is_some) {
+                column += 1;
+            }
+
+            let span = |attr: &str| el.value().attr(attr).and_then(|v| v.trim().parse::<usize>().ok()).filter(|n| *n > 0).unwrap_or(1);
+
+            // Spans stop at the edge of the table, and missing or invalid ones count as 1.
+            let colspan = span("colspan").min(1000);
+            let rowspan = span("rowspan").min(rows.len() - r);
+
+            let cell = Cell { text: el.text().collect::<String>().split_whitespace().collect::<Vec<&str>>().join(" "), header: el.value().name() == "th" };
+
+            for spanned in grid.iter_mut().skip(r).take(rowspan) {
+                if spanned.len() < column + colspan {
+                    spanned.resize(column + colspan, None);
+                }
+                for slot in spanned.iter_mut().skip(column).take(colspan) {
+                    *slot = Some(cell.clone());
+                }
+            }
+
+            column += colspan;
+        }
+    }
+
+    grid
+}
+
+/// A key per column: the distinct texts of its header cells, top to bottom, made unique with a _2, _3... suffix. <br>
+/// Suffixes skip the texts of other headers, so a repeated Name next to a Name_2 header becomes Name_3.
+fn keys(headers: &[Vec<Option<Cell>>], columns: usize) -> Vec<String> {
+
+    let texts: Vec<String> = (0..columns)
+        .map(|i| {
+            let mut parts: Vec<&str> = vec![];
+
+            // Spanning header cells repeat down their column: each text counts once.
+            for cell in headers.iter().filter_map(|row| row.get(i).and_then(Option::as_ref)) {
+                if !cell.text.is_empty() && parts.last() != Some(&cell.text.as_str()) {
+                    parts.push(&cell.text);
+                }
+            }
+
+            match parts.is_empty() {
+                true => format!("column_{}", i + 1),
+                false => parts.join(" / ")
+            }
+        })
+        .collect();
+
+    let taken: HashSet<&String> = texts.iter().collect();
+    let mut used: HashSet<String> = HashSet::new();
+    let mut counts: HashMap<&String, usize> = HashMap::new();
+
+    texts.iter()
+        .map(|text| {
+            let mut key = text.clone();
+
+            if used.contains(&key) {
+                let count = counts.entry(text).or_insert(1);
+                key = loop {
+                    *count += 1;
+                    let suffixed = format!("{}_{}", text, count);
+                    if !taken.contains(&suffixed) && !used.contains(&suffixed) {
+                        break suffixed;
+                    }
+                };
+            }
+
+            used.insert(key.clone());
+            key
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use serde_json::json;
+
+    use crate::scraping::{html::HtmlDocument, selector::Selector};
+
+    use super::*;
+
+    fn records(table: &Table, html: &str) -> Vec<serde_json::Value> {
+
+        let document = HtmlDocument::parse(html);
+        let tables = document.select("tables", &Selector::parse("table")).unwrap();
+
+        table.collect(Matches::Elements(tables))
+    }
+
+    /// The keys of a record, in order.
+    fn columns(record: &serde_json::Value) -> Vec<&str> {
+        record.as_object().unwrap().keys().map(String::as_str).collect()
+    }
+
+    #[test]
+    fn records_keep_the_column_order() {
+
+        let rows = records(&Table::new(), "<table><tr><th>Name</th><th>USD</th><th>EUR</th></tr><tr><td>Apple</td><td>1</td><td>0.9</td></tr></table>");
+
+        assert_eq!(rows, vec![json!({"Name": "Apple", "USD": "1", "EUR": "0.9"})]);
+        assert_eq!(columns(&rows[0]), vec!["Name", "USD", "EUR"]);
+    }
+
+    #[test]
+    fn thead_rows_are_headers() {
+
+        // td cells in thead are headers too, and th cells in the body aren't.
+        let rows = records(&Table::new(), "<table><thead><tr><td>Name</td><td>Price</td></tr></thead><tbody><tr><th>Apple</th><td>1</td></tr></tbody></table>");
+
+        assert_eq!(rows, vec![json!({"Name": "Apple", "Price": "1"})]);
+    }
+
+    #[test]
+    fn leading_th_rows_are_headers() {
+
+        let rows = records(&Table::new(), "<table><tr><th>Name</th><th>Price</th></tr><tr><th>Apple</th><td>1</td></tr><tr><td>Pear</td><td>2</td></tr></table>");
+
+        assert_eq!(rows, vec![json!({"Name": "Apple", "Price": "1"}), json!({"Name": "Pear", "Price": "2"})]);
+    }
+
+    #[test]
+    fn spans_are_expanded() {
+
+        let html = "<table>
+            <tr><th>Name</th><th>Price</th><th>Stock</th></tr>
+            <tr><td rowspan=2>Apple</td><td colspan=2>n/a</td></tr>
+            <tr><td>1</td><td>5</td></tr>
+            <tr><td>Pear</td><td>2</td></tr>
+        </table>";
+
+        assert_eq!(records(&Table::new(), html), vec![
+            json!({"Name": "Apple", "Price": "n/a", "Stock": "n/a"}),
+            json!({"Name": "Apple", "Price": "1", "Stock": "5"}),
+            json!({"Name": "Pear", "Price": "2", "Stock": null})
+        ]);
+    }
+
+    #[test]
+    fn stacked_headers_are_joined() {
+
+        let html = "<table><thead>
+            <tr><th rowspan=2>Name</th><th colspan=2>Price</th></tr>
+            <tr><th>USD</th><th>EUR</th></tr>
+        </thead><tbody><tr><td>Apple</td><td>1</td><td>0.9</td></tr></tbody></table>";
+
+        let rows = records(&Table::new(), html);
+
+        assert_eq!(rows, vec![json!({"Name": "Apple", "Price / USD": "1", "Price / EUR": "0.9"})]);
+        assert_eq!(columns(&rows[0]), vec!["Name", "Price / USD", "Price / EUR"]);
+    }
+
+    #[test]
+    fn header_rows_override_the_table() {
+
+        let html = "<table><tr><th>Name</th><th>Price</th></tr><tr><td>Apple</td><td>1</td></tr></table>";
+
+        assert_eq!(records(Table::new().set_header_rows(0), html), vec![
+            json!({"column_1": "Name", "column_2": "Price"}),
+            json!({"column_1": "Apple", "column_2": "1"})
+        ]);
+        assert_eq!(records(Table::new().set_header_rows(2), html), Vec::<serde_json::Value>::new());
+    }
+
+    #[test]
+    fn nested_tables_are_their_own() {
+
+        let html = "<div><table><tr><th>A</th></tr><tr><td>1<table><tr><th>B</th></tr><tr><td>2</td></tr></table></td></tr></table></div>";
+        let document = HtmlDocument::parse(html);
+        let divs = document.select("divs", &Selector::parse("div")).unwrap();
+
+        assert_eq!(Table::new().collect(Matches::Elements(divs)), vec![json!({"A": "1B2"}), json!({"B": "2"})]);
+    }
+
+    fn headers(texts: &[&str]) -> Vec<Vec<Option<Cell>>> {
+        vec![texts.iter().map(|text| Some(Cell { text: text.to_string(), header: true })).collect()]
+    }
+
+    #[test]
+    fn repeated_headers_are_suffixed() {
+        assert_eq!(keys(&headers(&["Name", "Name", "", "Name"]), 4), vec!["Name", "Name_2", "column_3", "Name_3"]);
+    }
+
+    #[test]
+    fn suffixes_skip_other_headers() {
+        assert_eq!(keys(&headers(&["Name", "Name", "Name_2"]), 3), vec!["Name", "Name_3", "Name_2"]);
+        assert_eq!(keys(&headers(&["Name_2", "Name", "Name", "Name"]), 4), vec!["Name_2", "Name", "Name_3", "Name_4"]);
+    }
+
+    #[test]
+    fn empty_headers_are_keyed_by_column() {
+        assert_eq!(keys(&headers(&["column_2", ""]), 2), vec!["column_2", "column_2_2"]);
+    }
+}
@@ -0,0 +1,446 @@
+use std::fmt::Display;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use regex::Regex;
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::{self, MapAccess, Visitor}};
+use url::Url;
+
+/// A clean-up step applied to every value a target extracts, e.g. `parse_int` or `regex: '(\d+) votes'`. <br>
+/// Transforms are chained in order. Null values go through untouched, lists item by item.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Transform {
+    Trim,
+
+    /// Runs of whitespace become a single space, and the ends are trimmed.
+    NormalizeWhitespace,
+
+    Lowercase,
+
+    /// The first capture group of the first match, or the whole match without groups. No match is a failure.
+    Regex(Pattern),
+
+    /// Every match of pattern is replaced, $1 and the like standing for its groups.
+    Replace { pattern: Pattern, with: String },
+
+    /// An integer, e.g. 1,234 or -12.
+    ParseInt,
+
+    /// A number, e.g. 1,234.5 or 1e3.
+    ParseFloat,
+
+    /// A price like $1,234.50, 1.234,50 € or EUR 12, as {"amount": 1234.5, "currency": "$"}. The currency is null if none is given.
+    ParseCurrency,
+
+    /// A date, time or both in the given chrono format, e.g. %d %b %Y, as an ISO 8601 string.
+    Date(String),
+
+    /// A URL relative to the page, made absolute.
+    AbsoluteUrl
+}
+
+/// A regular expression, compiled when the pipeline is loaded.
+#[derive(Clone, Debug)]
+pub struct Pattern(Regex);
+
+impl Pattern {
+
+    pub fn new(pattern: &str) -> Result<Pattern, regex::Error> {
+        Regex::new(pattern).map(Pattern)
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.0.is_match(text)
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for Pattern {}
+
+impl Serialize for Pattern {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Pattern, D::Error> {
+
+        let pattern = String::deserialize(deserializer)?;
+        Pattern::new(&pattern).map_err(|e| de::Error::custom(format!("invalid regex {}: {}", pattern, e)))
+    }
+}
+
+/// A value a transform couldn't handle. The value is replaced by null, so the others keep their index.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TransformFailure {
+
+    /// The target, or target.field for record fields.
+    pub target: String,
+
+    /// Where the value is in the target, or which record it's in.
+    pub index: usize,
+
+    /// Where the value is in its list, if the value at index is one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub item: Option<usize>,
+
+    pub value: serde_json::Value,
+    pub reason: String
+}
+
+impl Display for TransformFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.item {
+            Some(item) => write!(f, "{}[{}][{}]: {} (value: {})", self.target, self.index, item, self.reason, self.value),
+            None => write!(f, "{}[{}]: {} (value: {})", self.target, self.index, self.reason, self.value)
+        }
+    }
+}
+
+/// It runs the chain on every value of a target, replacing the ones failing with null and reporting them in failures.
+pub(crate) fn transform_values(target: &str, values: Vec<serde_json::Value>, transforms: &[Transform], page_url: Option<&Url>, failures: &mut Vec<TransformFailure>) -> Vec<serde_json::Value> {
+
+    values.into_iter()
+        .enumerate()
+        .map(|(index, value)| transform_value(target, index, value, transforms, page_url, failures))
+        .collect()
+}
+
+/// Same as transform_values, for the value at index. A list is transformed item by item, only the failing items becoming null.
+pub(crate) fn transform_value(target: &str, index: usize, value: serde_json::Value, transforms: &[Transform], page_url: Option<&Url>, failures: &mut Vec<TransformFailure>) -> serde_json::Value {
+
+    if transforms.is_empty() {
+        return value;
+    }
+
+    match value {
+        serde_json::Value::Array(items) => serde_json::Value::Array(items.into_iter()
+            .enumerate()
+            .map(|(item, value)| transform_item(target, index, Some(item), value, transforms, page_url, failures))
+            .collect()),
+        value => transform_item(target, index, None, value, transforms, page_url, failures)
+    }
+}
+
+/// It runs the chain on a single value, reporting it in failures as the item of the value at index if it fails.
+fn transform_item(target: &str, index: usize, item: Option<usize>, value: serde_json::Value, transforms: &[Transform], page_url: Option<&Url>, failures: &mut Vec<TransformFailure>) -> serde_json::Value {
+
+    match transforms.iter().try_fold(value.clone(), |value, transform| transform.apply(value, page_url)) {
+        Ok(value) => value,
+        Err(reason) => {
+            failures.push(TransformFailure { target: target.to_string(), index, item, value, reason });
+            serde_json::Value::Null
+        }
+    }
+}
+
+impl Transform {
+
+    /// It transforms value, or tells why it can't. A list fails as a whole if any of its items does.
+    pub fn apply(&self, value: serde_json::Value, page_url: Option<&Url>) -> std::result::Result<serde_json::Value, String> {
+
+        let text = match value {
+            serde_json::Value::Null => return Ok(value),
+            serde_json::Value::Array(values) => return values.into_iter().map(|v| self.apply(v, page_url)).collect::<std::result::Result<Vec<_>, _>>().map(serde_json::Value::Array),
+            serde_json::Value::Object(_) => return Err("records can't be transformed: transform their fields instead".to_string()),
+            serde_json::Value::Number(n) if matches!(self, Transform::ParseInt | Transform::ParseFloat) => return Ok(serde_json::Value::Number(n)),
+            serde_json::Value::String(s) => s,
+            other => other.to_string()
+        };
+
+        let string = serde_json::Value::String;
+
+        match self {
+            Transform::Trim => Ok(string(text.trim().to_string())),
+            Transform::NormalizeWhitespace => Ok(string(text.split_whitespace().collect::<Vec<&str>>().join(" "))),
+            Transform::Lowercase => Ok(string(text.to_lowercase())),
+            Transform::Regex(pattern) => {
+                let captures = pattern.0.captures(&text).ok_or_else(|| format!("no match for regex {}", pattern.as_str()))?;
+                let found = captures.get(1).or_else(|| captures.get(0)).map_or("", |m| m.as_str());
+                Ok(string(found.to_string()))
+            },
+            Transform::Replace { pattern, with } => Ok(string(pattern.0.replace_all(&text, with.as_str()).to_string())),
+            Transform::ParseInt => {
+                strip_separators(&text)
+                    .and_then(|digits| digits.parse::<i64>().ok())
+                    .map(serde_json::Value::from)
+                    .ok_or_else(|| format!("{} is not an integer", text.trim()))
+            },
+            Transform::ParseFloat => {
+                strip_separators(&text)
+                    .and_then(|digits| digits.parse::<f64>().ok())
+                    .and_then(serde_json::Number::from_f64)
+                    .map(serde_json::Value::Number)
+                    .ok_or_else(|| format!("{} is not a number", text.trim()))
+            },
+            Transform::ParseCurrency => parse_currency(&text),
+            Transform::Date(format) => parse_date(text.trim(), format).map(string),
+            Transform::AbsoluteUrl => {
+                let base = page_url.ok_or_else(|| "no page URL to resolve against".to_string())?;
+                base.join(text.trim()).map(|url| string(url.to_string())).map_err(|e| format!("{} is not a URL: {}", text.trim(), e))
+            }
+        }
+    }
+}
+
+impl Display for Transform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+
+        match self {
+            Transform::Trim => write!(f, "trim"),
+            Transform::NormalizeWhitespace => write!(f, "normalize_whitespace"),
+            Transform::Lowercase => write!(f, "lowercase"),
+            Transform::Regex(pattern) => write!(f, "regex({})", pattern.as_str()),
+            Transform::Replace { pattern, with } => write!(f, "replace({}, {})", pattern.as_str(), with),
+            Transform::ParseInt => write!(f, "parse_int"),
+            Transform::ParseFloat => write!(f, "parse_float"),
+            Transform::ParseCurrency => write!(f, "parse_currency"),
+            Transform::Date(format) => write!(f, "date({})", format),
+            Transform::AbsoluteUrl => write!(f, "absolute_url")
+        }
+    }
+}
+
+/// Thousands separators and blanks go away, e.g. 1,234 becomes 1234. <br>
+/// A , that doesn't group the integer part by three digits, as in 1,5 or 12,34, makes it ambiguous: None.
+fn strip_separators(text: &str) -> Option<String> {
+
+    let compact = text.chars().filter(|c| !c.is_whitespace() && *c != '_').collect::<String>();
+
+    if !compact.contains(',') {
+        return Some(compact);
+    }
+
+    let (integer, rest) = compact.split_at(compact.find(['.', 'e', 'E']).unwrap_or(compact.len()));
+    let mut groups = integer.trim_start_matches(['-', '+']).split(',');
+
+    let first = groups.next().filter(|group| (1..=3).contains(&group.len()))?;
+    let grouped = first.chars().all(|c| c.is_ascii_digit())
+        && groups.all(|group| group.len() == 3 && group.chars().all(|c| c.is_ascii_digit()));
+
+    (grouped && !rest.contains(',')).then(|| format!("{}{}", integer.replace(',', ""), rest))
+}
+
+/// The decimal separator is the last . or , followed by at most two digits: 1.234,50 and 1,234.50 are both 1234.5.
+fn parse_currency(text: &str) -> std::result::Result<serde_json::Value, String> {
+
+    let invalid = || format!("{} is not an amount of money", text.trim());
+
+    let start = text.find(|c: char| c.is_ascii_digit()).ok_or_else(invalid)?;
+    let end = text.rfind(|c: char| c.is_ascii_digit()).ok_or_else(invalid)? + 1;
+
+    let negative = text[..start].contains('-') || text[..start].contains('(');
+    let currency = [&text[..start], &text[end..]]
+        .iter()
+        .map(|part| part.trim_matches(|c: char| c.is_whitespace() || c == '-' || c == '(' || c == ')'))
+        .find(|part| !part.is_empty())
+        .map(str::to_string);
+
+    let number = &text[start..end];
+
+    let decimal = number.rfind(['.', ',']).filter(|i| number.len() - i - 1 <= 2);
+
+    let digits = number.char_indices()
+        .filter_map(|(i, c)| match c {
+            _ if Some(i) == decimal => Some('.'),
+            '0'..='9' => Some(c),
+            '.' | ',' | ' ' | '\'' | '\u{a0}' => None,
+            _ => Some('?')
+        })
+        .collect::<String>();
+
+    let amount = digits.parse::<f64>().map_err(|_| invalid())?;
+    let amount = if negative { -amount } else { amount };
+
+    Ok(serde_json::json!({
+        "amount": amount,
+        "currency": currency
+    }))
+}
+
+/// It tries the format as a date and time with an offset, without one, then as a date alone.
+fn parse_date(text: &str, format: &str) -> std::result::Result<String, String> {
+
+    if let Ok(date) = DateTime::parse_from_str(text, format) {
+        return Ok(date.to_rfc3339());
+    }
+
+    if let Ok(date) = NaiveDateTime::parse_from_str(text, format) {
+        return Ok(date.format("%Y-%m-%dT%H:%M:%S").to_string());
+    }
+
+    NaiveDate::parse_from_str(text, format)
+        .map(|date| date.format("%Y-%m-%d").to_string())
+        .map_err(|e| format!("{} doesn't match the date format {}: {}", text, format, e))
+}
+
+impl<'de> Deserialize<'de> for Transform {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Transform, D::Error> {
+
+        const TRANSFORMS: &[&str] = &["trim", "normalize_whitespace", "lowercase", "regex", "replace", "parse_int", "parse_float", "parse_currency", "date", "absolute_url"];
+
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Replace {
+            pattern: Pattern,
+
+            #[serde(default)]
+            with: String
+        }
+
+        struct TransformVisitor;
+
+        impl<'de> Visitor<'de> for TransformVisitor {
+            type Value = Transform;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a transform, e.g. trim, or a map with one, e.g. regex: PATTERN")
+            }
+
+            fn visit_str<E: de::Error>(self, name: &str) -> std::result::Result<Transform, E> {
+
+                match name {
+                    "trim" => Ok(Transform::Trim),
+                    "normalize_whitespace" => Ok(Transform::NormalizeWhitespace),
+                    "lowercase" => Ok(Transform::Lowercase),
+                    "parse_int" => Ok(Transform::ParseInt),
+                    "parse_float" => Ok(Transform::ParseFloat),
+                    "parse_currency" => Ok(Transform::ParseCurrency),
+                    "absolute_url" => Ok(Transform::AbsoluteUrl),
+                    "regex" | "replace" | "date" => Err(E::custom(format!("{} needs an argument, e.g. {}: ...", name, name))),
+                    _ => Err(E::unknown_variant(name, TRANSFORMS))
+                }
+            }
+
+            fn visit_map<M: MapAccess<'de>>(self, mut map: M) -> std::result::Result<Transform, M::Error> {
+
+                let name = map.next_key::<String>()?.ok_or_else(|| de::Error::custom("empty transform"))?;
+
+                let transform = match name.as_str() {
+                    "regex" => Transform::Regex(map.next_value()?),
+                    "replace" => {
+                        let replace = map.next_value::<Replace>()?;
+                        Transform::Replace { pattern: replace.pattern, with: replace.with }
+                    },
+                    "date" => Transform::Date(map.next_value()?),
+                    _ => return Err(de::Error::unknown_variant(&name, &["regex", "replace", "date"]))
+                };
+
+                if map.next_key::<String>()?.is_some() {
+                    return Err(de::Error::custom("one transform per list item"));
+                }
+
+                Ok(transform)
+            }
+        }
+
+        deserializer.deserialize_any(TransformVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use serde_json::json;
+
+    use super::*;
+
+    fn apply(transform: Transform, value: serde_json::Value) -> std::result::Result<serde_json::Value, String> {
+        transform.apply(value, Url::parse("https://example.com/a/b").ok().as_ref())
+    }
+
+    #[test]
+    fn transforms_text() {
+
+        assert_eq!(apply(Transform::Trim, json!("  a b ")), Ok(json!("a b")));
+        assert_eq!(apply(Transform::NormalizeWhitespace, json!(" a \n  b ")), Ok(json!("a b")));
+        assert_eq!(apply(Transform::Lowercase, json!("AbC")), Ok(json!("abc")));
+        assert_eq!(apply(Transform::Regex(Pattern::new(r"(\d+) votes").unwrap()), json!("12 votes")), Ok(json!("12")));
+        assert_eq!(apply(Transform::Regex(Pattern::new(r"\d+").unwrap()), json!("a 12 b")), Ok(json!("12")));
+        assert!(apply(Transform::Regex(Pattern::new(r"\d+").unwrap()), json!("none")).is_err());
+        assert_eq!(apply(Transform::Replace { pattern: Pattern::new(r"(\w+)@").unwrap(), with: "$1 at ".to_string() }, json!("me@x")), Ok(json!("me at x")));
+        assert_eq!(apply(Transform::AbsoluteUrl, json!("../c?d=1")), Ok(json!("https://example.com/c?d=1")));
+        assert_eq!(apply(Transform::Trim, json!(null)), Ok(json!(null)));
+    }
+
+    #[test]
+    fn parses_numbers() {
+
+        assert_eq!(apply(Transform::ParseInt, json!("1,234")), Ok(json!(1234)));
+        assert_eq!(apply(Transform::ParseInt, json!(" -12 ")), Ok(json!(-12)));
+        assert_eq!(apply(Transform::ParseInt, json!("1 234 567")), Ok(json!(1234567)));
+        assert_eq!(apply(Transform::ParseInt, json!(7)), Ok(json!(7)));
+        assert!(apply(Transform::ParseInt, json!("12 votes")).is_err());
+
+        assert_eq!(apply(Transform::ParseFloat, json!("1,234.5")), Ok(json!(1234.5)));
+        assert_eq!(apply(Transform::ParseFloat, json!("1e3")), Ok(json!(1000.0)));
+        assert_eq!(apply(Transform::ParseFloat, json!("-1,000,000")), Ok(json!(-1000000.0)));
+    }
+
+    #[test]
+    fn ambiguous_commas_are_rejected() {
+
+        assert!(apply(Transform::ParseFloat, json!("1,5")).is_err());
+        assert!(apply(Transform::ParseFloat, json!("12,34")).is_err());
+        assert!(apply(Transform::ParseFloat, json!("1234,567")).is_err());
+        assert!(apply(Transform::ParseFloat, json!("1.5,0")).is_err());
+        assert!(apply(Transform::ParseInt, json!("1,23")).is_err());
+        assert!(apply(Transform::ParseInt, json!(",123")).is_err());
+    }
+
+    #[test]
+    fn parses_currencies() {
+
+        assert_eq!(parse_currency("$1,234.50"), Ok(json!({"amount": 1234.5, "currency": "$"})));
+        assert_eq!(parse_currency("1.234,50 €"), Ok(json!({"amount": 1234.5, "currency": "€"})));
+        assert_eq!(parse_currency("EUR 12"), Ok(json!({"amount": 12.0, "currency": "EUR"})));
+        assert_eq!(parse_currency("-$5"), Ok(json!({"amount": -5.0, "currency": "$"})));
+        assert_eq!(parse_currency("(3.10)"), Ok(json!({"amount": -3.1, "currency": null})));
+        assert_eq!(parse_currency("1.234"), Ok(json!({"amount": 1234.0, "currency": null})));
+        assert!(parse_currency("free").is_err());
+        assert!(parse_currency("1x2").is_err());
+    }
+
+    #[test]
+    fn parses_dates() {
+
+        assert_eq!(parse_date("3 Mar 2021", "%d %b %Y"), Ok("2021-03-03".to_string()));
+        assert_eq!(parse_date("2021-03-03 10:20", "%Y-%m-%d %H:%M"), Ok("2021-03-03T10:20:00".to_string()));
+        assert_eq!(parse_date("2021-03-03 10:20 +0100", "%Y-%m-%d %H:%M %z"), Ok("2021-03-03T10:20:00+01:00".to_string()));
+        assert!(parse_date("March 3", "%d %b %Y").is_err());
+    }
+
+    #[test]
+    fn lists_fail_item_by_item() {
+
+        let mut failures = vec![];
+        let value = transform_value("prices", 2, json!(["1", "x", "3"]), &[Transform::ParseInt], None, &mut failures);
+
+        assert_eq!(value, json!([1, null, 3]));
+        assert_eq!(failures.len(), 1);
+        assert_eq!((failures[0].index, failures[0].item), (2, Some(1)));
+        assert_eq!(failures[0].value, json!("x"));
+        assert_eq!(failures[0].to_string(), "prices[2][1]: x is not an integer (value: \"x\")");
+    }
+
+    #[test]
+    fn values_fail_one_by_one() {
+
+        let mut failures = vec![];
+        let values = transform_values("n", vec![json!("1"), json!("a"), json!(null)], &[Transform::Trim, Transform::ParseInt], None, &mut failures);
+
+        assert_eq!(values, vec![json!(1), json!(null), json!(null)]);
+        assert_eq!(failures.len(), 1);
+        assert_eq!((failures[0].index, failures[0].item), (1, None));
+    }
+}
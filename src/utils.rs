@@ -8,7 +8,7 @@ pub fn save_screenshot(img_data: &Vec<u8>, file_path: &Path) -> Result<bool, Str
 
     std::fs::write(file_path, img_data).unwrap();
 
-    return Ok(true);
+    Ok(true)
     
 }
 
@@ -0,0 +1,174 @@
+use std::{path::Path, str::FromStr};
+
+use webscrape::{Error, Scrape, SimpleScraper, SimpleScraperBuilder};
+
+const PAGE: &str = r#"<!DOCTYPE html><html><head><title>Questions</title></head><body>
+    <div class="question">
+        <h3><a href="/q/1">How do I parse HTML?</a></h3>
+        <span class="votes">12</span>
+        <a class="tag">rust</a><a class="tag">html</a>
+        <div class="owner"><a>alice</a><span class="reputation">1,024</span></div>
+    </div>
+    <div class="question">
+        <h3><a href="/q/2">Why is my scraper slow?</a></h3>
+        <div class="owner"><a>bob</a></div>
+    </div>
+</body></html>"#;
+
+/// A FromStr type of this crate, next to the structs deriving Scrape: both kinds of ScrapeField live together.
+#[derive(Debug, PartialEq)]
+struct Reputation(u32);
+
+impl FromStr for Reputation {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Reputation, Self::Err> {
+        s.replace(',', "").parse().map(Reputation)
+    }
+}
+
+#[derive(Scrape, Debug, PartialEq)]
+struct Owner {
+    #[css("a")]
+    name: String,
+
+    #[css("span.reputation")]
+    reputation: Option<Reputation>
+}
+
+#[derive(Scrape, Debug, PartialEq)]
+#[css("div.question")]
+struct Question {
+    #[css("h3 a")]
+    title: String,
+
+    #[css("h3 a")]
+    #[attr("href")]
+    link: String,
+
+    #[css("span.votes")]
+    votes: Option<i64>,
+
+    #[css("a.tag")]
+    tags: Vec<String>,
+
+    #[css("div.owner")]
+    owner: Owner
+}
+
+#[derive(Scrape, Debug, PartialEq)]
+struct Page {
+    #[xpath("//title")]
+    title: String,
+
+    #[css("div.question")]
+    questions: Vec<Owned>
+}
+
+#[derive(Scrape, Debug, PartialEq)]
+struct Owned {
+    #[css("div.owner a")]
+    owner: String
+}
+
+/// Only its errors are looked at, as the second question has no votes.
+#[allow(dead_code)]
+#[derive(Scrape)]
+#[css("div.question")]
+struct WithScore {
+    #[css("span.votes")]
+    score: i64
+}
+
+/// Only its errors are looked at, as titles aren't numbers.
+#[allow(dead_code)]
+#[derive(Scrape)]
+#[css("div.question")]
+struct WithAnswers {
+    #[css("span.answers")]
+    answers: Option<i64>,
+
+    #[css("h3 a")]
+    title: u32
+}
+
+fn scraper(name: &str, html: &str) -> SimpleScraper {
+
+    let path = std::env::temp_dir().join(format!("webscrape_derive_{}_{}.html", name, std::process::id()));
+    std::fs::write(&path, html).unwrap();
+
+    let mut scraper = SimpleScraperBuilder::default().build();
+    scraper.try_navigate_to(url(&path)).unwrap();
+
+    scraper
+}
+
+fn url(path: &Path) -> String {
+    format!("file://{}", path.display())
+}
+
+#[test]
+fn structs_are_scraped_per_container() {
+
+    let questions: Vec<Question> = scraper("containers", PAGE).try_scrape().unwrap();
+
+    assert_eq!(questions, vec![
+        Question {
+            title: "How do I parse HTML?".to_string(),
+            link: "/q/1".to_string(),
+            votes: Some(12),
+            tags: vec!["rust".to_string(), "html".to_string()],
+            owner: Owner { name: "alice".to_string(), reputation: Some(Reputation(1024)) }
+        },
+        Question {
+            title: "Why is my scraper slow?".to_string(),
+            link: "/q/2".to_string(),
+            votes: None,
+            tags: vec![],
+            owner: Owner { name: "bob".to_string(), reputation: None }
+        }
+    ]);
+}
+
+#[test]
+fn structs_without_a_selector_scrape_the_page() {
+
+    let pages: Vec<Page> = scraper("page", PAGE).try_scrape().unwrap();
+
+    assert_eq!(pages, vec![Page {
+        title: "Questions".to_string(),
+        questions: vec![Owned { owner: "alice".to_string() }, Owned { owner: "bob".to_string() }]
+    }]);
+}
+
+#[test]
+fn no_container_is_no_value() {
+
+    let questions: Vec<Question> = scraper("empty", "<html><body><p>Nothing here</p></body></html>").try_scrape().unwrap();
+
+    assert!(questions.is_empty());
+}
+
+#[test]
+fn missing_fields_fail() {
+
+    match scraper("missing", PAGE).try_scrape::<WithScore>() {
+        Err(Error::Parse { field, reason }) => {
+            assert_eq!(field, "WithScore.score");
+            assert_eq!(reason, "nothing matched");
+        },
+        other => panic!("expected a parse error, got {:?}", other.map(|values| values.len()))
+    }
+}
+
+#[test]
+fn unparsable_fields_fail() {
+
+    match scraper("unparsable", PAGE).try_scrape::<WithAnswers>() {
+        Err(Error::Parse { field, reason }) => {
+            assert_eq!(field, "WithAnswers.title");
+            assert!(reason.contains("How do I parse HTML?"), "{}", reason);
+        },
+        other => panic!("expected a parse error, got {:?}", other.map(|values| values.len()))
+    }
+}
@@ -0,0 +1,174 @@
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::{Attribute, Data, DeriveInput, Fields, GenericArgument, LitStr, PathArguments, Type, parse_macro_input};
+
+/// It implements webscrape::Scrape for a struct with named fields, each scraped from a record of its container. <br>
+/// The struct takes an optional `#[css("...")]` or `#[xpath("...")]`, the selector of its containers: without one, the whole page is the only container. <br>
+/// Each field takes one of them too, relative to the container, and optionally `#[attr("href")]` to keep an attribute rather than the text. <br>
+/// `Option<T>` fields are None when nothing matches, `Vec<T>` fields keep every match, and any other field must match. <br>
+/// T is parsed with FromStr, or is a struct deriving Scrape itself, scraped with the matches as its containers.
+#[proc_macro_derive(Scrape, attributes(css, xpath, attr))]
+pub fn derive_scrape(input: TokenStream) -> TokenStream {
+
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into()
+    }
+}
+
+/// How many matches a field keeps, told by its type.
+enum Cardinality {
+    One,
+    Optional,
+    Many
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(syn::Error::new_spanned(name, "Scrape can only be derived for structs with named fields"))
+        },
+        _ => return Err(syn::Error::new_spanned(name, "Scrape can only be derived for structs"))
+    };
+
+    let container = match selector(&input.attrs)? {
+        Some(selector) => selector,
+        None => LitStr::new("xpath:/*", Span::call_site())
+    };
+
+    let mut definitions = vec![];
+    let mut values = vec![];
+
+    for field in fields {
+
+        let ident = field.ident.as_ref().expect("named fields have a name");
+        let key = ident.to_string();
+
+        let field_selector = selector(&field.attrs)?.ok_or_else(|| syn::Error::new_spanned(ident, format!("{} needs a #[css(\"...\")] or #[xpath(\"...\")] selector", key)))?;
+
+        let (cardinality, inner) = cardinality(&field.ty);
+
+        let all = matches!(cardinality, Cardinality::Many);
+
+        let extract = match string_attr(&field.attrs, "attr")? {
+            Some(attr) => quote! { field.set_extract(::webscrape::Extract::Attr(#attr.to_string())); },
+            None => quote! {}
+        };
+
+        definitions.push(quote! {
+            {
+                let mut field = ::webscrape::Field::new(#field_selector);
+                field.set_all(#all);
+                #extract
+                for (name, nested) in <#inner as ::webscrape::ScrapeField>::fields() {
+                    field.add_field(&name, nested);
+                }
+                fields.insert(#key.to_string(), field);
+            }
+        });
+
+        let read = match cardinality {
+            Cardinality::One => quote! { ::webscrape::__private::required },
+            Cardinality::Optional => quote! { ::webscrape::__private::optional },
+            Cardinality::Many => quote! { ::webscrape::__private::many }
+        };
+
+        values.push(quote! {
+            #ident: #read::<#inner>(record, Self::NAME, #key)?
+        });
+    }
+
+    let struct_name = name.to_string();
+
+    Ok(quote! {
+        impl #impl_generics ::webscrape::Scrape for #name #ty_generics #where_clause {
+
+            const NAME: &'static str = #struct_name;
+
+            fn selector() -> ::std::string::String {
+                #container.to_string()
+            }
+
+            fn fields() -> ::std::collections::BTreeMap<::std::string::String, ::webscrape::Field> {
+                let mut fields = ::std::collections::BTreeMap::new();
+                #(#definitions)*
+                fields
+            }
+
+            fn from_record(record: &::webscrape::__private::serde_json::Value) -> ::webscrape::Result<Self> {
+                ::std::result::Result::Ok(#name {
+                    #(#values),*
+                })
+            }
+        }
+
+        impl #impl_generics ::webscrape::ScrapeField for #name #ty_generics #where_clause {
+
+            fn fields() -> ::std::collections::BTreeMap<::std::string::String, ::webscrape::Field> {
+                <Self as ::webscrape::Scrape>::fields()
+            }
+
+            fn from_value(value: &::webscrape::__private::serde_json::Value) -> ::std::result::Result<Self, ::std::string::String> {
+                <Self as ::webscrape::Scrape>::from_record(value).map_err(|e| e.to_string())
+            }
+        }
+    })
+}
+
+/// The selector given by #[css] or #[xpath], prefixed with its kind.
+fn selector(attrs: &[Attribute]) -> syn::Result<Option<LitStr>> {
+
+    let css = string_attr(attrs, "css")?;
+    let xpath = string_attr(attrs, "xpath")?;
+
+    match (css, xpath) {
+        (Some(css), None) => Ok(Some(LitStr::new(&format!("css:{}", css.value()), css.span()))),
+        (None, Some(xpath)) => Ok(Some(LitStr::new(&format!("xpath:{}", xpath.value()), xpath.span()))),
+        (Some(css), Some(_)) => Err(syn::Error::new_spanned(css, "give either #[css] or #[xpath], not both")),
+        (None, None) => Ok(None)
+    }
+}
+
+/// The string argument of #[name("...")], if there's one.
+fn string_attr(attrs: &[Attribute], name: &str) -> syn::Result<Option<LitStr>> {
+
+    let mut found = attrs.iter().filter(|attr| attr.path().is_ident(name));
+
+    let Some(attr) = found.next() else {
+        return Ok(None);
+    };
+
+    if let Some(duplicate) = found.next() {
+        return Err(syn::Error::new_spanned(duplicate, format!("#[{}] is given twice", name)));
+    }
+
+    attr.parse_args::<LitStr>().map(Some)
+}
+
+/// The cardinality of a field, and the type of each of its values: T for Option<T> and Vec<T>.
+fn cardinality(ty: &Type) -> (Cardinality, &Type) {
+
+    if let Type::Path(path) = ty {
+        if let Some(segment) = path.path.segments.last() {
+            if let PathArguments::AngleBracketed(args) = &segment.arguments {
+                if let (1, Some(GenericArgument::Type(inner))) = (args.args.len(), args.args.first()) {
+                    match segment.ident.to_string().as_str() {
+                        "Option" => return (Cardinality::Optional, inner),
+                        "Vec" => return (Cardinality::Many, inner),
+                        _ => ()
+                    }
+                }
+            }
+        }
+    }
+
+    (Cardinality::One, ty)
+}
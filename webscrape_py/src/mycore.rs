@@ -1,30 +1,25 @@
-use pyo3::prelude::*;
+use pyo3::{prelude::*, exceptions::PyRuntimeError};
 
-use webscrape::{self, ScraperBuilder, ScrapingPipeline};
+use webscrape::{self, ScrapingPipeline};
 #[pyfunction]
-pub fn run_pipeline(pipeline_file: &str) {
+pub fn run_pipeline(pipeline_file: &str) -> PyResult<()> {
     println!("Hello, world!");
 
     let pipeline_file = pipeline_file.to_owned();
 
-    let mut builder = ScraperBuilder::default();
-
-    let scraper = builder.set_headless(false).set_default_timeout(5).build();
-
     println!("--------------------");
 
-    let mut pipeline = ScrapingPipeline::from_file(&pipeline_file, scraper);
+    let mut pipeline = ScrapingPipeline::from_file(&pipeline_file)
+        .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
     
     print!("{}", pipeline);
 
     for step in &pipeline.get_steps() {
         println!("{}", step);
     }
-    let mut line = String::new();
-    print!("Run : ");
-    let b1 = std::io::stdin().read_line(&mut line).unwrap();
 
-    let res = pipeline.run();
+    let res = pipeline.run("DEFAULT")
+        .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
 
     println!("num: {}", &res.elements.len());
 
@@ -40,4 +35,6 @@ pub fn run_pipeline(pipeline_file: &str) {
             println!("--------------------");
         }
     }
+
+    Ok(())
 }